@@ -0,0 +1,293 @@
+use super::message::TymokOrTaxot;
+use super::player::{play_game_from_state, Player};
+use super::state::{self, HandNotResolved_};
+use super::{setup, Config, IfTaxot_, Victor};
+use cetkaik_fundamental::AbsoluteSide;
+use cetkaik_traits::CetkaikRepresentation;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+pub use super::win_probability::MarkovModel;
+
+pub enum Model<T: CetkaikRepresentation> {
+    Markov(MarkovModel),
+
+    /// Plays the game out `playouts` times from each branch, with players created by `new_player` on both sides.
+    /// ／各分岐から `playouts` 回、両側とも `new_player` で作ったプレイヤーで対局を最後まで指す。
+    MonteCarlo {
+        new_player: Box<dyn Fn() -> Box<dyn Player<T>>>,
+        playouts: u32,
+        seed: u64,
+    },
+}
+
+/// The estimate for a branch, from the point of view of the player who has formed the hand.
+/// ／ある分岐の見積もり。役を作ったプレイヤーから見たもの。
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct BranchEstimate {
+    /// Counting a draw as half a win.
+    /// ／引き分けは半勝とする。
+    pub win_probability: f64,
+
+    /// The expected value of (own final score − opponent's final score).
+    /// ／（自分の最終得点 − 相手の最終得点）の期待値。
+    pub expected_margin: f64,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct Advice {
+    /// The player who has formed the hand and has to choose.
+    /// ／役を作り、選択をしなければならないプレイヤー。
+    pub player: AbsoluteSide,
+    pub tymok: BranchEstimate,
+    pub taxot: BranchEstimate,
+
+    /// The branch with the higher win probability, or with the higher expected margin if they tie.
+    /// ／勝率の高い方。勝率が等しければ期待得点差が大きい方。
+    pub recommendation: TymokOrTaxot,
+}
+
+/// Estimates the two branches of `HandResolved_::HandExists`.
+/// ／`HandResolved_::HandExists` の二つの分岐を見積もる。
+///
+/// ```
+/// use cetkaik_full_state_transition::advisor::{Advisor, MarkovModel, Model};
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// // Under `strict_y1_huap1`, moving Tam2 back to where it was is a hand worth -3
+/// // `strict_y1_huap1` では皇を元の位置に戻すと-3点の役になる
+/// let config = Config::strict_y1_huap1();
+/// let advisor = Advisor::<CetkaikNaive>::new(config, Model::Markov(MarkovModel::default()));
+/// let probabilistic::Probabilistic::WhoGoesFirst { ia_first, .. } = initial_state() else {
+///     unreachable!()
+/// };
+/// let (_, candidates) = ia_first.get_candidates(config);
+/// let hand_not_resolved = candidates
+///     .into_iter()
+///     .find_map(|mv| match mv {
+///         message::PureMove__::NormalMove(
+///             msg @ message::NormalMove_::TamMoveNoStep { src, second_dest, .. },
+///         ) if src == second_dest => Some(
+///             apply_normal_move(&ia_first, msg, config)
+///                 .unwrap()
+///                 .choose_when_no_ciurl(),
+///         ),
+///         _ => None,
+///     })
+///     .unwrap();
+///
+/// // Banking a negative hand is worse than doubling the stake of an even game
+/// // 負の役を確定させるよりも、五分の勝負の賭け金を倍にする方がよい
+/// let advice = advisor.advise(&hand_not_resolved).unwrap();
+/// assert_eq!(advice.recommendation, message::TymokOrTaxot::Tymok);
+/// assert!(advice.taxot.win_probability < 0.5);
+/// assert!((advice.tymok.win_probability - 0.5).abs() < 1e-9);
+/// ```
+pub struct Advisor<T: CetkaikRepresentation> {
+    config: Config,
+    model: Model<T>,
+    setup: setup::Setup<T>,
+}
+
+impl<T: CetkaikRepresentation + Clone> Advisor<T> {
+    #[must_use]
+    pub fn new(config: Config, model: Model<T>) -> Self {
+        Self {
+            config,
+            model,
+            setup: setup::Setup::yhuap_initial(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_setup(self, setup: setup::Setup<T>) -> Self {
+        Self { setup, ..self }
+    }
+
+    /// `None` unless `state` resolves into `HandResolved_::HandExists`.
+    /// ／`state` が `HandResolved_::HandExists` にならなければ `None`。
+    #[must_use]
+    pub fn advise(&self, state: &HandNotResolved_<T>) -> Option<Advice> {
+        let state::HandResolved_::HandExists { if_tymok, if_taxot } =
+            super::resolve_with_setup(state, self.config, &self.setup)
+        else {
+            return None;
+        };
+        let player = state.whose_turn;
+
+        // (IA side's win probability, IA side's expected final score)
+        // （IA 側の勝率, IA 側の最終得点の期待値）
+        let (tymok, taxot) = match &self.model {
+            Model::Markov(model) => {
+                let of = |estimate: super::win_probability::MatchEstimate| {
+                    (
+                        estimate.win_probability(AbsoluteSide::IASide),
+                        estimate.expected_final_ia_score,
+                    )
+                };
+                (
+                    of(model.estimate(
+                        if_tymok.scores,
+                        if_tymok.rate,
+                        if_tymok.season,
+                        if_tymok.whose_turn,
+                    )),
+                    match &if_taxot {
+                        IfTaxot_::VictoriousSide(victor) => self.game_over(state, *victor),
+                        IfTaxot_::NextSeason(p) => p.clone().into_outcomes().into_iter().fold(
+                            (0.0, 0.0),
+                            |(w, s), (next, probability)| {
+                                let (dw, ds) = of(model.estimate_at_season_start(
+                                    next.scores,
+                                    Some(next.season),
+                                    Some(next.whose_turn),
+                                ));
+                                (probability.mul_add(dw, w), probability.mul_add(ds, s))
+                            },
+                        ),
+                    },
+                )
+            }
+            Model::MonteCarlo {
+                new_player,
+                playouts,
+                seed,
+            } => {
+                let play = |state: state::GroundState_<T>, seed| {
+                    let (record, victor) = play_game_from_state(
+                        &mut *new_player(),
+                        &mut *new_player(),
+                        self.config,
+                        &self.setup,
+                        state,
+                        seed,
+                    );
+                    (win_of_ia(victor), f64::from(record.final_ia_score))
+                };
+                let average = |results: Vec<(f64, f64)>| {
+                    let n = f64::from(*playouts).max(1.0);
+                    results
+                        .into_iter()
+                        .fold((0.0, 0.0), |(w, s), (dw, ds)| (w + dw / n, s + ds / n))
+                };
+                let tymok = average(
+                    (0..*playouts)
+                        .map(|k| play(if_tymok.clone(), seed + u64::from(k)))
+                        .collect(),
+                );
+                let taxot = match &if_taxot {
+                    IfTaxot_::VictoriousSide(victor) => self.game_over(state, *victor),
+                    IfTaxot_::NextSeason(p) => average(
+                        (0..*playouts)
+                            .map(|k| {
+                                let seed = seed + u64::from(k);
+                                let mut rng = StdRng::seed_from_u64(seed);
+                                let next = p.clone().choose_by_uniform_random_variable(rng.gen()).0;
+                                play(next, seed)
+                            })
+                            .collect(),
+                    ),
+                };
+                (tymok, taxot)
+            }
+        };
+
+        let from_player = |(win, final_ia_score): (f64, f64)| {
+            let ia_margin = 2.0 * final_ia_score - 40.0;
+            match player {
+                AbsoluteSide::IASide => BranchEstimate {
+                    win_probability: win,
+                    expected_margin: ia_margin,
+                },
+                AbsoluteSide::ASide => BranchEstimate {
+                    win_probability: 1.0 - win,
+                    expected_margin: -ia_margin,
+                },
+            }
+        };
+        let tymok = from_player(tymok);
+        let taxot = from_player(taxot);
+        let recommendation = if (tymok.win_probability, tymok.expected_margin)
+            > (taxot.win_probability, taxot.expected_margin)
+        {
+            TymokOrTaxot::Tymok
+        } else {
+            TymokOrTaxot::Taxot
+        };
+        Some(Advice {
+            player,
+            tymok,
+            taxot,
+            recommendation,
+        })
+    }
+
+    /// The value when taxot ends the game.
+    /// ／終季によってゲームが終わるときの値。
+    fn game_over(&self, state: &HandNotResolved_<T>, victor: Victor) -> (f64, f64) {
+        let final_ia_score = match super::scores_if_taxot(state, self.config) {
+            Some(Ok(scores)) => scores.ia(),
+            _ => match victor.0 {
+                Some(AbsoluteSide::IASide) => 40,
+                Some(AbsoluteSide::ASide) => 0,
+                None => 20,
+            },
+        };
+        (win_of_ia(victor), f64::from(final_ia_score))
+    }
+}
+
+const fn win_of_ia(victor: Victor) -> f64 {
+    match victor.0 {
+        Some(AbsoluteSide::IASide) => 1.0,
+        Some(AbsoluteSide::ASide) => 0.0,
+        None => 0.5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{position_with_a_winning_hand, WINNING_CAPTURE};
+    use crate::{apply_normal_move, message, player, search, MoveLimit, MoveLimitOutcome};
+    use cetkaik_fundamental::AbsoluteSide::IASide;
+
+    #[test]
+    fn test_advisor_banks_a_winning_hand() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        let config = Config::cerke_online_alpha().with_move_limit(Some(MoveLimit {
+            moves: 20,
+            outcome: MoveLimitOutcome::GameEndsInADraw,
+        }));
+        let search::Decision::Move(message::PureMove__::NormalMove(msg)) = WINNING_CAPTURE else {
+            unreachable!()
+        };
+        let hand_not_resolved = apply_normal_move(&position_with_a_winning_hand(), msg, config)
+            .unwrap()
+            .choose_when_no_ciurl();
+
+        for model in [
+            Model::Markov(MarkovModel::default()),
+            Model::MonteCarlo {
+                new_player: Box::new(|| {
+                    Box::new(player::RandomPlayer) as Box<dyn Player<CetkaikNaive>>
+                }),
+                playouts: 4,
+                seed: 0,
+            },
+        ] {
+            let advice = Advisor::new(config, model)
+                .advise(&hand_not_resolved)
+                .unwrap();
+            assert_eq!(advice.player, IASide);
+            assert_eq!(advice.recommendation, TymokOrTaxot::Taxot);
+
+            // 36 + 5 knocks A side out
+            // 36 + 5 で A 側の点が尽きる
+            assert!((advice.taxot.win_probability - 1.0).abs() < f64::EPSILON);
+            assert!((advice.taxot.expected_margin - 40.0).abs() < f64::EPSILON);
+            assert!(advice.tymok.win_probability < 1.0);
+        }
+    }
+}
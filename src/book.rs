@@ -0,0 +1,612 @@
+use super::message::{InfAfterStep_, NormalMove_, PureMove__};
+use super::player::GameRecord;
+use super::search::Decision;
+use super::{state, Config, Victor, COLORS, PROFESSIONS};
+use cetkaik_fundamental::AbsoluteSide::{self, ASide, IASide};
+use cetkaik_fundamental::{Color, Profession};
+use cetkaik_traits::{CetkaikRepresentation, IsAbsoluteField, IsBoard, IsPieceWithSide};
+use std::collections::HashMap;
+
+/// How often a move was played in a position of the book, and how those games ended for the player who made it.
+/// ／ある局面である手が指された回数と、その手を指した側にとってそれらの対局がどう終わったか。
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MoveStats {
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+}
+
+impl MoveStats {
+    /// Counts a draw as half a win. 0.5 if the move was never played.
+    /// ／引き分けは半分の勝ちとして数える。一度も指されていなければ0.5。
+    #[must_use]
+    pub fn win_rate(self) -> f64 {
+        if self.games == 0 {
+            return 0.5;
+        }
+        (f64::from(self.wins) + f64::from(self.draws) / 2.0) / f64::from(self.games)
+    }
+}
+
+/// A candidate move found in the book.
+/// ／定跡に見つかった候補手。
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct BookMove<Coord> {
+    pub mv: PureMove__<Coord>,
+    pub stats: MoveStats,
+
+    /// The share of `stats.games` among the book moves of the position, so that the weights sum to 1.
+    /// ／その局面の定跡手の中で `stats.games` が占める割合。重みの和は1になる。
+    pub weight: f64,
+}
+
+/// Moves played at the beginning of each season in a set of games, with how often they were played and how those games ended.
+///
+/// A position is looked up as seen by the player to move: a position with `ASide` to move is rotated by 180° and its sides swapped, so that both players share the book. Since this alone would leave the Io and the Nuak1 on column Z with the other color, they also swap colors (see `canonical_color`), so that a season opened by `IASide` and one opened by `ASide` are merged; the colors of the other pieces are kept as they are. Seasons, scores and rates are ignored as well, so the book applies to the opening of every season.
+/// Only games starting from `Setup::yhuap_initial` can be added.
+///
+/// ／複数の対局において各季節の序盤に指された手を、指された回数と対局の結果とともに集めたもの。
+///
+/// 局面は手番の側から見て引かれる。`ASide` の手番の局面は180°回転して両者を入れ替えるので、両者が同じ定跡を共有する。これだけでは Z 列の王と車の色が逆になるので、それらの色も入れ替え（`canonical_color` を参照）、`IASide` が始めた季節と `ASide` が始めた季節を一つにまとめる。それ以外の駒の色はそのまま保つ。季節・得点・レートも無視するので、定跡はどの季節の序盤にも当てはまる。
+/// 追加できるのは `Setup::yhuap_initial` から始まった対局のみ。
+///
+/// ```
+/// use cetkaik_full_state_transition::book::OpeningBook;
+/// use cetkaik_full_state_transition::player::{play_game, RandomPlayer};
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let config = Config::cerke_online_alpha();
+/// let mut book = OpeningBook::new();
+/// for seed in 0..2 {
+///     let (record, victor) = play_game::<CetkaikNaive>(&mut RandomPlayer, &mut RandomPlayer, config, seed);
+///     book.add_game::<CetkaikNaive>(&record, victor, 4).unwrap();
+/// }
+///
+/// let state = initial_state::<CetkaikNaive>().choose().0;
+/// let moves = book.lookup(&state, config);
+/// assert!(!moves.is_empty());
+/// assert!((moves.iter().map(|m| m.weight).sum::<f64>() - 1.0).abs() < 1e-9);
+///
+/// assert_eq!(OpeningBook::from_bytes(&book.to_bytes()), Ok(book));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpeningBook {
+    /// Moves use square indices as seen by the player to move (see `canonical_square`).
+    /// ／手のマスは手番の側から見た番号で表す（`canonical_square` を参照）。
+    positions: HashMap<u64, Vec<(PureMove__<u8>, MoveStats)>>,
+}
+
+const MAGIC: &[u8; 4] = b"CKOB";
+const VERSION: u32 = 1;
+
+impl OpeningBook {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of positions in the book.
+    /// ／定跡に含まれる局面の数。
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Adds the first `max_plies` moves of every season of `record`, which ended with `victor`. Passes are not recorded.
+    /// ／`victor` で終わった `record` の各季節の最初の `max_plies` 手を加える。パスは記録しない。
+    ///
+    /// # Errors
+    /// Fails without changing the book if `record` did not start from `Setup::yhuap_initial` or is inconsistent with `record.config`.
+    pub fn add_game<T: CetkaikRepresentation + Clone>(
+        &mut self,
+        record: &GameRecord<T::AbsoluteCoord>,
+        victor: Victor,
+        max_plies: u32,
+    ) -> Result<(), &'static str> {
+        let mut plies = vec![];
+        super::player::replay(
+            record,
+            &super::setup::Setup::yhuap_initial(),
+            |state: &state::GroundState_<T>, decision| {
+                if let Decision::Move(mv) = decision {
+                    if state.moves_in_this_season < max_plies {
+                        plies.push((
+                            position_key(state),
+                            canonical_drop(
+                                mv.map(|coord| canonical_square::<T>(coord, state.whose_turn)),
+                                state.whose_turn,
+                            ),
+                            state.whose_turn,
+                        ));
+                    }
+                }
+            },
+        )?;
+
+        for (key, mv, mover) in plies {
+            let moves = self.positions.entry(key).or_default();
+            let index = moves.iter().position(|(m, _)| *m == mv).unwrap_or_else(|| {
+                moves.push((mv, MoveStats::default()));
+                moves.len() - 1
+            });
+            let stats = &mut moves[index].1;
+            stats.games += 1;
+            match victor {
+                Victor(None) => stats.draws += 1,
+                Victor(Some(side)) if side == mover => stats.wins += 1,
+                Victor(Some(_)) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The book moves that are legal in `state`, most played first.
+    /// ／`state` で合法な定跡手。指された回数の多い順。
+    #[must_use]
+    pub fn lookup<T: CetkaikRepresentation + Clone>(
+        &self,
+        state: &state::GroundState_<T>,
+        config: Config,
+    ) -> Vec<BookMove<T::AbsoluteCoord>> {
+        let Some(moves) = self.positions.get(&position_key(state)) else {
+            return vec![];
+        };
+        let squares = squares::<T>();
+        let (hop1zuo1_candidates, candidates) = state.get_candidates(config);
+        let mut found: Vec<_> = moves
+            .iter()
+            .map(|&(mv, stats)| {
+                let mv = mv.map(|i| squares[canonical_index(usize::from(i), state.whose_turn)]);
+                (canonical_drop(mv, state.whose_turn), stats)
+            })
+            .filter(|(mv, _)| hop1zuo1_candidates.contains(mv) || candidates.contains(mv))
+            .collect();
+        found.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.games));
+
+        let total: u32 = found.iter().map(|(_, stats)| stats.games).sum();
+        found
+            .into_iter()
+            .map(|(mv, stats)| BookMove {
+                mv,
+                stats,
+                weight: f64::from(stats.games) / f64::from(total),
+            })
+            .collect()
+    }
+
+    /// Picks one of `lookup`'s moves at random in proportion to its weight. `None` if there is none.
+    /// ／`lookup` の手を重みに比例した確率で一つ選ぶ。無ければ `None`。
+    pub fn choose<T: CetkaikRepresentation + Clone>(
+        &self,
+        state: &state::GroundState_<T>,
+        config: Config,
+        rng: &mut impl rand::Rng,
+    ) -> Option<PureMove__<T::AbsoluteCoord>> {
+        let moves = self.lookup(state, config);
+        let mut r = rng.gen::<f64>();
+        for m in &moves {
+            if r < m.weight {
+                return Some(m.mv);
+            }
+            r -= m.weight;
+        }
+        moves.last().map(|m| m.mv)
+    }
+
+    /// Encodes the book in a compact binary format. Positions are sorted, so the same book always gives the same bytes.
+    ///
+    /// The format is little-endian: the magic `CKOB`, the version (u32) and the number of positions (u32), followed by the positions. A position is its key (u64) and the number of its moves (u16), followed by the moves. A move is 5 bytes (a tag and up to four squares, or a color and a profession), followed by its games, wins and draws (u32 each).
+    ///
+    /// ／定跡をコンパクトなバイナリ形式に符号化する。局面は整列するので、同じ定跡は常に同じバイト列になる。
+    ///
+    /// 形式はリトルエンディアンで、マジック `CKOB`・版（u32）・局面数（u32）に局面が続く。局面は鍵（u64）と手の数（u16）に手が続く。手は5バイト（タグと最大四つのマス、または色と職業）に対局数・勝ち数・引き分け数（それぞれ u32）が続く。
+    ///
+    /// # Panics
+    /// Panics if a position has more than 65535 moves.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut keys: Vec<_> = self.positions.keys().copied().collect();
+        keys.sort_unstable();
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(
+            u32::try_from(keys.len())
+                .expect("too many positions")
+                .to_le_bytes(),
+        );
+        for key in keys {
+            let moves = &self.positions[&key];
+            bytes.extend(key.to_le_bytes());
+            bytes.extend(
+                u16::try_from(moves.len())
+                    .expect("too many moves in a position")
+                    .to_le_bytes(),
+            );
+            for &(mv, stats) in moves {
+                bytes.extend(encode(mv));
+                bytes.extend(stats.games.to_le_bytes());
+                bytes.extend(stats.wins.to_le_bytes());
+                bytes.extend(stats.draws.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// # Errors
+    /// Fails if `bytes` is not in the format written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        let mut rest = bytes;
+        let mut take = |n: usize| -> Result<&[u8], &'static str> {
+            if rest.len() < n {
+                return Err("the opening book is truncated");
+            }
+            let (taken, remaining) = rest.split_at(n);
+            rest = remaining;
+            Ok(taken)
+        };
+        let u32_of = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+
+        if take(4)? != MAGIC {
+            return Err("not an opening book");
+        }
+        if u32_of(take(4)?) != VERSION {
+            return Err("unsupported version of the opening book");
+        }
+        let mut positions = HashMap::new();
+        for _ in 0..u32_of(take(4)?) {
+            let b = take(8)?;
+            let key = u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+            let b = take(2)?;
+            let count = u16::from_le_bytes([b[0], b[1]]);
+            let mut moves = Vec::with_capacity(usize::from(count));
+            for _ in 0..count {
+                let b = take(5)?;
+                let mv = decode([b[0], b[1], b[2], b[3], b[4]])?;
+                let b = take(12)?;
+                moves.push((
+                    mv,
+                    MoveStats {
+                        games: u32_of(&b[0..4]),
+                        wins: u32_of(&b[4..8]),
+                        draws: u32_of(&b[8..12]),
+                    },
+                ));
+            }
+            if positions.insert(key, moves).is_some() {
+                return Err("a position appears twice in the opening book");
+            }
+        }
+        if !rest.is_empty() {
+            return Err("trailing bytes after the opening book");
+        }
+        Ok(Self { positions })
+    }
+
+    /// # Errors
+    /// Fails if the file cannot be read or is malformed.
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// # Errors
+    /// Fails if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+}
+
+/// The 81 squares, ordered by `square_index`.
+/// ／`square_index` の順に並べた81マス。
+fn squares<T: CetkaikRepresentation>() -> Vec<T::AbsoluteCoord> {
+    let f = T::AbsoluteField::yhuap_initial();
+    let mut squares = super::occupied_squares::<T>(&f);
+    squares.extend(T::as_board_absolute(&f).empty_squares());
+    squares.sort_by_key(|&coord| super::square_index::<T>(coord));
+    squares
+}
+
+/// Rotates the board by 180° when `ASide` is to move, so that the player to move always sits where `IASide` does. Being a rotation by 180°, it is its own inverse.
+/// ／`ASide` の手番なら盤を180°回転し、手番の側が常に `IASide` の位置に座るようにする。180°回転なので、逆変換も同じ。
+const fn canonical_index(index: usize, whose_turn: AbsoluteSide) -> usize {
+    match whose_turn {
+        IASide => index,
+        ASide => 80 - index,
+    }
+}
+
+// `square_index` is less than 81
+// `square_index` は81未満
+#[allow(clippy::cast_possible_truncation)]
+fn canonical_square<T: CetkaikRepresentation>(
+    coord: T::AbsoluteCoord,
+    whose_turn: AbsoluteSide,
+) -> u8 {
+    canonical_index(super::square_index::<T>(coord), whose_turn) as u8
+}
+
+/// Swaps the colors of Io and Nuak1 when `ASide` is to move. In `Setup::yhuap_initial`, rotating the board by 180° brings every piece onto one of the same profession and color on the other side, except for the Io and the Nuak1 on column Z, whose colors differ; with this, the rotation becomes an exact mirror of the initial position. Being a swap, it is its own inverse.
+/// ／`ASide` の手番なら王と車の色を入れ替える。`Setup::yhuap_initial` において盤を180°回転すると、全ての駒は相手側の同じ職業・同じ色の駒に重なるが、Z 列の王と車だけは色が異なる。これにより、回転は初期局面の正確な鏡映となる。入れ替えなので、逆変換も同じ。
+const fn canonical_color(color: Color, prof: Profession, whose_turn: AbsoluteSide) -> Color {
+    match (whose_turn, prof, color) {
+        (ASide, Profession::Io | Profession::Nuak1, Color::Kok1) => Color::Huok2,
+        (ASide, Profession::Io | Profession::Nuak1, Color::Huok2) => Color::Kok1,
+        _ => color,
+    }
+}
+
+/// Applies `canonical_color` to the piece that `mv` drops, if any.
+/// ／`mv` が打つ駒があれば、それに `canonical_color` を適用する。
+const fn canonical_drop<Coord: Copy>(
+    mv: PureMove__<Coord>,
+    whose_turn: AbsoluteSide,
+) -> PureMove__<Coord> {
+    match mv {
+        PureMove__::NormalMove(NormalMove_::NonTamMoveFromHopZuo { color, prof, dest }) => {
+            PureMove__::NormalMove(NormalMove_::NonTamMoveFromHopZuo {
+                color: canonical_color(color, prof, whose_turn),
+                prof,
+                dest,
+            })
+        }
+        _ => mv,
+    }
+}
+
+/// A key that does not depend on the build (unlike `DefaultHasher`), so that a saved book stays usable. See `OpeningBook` for what it ignores.
+/// ／（`DefaultHasher` と異なり）ビルドに依存しない鍵。保存した定跡が使い続けられるように。何を無視するかは `OpeningBook` を参照。
+fn position_key<T: CetkaikRepresentation>(state: &state::GroundState_<T>) -> u64 {
+    let mover = state.whose_turn;
+    let mut bytes = [0_u8; 81 + 2 * 20 + 1];
+    for coord in super::occupied_squares::<T>(&state.f) {
+        let Some(piece) = T::as_board_absolute(&state.f).peek(coord) else {
+            continue;
+        };
+        // Tam2 is 1; the other pieces are numbered from 2 by profession, by whether they belong to the player to move and by color
+        // 皇は1、それ以外の駒は職業・手番の側のものかどうか・色によって2から番号を振る
+        bytes[canonical_index(super::square_index::<T>(coord), mover)] = piece
+            .match_on_piece_and_apply(&|| 1, &|color, prof, side| {
+                2 + (prof as u8 * 2 + u8::from(side != mover)) * 2
+                    + canonical_color(color, prof, mover) as u8
+            });
+    }
+    for (i, side) in [mover, !mover].into_iter().enumerate() {
+        for piece in state.f.hop1zuo1_of(side) {
+            let color = canonical_color(piece.color, piece.prof, mover);
+            bytes[81 + i * 20 + color as usize * 10 + piece.prof as usize] += 1;
+        }
+    }
+    bytes[81 + 2 * 20] = u8::from(state.tam_has_moved_previously);
+
+    // FNV-1a
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+const fn encode(mv: PureMove__<u8>) -> [u8; 5] {
+    match mv {
+        PureMove__::InfAfterStep(InfAfterStep_ {
+            src,
+            step,
+            planned_direction,
+        }) => [0, src, step, planned_direction, 0],
+        PureMove__::NormalMove(NormalMove_::NonTamMoveSrcDst { src, dest }) => [1, src, dest, 0, 0],
+        PureMove__::NormalMove(NormalMove_::NonTamMoveSrcStepDstFinite { src, step, dest }) => {
+            [2, src, step, dest, 0]
+        }
+        PureMove__::NormalMove(NormalMove_::NonTamMoveFromHopZuo { color, prof, dest }) => {
+            [3, color as u8, prof as u8, dest, 0]
+        }
+        PureMove__::NormalMove(NormalMove_::TamMoveNoStep {
+            src,
+            first_dest,
+            second_dest,
+        }) => [4, src, first_dest, second_dest, 0],
+        PureMove__::NormalMove(NormalMove_::TamMoveStepsDuringFormer {
+            src,
+            step,
+            first_dest,
+            second_dest,
+        }) => [5, src, step, first_dest, second_dest],
+        PureMove__::NormalMove(NormalMove_::TamMoveStepsDuringLatter {
+            src,
+            step,
+            first_dest,
+            second_dest,
+        }) => [6, src, step, first_dest, second_dest],
+    }
+}
+
+fn decode([tag, a, b, c, d]: [u8; 5]) -> Result<PureMove__<u8>, &'static str> {
+    let square = |i: u8| {
+        if i < 81 {
+            Ok(i)
+        } else {
+            Err("a square in the opening book is out of range")
+        }
+    };
+    Ok(match tag {
+        0 => PureMove__::InfAfterStep(InfAfterStep_ {
+            src: square(a)?,
+            step: square(b)?,
+            planned_direction: square(c)?,
+        }),
+        1 => PureMove__::NormalMove(NormalMove_::NonTamMoveSrcDst {
+            src: square(a)?,
+            dest: square(b)?,
+        }),
+        2 => PureMove__::NormalMove(NormalMove_::NonTamMoveSrcStepDstFinite {
+            src: square(a)?,
+            step: square(b)?,
+            dest: square(c)?,
+        }),
+        3 => PureMove__::NormalMove(NormalMove_::NonTamMoveFromHopZuo {
+            color: *COLORS
+                .get(usize::from(a))
+                .ok_or("a color in the opening book is out of range")?,
+            prof: *PROFESSIONS
+                .get(usize::from(b))
+                .ok_or("a profession in the opening book is out of range")?,
+            dest: square(c)?,
+        }),
+        4 => PureMove__::NormalMove(NormalMove_::TamMoveNoStep {
+            src: square(a)?,
+            first_dest: square(b)?,
+            second_dest: square(c)?,
+        }),
+        5 => PureMove__::NormalMove(NormalMove_::TamMoveStepsDuringFormer {
+            src: square(a)?,
+            step: square(b)?,
+            first_dest: square(c)?,
+            second_dest: square(d)?,
+        }),
+        6 => PureMove__::NormalMove(NormalMove_::TamMoveStepsDuringLatter {
+            src: square(a)?,
+            step: square(b)?,
+            first_dest: square(c)?,
+            second_dest: square(d)?,
+        }),
+        _ => return Err("unknown kind of move in the opening book"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_state, player, setup, Season};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_opening_book() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        use player::{play_game, replay, Event, RandomPlayer};
+        let config = Config::cerke_online_alpha();
+        let games: Vec<_> = (0..6)
+            .map(|seed| {
+                play_game::<CetkaikNaive>(&mut RandomPlayer, &mut RandomPlayer, config, seed)
+            })
+            .collect();
+
+        // Every early move of every game is found in the book, whoever moves
+        // どちらの手番であっても、全ての対局の序盤の手は全て定跡に見つかる
+        let mut book = OpeningBook::new();
+        for (record, victor) in &games {
+            book.add_game::<CetkaikNaive>(record, *victor, 3).unwrap();
+        }
+        for (record, _) in &games {
+            replay(
+                record,
+                &setup::Setup::yhuap_initial(),
+                |state: &state::GroundState_<CetkaikNaive>, decision| {
+                    if let Decision::Move(mv) = decision {
+                        if state.moves_in_this_season < 3 {
+                            assert!(book.lookup(state, config).iter().any(|m| m.mv == mv));
+                        }
+                    }
+                },
+            )
+            .unwrap();
+        }
+
+        // A book of seasons opened by IA side also answers for seasons opened by A side, mirrored
+        // IA 側が始めた季節の定跡は、反転されて A 側が始めた季節にも答える
+        let opened_by = |side| {
+            let mut book = OpeningBook::new();
+            for (record, victor) in &games {
+                if record.events.first()
+                    == Some(&Event::SeasonStarts {
+                        season: Season::Iei2,
+                        first_mover: side,
+                    })
+                {
+                    book.add_game::<CetkaikNaive>(record, *victor, 1).unwrap();
+                }
+            }
+            book
+        };
+        let book_of_ia_side = opened_by(IASide);
+        assert!(!book_of_ia_side.is_empty());
+        let [ia_side_opens, a_side_opens] = [IASide, ASide].map(|side| {
+            initial_state::<CetkaikNaive>()
+                .into_outcomes()
+                .into_iter()
+                .map(|(s, _)| s)
+                .find(|s| s.whose_turn == side)
+                .unwrap()
+        });
+        let games_of = |moves: &[BookMove<_>]| moves.iter().map(|m| m.stats.games).sum::<u32>();
+        let book_moves = book_of_ia_side.lookup(&ia_side_opens, config);
+        let mirrored_moves = book_of_ia_side.lookup(&a_side_opens, config);
+        assert!(!mirrored_moves.is_empty());
+        assert_eq!(games_of(&book_moves), games_of(&mirrored_moves));
+        let mut rng = StdRng::seed_from_u64(0);
+        let chosen = book_of_ia_side
+            .choose(&a_side_opens, config, &mut rng)
+            .unwrap();
+        assert!(mirrored_moves.iter().any(|m| m.mv == chosen));
+
+        // Round trip through a file
+        // ファイルを経由した往復
+        let path = std::env::temp_dir().join(format!("opening_book_{}.bin", std::process::id()));
+        book.save(&path).unwrap();
+        assert_eq!(OpeningBook::load(&path).unwrap(), book);
+        std::fs::remove_file(&path).unwrap();
+        let bytes = book.to_bytes();
+        assert!(OpeningBook::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(OpeningBook::from_bytes(b"CKOB\x02\0\0\0\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn test_position_key_keeps_colors() {
+        use cetkaik_naive_representation::absolute::{Board, Column, Coord, Field, Piece, Row};
+        use cetkaik_naive_representation::CetkaikNaive;
+
+        // The initial positions, whoever moves first, are mirror images of each other
+        // 先手がどちらであっても、初期局面は互いの鏡像である
+        let [ia_side_opens, a_side_opens] = [IASide, ASide].map(|side| {
+            initial_state::<CetkaikNaive>()
+                .into_outcomes()
+                .into_iter()
+                .map(|(s, _)| s)
+                .find(|s| s.whose_turn == side)
+                .unwrap()
+        });
+        assert_eq!(position_key(&ia_side_opens), position_key(&a_side_opens));
+
+        // A position whose pieces differ only in color is another position, on the board and in hop1zuo1
+        // 駒の色のみが異なる局面は、盤上でも手駒でも別の局面である
+        let position = |on_board, in_hop1zuo1| {
+            crate::fixtures::ground_state(
+                &Field {
+                    board: Board(maplit::hashmap! {
+                        Coord(Row::O, Column::Z) => Piece::Tam2,
+                        Coord(Row::AI, Column::K) => Piece::NonTam2Piece {
+                            color: on_board,
+                            prof: Profession::Kauk2,
+                            side: IASide,
+                        },
+                    }),
+                    a_side_hop1zuo1: vec![],
+                    ia_side_hop1zuo1: vec![cetkaik_fundamental::ColorAndProf {
+                        color: in_hop1zuo1,
+                        prof: Profession::Io,
+                    }],
+                },
+                ASide,
+                crate::Scores::new(),
+                crate::Rate::X1,
+            )
+        };
+        let key = position_key(&position(Color::Kok1, Color::Kok1));
+        assert_ne!(key, position_key(&position(Color::Huok2, Color::Kok1)));
+        assert_ne!(key, position_key(&position(Color::Kok1, Color::Huok2)));
+    }
+}
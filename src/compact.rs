@@ -0,0 +1,796 @@
+use super::{COLORS, PROFESSIONS};
+use cetkaik_fundamental::{AbsoluteSide, Color, ColorAndProf, Profession};
+use cetkaik_naive_representation::perspective::{self, Perspective};
+use cetkaik_naive_representation::{absolute, relative, CetkaikNaive};
+use cetkaik_traits::{
+    CetkaikRepresentation, IsAbsoluteBoard, IsAbsoluteField, IsBoard, IsField, IsPieceWithSide,
+};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU8;
+
+/// A marker type for a representation whose absolute board is an array of 81 squares with an occupancy bitboard, and whose hop1zuo1 are counted multisets, so that a field is `Copy` and needs no allocation. The relative side is shared with `cetkaik_naive_representation`, and the two representations produce the same transitions, the same candidates (up to the order of hop1zuo1) and the same `zobrist_hash`.
+/// ／絶対座標の盤が81マスの配列と占有ビットボードであり、手駒が個数付きの多重集合である表現を表すマーカー型。盤は `Copy` であり、確保を必要としない。相対座標の側は `cetkaik_naive_representation` と共通であり、二つの表現は同じ状態遷移・（手駒の順序を除いて）同じ候補手・同じ `zobrist_hash` を生む。
+///
+/// ```
+/// use cetkaik_full_state_transition::compact::{CetkaikCompact, Square};
+/// use cetkaik_full_state_transition::message::NormalMove_;
+/// use cetkaik_full_state_transition::probabilistic::Probabilistic;
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::absolute::{Column, Coord, Row};
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let Probabilistic::WhoGoesFirst { ia_first, .. } = initial_state::<CetkaikCompact>() else {
+///     unreachable!()
+/// };
+/// let Probabilistic::WhoGoesFirst { ia_first: naive, .. } = initial_state::<CetkaikNaive>() else {
+///     unreachable!()
+/// };
+/// assert_eq!(ia_first.zobrist_hash(), naive.zobrist_hash());
+///
+/// let msg = NormalMove_::NonTamMoveSrcDst {
+///     src: Square::from(Coord(Row::AI, Column::K)),
+///     dest: Square::from(Coord(Row::Y, Column::K)),
+/// };
+/// let next = apply_normal_move(&ia_first, msg, Config::cerke_online_alpha()).unwrap();
+/// assert!(matches!(next, Probabilistic::Pure(_)));
+/// ```
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct CetkaikCompact;
+
+/// A square, numbered from 0 to 80 as `9 * row + column`, where the rows go from A to IA and the columns from K to P.
+/// ／マス。行を A から IA、列を K から P として `9 * 行 + 列` で0から80の番号を振る。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Square(u8);
+
+impl Square {
+    /// `None` unless both `row` and `column` are less than 9.
+    /// ／`row` と `column` の両方が9未満でなければ `None`。
+    #[must_use]
+    pub const fn new(row: usize, column: usize) -> Option<Self> {
+        if row < 9 && column < 9 {
+            #[allow(clippy::cast_possible_truncation)] // less than 81
+            Some(Self((row * 9 + column) as u8))
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    #[must_use]
+    pub const fn row(self) -> usize {
+        self.index() / 9
+    }
+
+    #[must_use]
+    pub const fn column(self) -> usize {
+        self.index() % 9
+    }
+}
+
+impl TryFrom<u8> for Square {
+    type Error = &'static str;
+    fn try_from(index: u8) -> Result<Self, Self::Error> {
+        if index < 81 {
+            Ok(Self(index))
+        } else {
+            Err("a square must be numbered from 0 to 80")
+        }
+    }
+}
+
+impl From<Square> for u8 {
+    fn from(square: Square) -> Self {
+        square.0
+    }
+}
+
+impl From<absolute::Coord> for Square {
+    fn from(coord: absolute::Coord) -> Self {
+        let [row, column] =
+            perspective::to_relative_coord(coord, Perspective::IaIsDownAndPointsUpward);
+        Self::new(row, column).expect("a relative coordinate is always on the board")
+    }
+}
+
+impl From<Square> for absolute::Coord {
+    fn from(square: Square) -> Self {
+        perspective::to_absolute_coord(
+            [square.row(), square.column()],
+            Perspective::IaIsDownAndPointsUpward,
+        )
+    }
+}
+
+/// Written in the same way as `absolute::Coord`, e.g. `LIA`.
+/// ／`absolute::Coord` と同じく、例えば `LIA` と書く。
+impl std::fmt::Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", absolute::Coord::from(*self))
+    }
+}
+
+impl std::fmt::Debug for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+/// A piece on the board. Tam2 is 1, and the other pieces are numbered from 2 by color, profession and side. 0 is never used, so that `Option<Piece>` takes a single byte.
+/// ／盤上の駒。皇は1、それ以外の駒は色・職業・側によって2から番号を振る。0は使わないので、`Option<Piece>` は1バイトを占める。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Piece(NonZeroU8);
+
+impl Piece {
+    pub const TAM2: Self = Self(NonZeroU8::MIN);
+
+    #[must_use]
+    pub const fn new(color: Color, prof: Profession, side: AbsoluteSide) -> Self {
+        let code = 2 + kind_index(ColorAndProf { color, prof }) * 2 + side_index(side);
+        #[allow(clippy::cast_possible_truncation)] // at most 41
+        match NonZeroU8::new(code as u8) {
+            Some(code) => Self(code),
+            None => unreachable!(),
+        }
+    }
+
+    /// `None` for Tam2.
+    /// ／皇ならば `None`。
+    #[must_use]
+    pub const fn non_tam2(self) -> Option<(ColorAndProf, AbsoluteSide)> {
+        let code = self.0.get() as usize;
+        if code == 1 {
+            return None;
+        }
+        let kind = (code - 2) / 2;
+        Some((
+            ColorAndProf {
+                color: COLORS[kind / 10],
+                prof: PROFESSIONS[kind % 10],
+            },
+            if code & 1 == 0 {
+                AbsoluteSide::IASide
+            } else {
+                AbsoluteSide::ASide
+            },
+        ))
+    }
+}
+
+impl TryFrom<u8> for Piece {
+    type Error = &'static str;
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        NonZeroU8::new(code)
+            .filter(|code| code.get() <= 41)
+            .map(Self)
+            .ok_or("a piece must be numbered from 1 to 41")
+    }
+}
+
+impl From<Piece> for u8 {
+    fn from(piece: Piece) -> Self {
+        piece.0.get()
+    }
+}
+
+impl From<absolute::Piece> for Piece {
+    fn from(piece: absolute::Piece) -> Self {
+        match piece {
+            absolute::Piece::Tam2 => Self::TAM2,
+            absolute::Piece::NonTam2Piece { color, prof, side } => Self::new(color, prof, side),
+        }
+    }
+}
+
+impl From<Piece> for absolute::Piece {
+    fn from(piece: Piece) -> Self {
+        piece
+            .non_tam2()
+            .map_or(Self::Tam2, |(ColorAndProf { color, prof }, side)| {
+                Self::NonTam2Piece { color, prof, side }
+            })
+    }
+}
+
+impl std::fmt::Debug for Piece {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", absolute::Piece::from(*self))
+    }
+}
+
+impl IsPieceWithSide for Piece {
+    type Side = AbsoluteSide;
+    fn match_on_piece_and_apply<U>(
+        self,
+        f_tam: &dyn Fn() -> U,
+        f_piece: &dyn Fn(Color, Profession, Self::Side) -> U,
+    ) -> U {
+        match self.non_tam2() {
+            None => f_tam(),
+            Some((ColorAndProf { color, prof }, side)) => f_piece(color, prof, side),
+        }
+    }
+}
+
+/// The 81 squares, together with a bitboard of the occupied ones.
+/// ／81マスと、埋まっているマスのビットボード。
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Board {
+    squares: [Option<Piece>; 81],
+    occupied: u128,
+}
+
+impl Board {
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self {
+            squares: [None; 81],
+            occupied: 0,
+        }
+    }
+
+    /// The occupied squares and their pieces, in the order of `Square::index`.
+    /// ／埋まっているマスとその駒。`Square::index` の順。
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        Squares(self.occupied).filter_map(|square| Some((square, self.squares[square.index()]?)))
+    }
+}
+
+impl std::fmt::Debug for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.pieces()).finish()
+    }
+}
+
+/// Serialized as the list of the occupied squares and their pieces.
+/// ／埋まっているマスとその駒のリストとしてシリアライズする。
+impl Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.pieces())
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut board = Self::empty();
+        for (square, piece) in Vec::<(Square, Piece)>::deserialize(deserializer)? {
+            board.put(square, Some(piece));
+        }
+        Ok(board)
+    }
+}
+
+impl From<&absolute::Board> for Board {
+    fn from(board: &absolute::Board) -> Self {
+        let mut ans = Self::empty();
+        for (&coord, &piece) in &board.0 {
+            ans.put(coord.into(), Some(piece.into()));
+        }
+        ans
+    }
+}
+
+impl From<&Board> for absolute::Board {
+    fn from(board: &Board) -> Self {
+        Self(
+            board
+                .pieces()
+                .map(|(square, piece)| (square.into(), piece.into()))
+                .collect(),
+        )
+    }
+}
+
+/// Iterates over the squares whose bits are set.
+/// ／ビットが立っているマスを巡る。
+#[derive(Clone, Debug)]
+pub struct Squares(u128);
+
+impl Iterator for Squares {
+    type Item = Square;
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 {
+            return None;
+        }
+        #[allow(clippy::cast_possible_truncation)] // less than 81
+        let index = self.0.trailing_zeros() as u8;
+        self.0 &= self.0 - 1;
+        Some(Square(index))
+    }
+}
+
+const ALL_SQUARES: u128 = (1 << 81) - 1;
+
+impl IsBoard for Board {
+    type PieceWithSide = Piece;
+    type Coord = Square;
+
+    fn peek(&self, c: Square) -> Option<Piece> {
+        self.squares[c.index()]
+    }
+
+    fn pop(&mut self, c: Square) -> Option<Piece> {
+        self.occupied &= !(1 << c.index());
+        self.squares[c.index()].take()
+    }
+
+    fn put(&mut self, c: Square, p: Option<Piece>) {
+        if p.is_some() {
+            self.occupied |= 1 << c.index();
+        } else {
+            self.occupied &= !(1 << c.index());
+        }
+        self.squares[c.index()] = p;
+    }
+
+    fn assert_empty(&self, c: Square) {
+        assert!(
+            self.peek(c).is_none(),
+            "Expected the square {c:?} to be empty, but it was occupied"
+        );
+    }
+
+    fn assert_occupied(&self, c: Square) {
+        assert!(
+            self.peek(c).is_some(),
+            "Expected the square {c:?} to be occupied, but it was empty"
+        );
+    }
+
+    type EmptySquaresIter = Squares;
+    fn empty_squares(&self) -> Squares {
+        Squares(!self.occupied & ALL_SQUARES)
+    }
+}
+
+impl IsAbsoluteBoard for Board {
+    fn yhuap_initial() -> Self {
+        (&absolute::yhuap_initial_board()).into()
+    }
+}
+
+/// The board and how many pieces of each kind (numbered as `color * 10 + prof`) each side holds in hop1zuo1.
+/// ／盤と、それぞれの側が手駒に各種類（`色 * 10 + 職業` と番号を振る）の駒をいくつ持っているか。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Field {
+    pub board: Board,
+    hop1zuo1: [[u8; 20]; 2],
+}
+
+impl Field {
+    /// How many copies of `piece` `side` holds in hop1zuo1.
+    /// ／`side` が手駒に `piece` をいくつ持っているか。
+    #[must_use]
+    pub const fn hop1zuo1_count(&self, side: AbsoluteSide, piece: ColorAndProf) -> u8 {
+        self.hop1zuo1[side_index(side)][kind_index(piece)]
+    }
+
+    /// Adds `piece` to `side`'s hop1zuo1.
+    /// ／`side` の手駒に `piece` を加える。
+    pub fn insert_nontam_piece_into_hop1zuo1(&mut self, side: AbsoluteSide, piece: ColorAndProf) {
+        self.hop1zuo1[side_index(side)][kind_index(piece)] += 1;
+    }
+}
+
+impl From<&absolute::Field> for Field {
+    fn from(field: &absolute::Field) -> Self {
+        let mut ans = Self {
+            board: (&field.board).into(),
+            hop1zuo1: [[0; 20]; 2],
+        };
+        for (side, pieces) in [
+            (AbsoluteSide::IASide, &field.ia_side_hop1zuo1),
+            (AbsoluteSide::ASide, &field.a_side_hop1zuo1),
+        ] {
+            for &piece in pieces {
+                ans.insert_nontam_piece_into_hop1zuo1(side, piece);
+            }
+        }
+        ans
+    }
+}
+
+impl From<&Field> for absolute::Field {
+    fn from(field: &Field) -> Self {
+        Self {
+            board: (&field.board).into(),
+            ia_side_hop1zuo1: field.hop1zuo1_of(AbsoluteSide::IASide).collect(),
+            a_side_hop1zuo1: field.hop1zuo1_of(AbsoluteSide::ASide).collect(),
+        }
+    }
+}
+
+impl IsField for Field {
+    type Board = Board;
+    type Coord = Square;
+    type PieceWithSide = Piece;
+    type Side = AbsoluteSide;
+
+    fn move_nontam_piece_from_src_to_dest_while_taking_opponent_piece_if_needed(
+        &self,
+        src: Square,
+        dest: Square,
+        whose_turn: AbsoluteSide,
+    ) -> Result<Self, &'static str> {
+        let mut new_self = *self;
+        let src_piece = new_self
+            .board
+            .pop(src)
+            .ok_or("src does not contain a piece")?;
+        let (_, side) = src_piece
+            .non_tam2()
+            .ok_or("Expected a NonTam2Piece to be present at the src, but found a Tam2")?;
+        if whose_turn != side {
+            return Err("Found the opponent piece at the src");
+        }
+
+        if let Some(captured_piece) = new_self.board.pop(dest) {
+            let (captured, captured_side) =
+                captured_piece.non_tam2().ok_or("Tried to capture a Tam2")?;
+            if captured_side == whose_turn {
+                return Err("Tried to capture an ally");
+            }
+            new_self.insert_nontam_piece_into_hop1zuo1(whose_turn, captured);
+        }
+        new_self.board.put(dest, Some(src_piece));
+        Ok(new_self)
+    }
+
+    fn search_from_hop1zuo1_and_parachute_at(
+        &self,
+        color: Color,
+        prof: Profession,
+        side: AbsoluteSide,
+        dest: Square,
+    ) -> Option<Self> {
+        let piece = ColorAndProf { color, prof };
+        if self.hop1zuo1_count(side, piece) == 0 || self.board.peek(dest).is_some() {
+            return None;
+        }
+        let mut new_self = *self;
+        new_self.hop1zuo1[side_index(side)][kind_index(piece)] -= 1;
+        new_self
+            .board
+            .put(dest, Some(Piece::new(color, prof, side)));
+        Some(new_self)
+    }
+
+    fn as_board(&self) -> &Board {
+        &self.board
+    }
+
+    fn as_board_mut(&mut self) -> &mut Board {
+        &mut self.board
+    }
+}
+
+impl super::setup::Hop1Zuo1Editable for CetkaikCompact {
+    fn put_into_hop1zuo1(f: &mut Field, side: AbsoluteSide, piece: ColorAndProf) {
+        f.insert_nontam_piece_into_hop1zuo1(side, piece);
+    }
+
+    fn take_from_hop1zuo1(f: &mut Field, side: AbsoluteSide, piece: ColorAndProf) -> bool {
+        let count = &mut f.hop1zuo1[side_index(side)][kind_index(piece)];
+        if *count == 0 {
+            return false;
+        }
+        *count -= 1;
+        true
+    }
+
+    fn piece_of(piece: ColorAndProf, side: AbsoluteSide) -> Piece {
+        Piece::new(piece.color, piece.prof, side)
+    }
+}
+
+/// Yields the pieces of a hop1zuo1 in the order of their kinds.
+/// ／手駒の駒を種類の順に返す。
+#[derive(Clone, Debug)]
+pub struct Hop1zuo1Iter {
+    counts: [u8; 20],
+    kind: usize,
+}
+
+impl Iterator for Hop1zuo1Iter {
+    type Item = ColorAndProf;
+    fn next(&mut self) -> Option<ColorAndProf> {
+        while self.counts.get(self.kind)? == &0 {
+            self.kind += 1;
+        }
+        self.counts[self.kind] -= 1;
+        Some(ColorAndProf {
+            color: COLORS[self.kind / 10],
+            prof: PROFESSIONS[self.kind % 10],
+        })
+    }
+}
+
+impl IsAbsoluteField for Field {
+    fn yhuap_initial() -> Self {
+        Self {
+            board: Board::yhuap_initial(),
+            hop1zuo1: [[0; 20]; 2],
+        }
+    }
+
+    type Hop1Zuo1Iter = Hop1zuo1Iter;
+    fn hop1zuo1_of(&self, side: AbsoluteSide) -> Hop1zuo1Iter {
+        Hop1zuo1Iter {
+            counts: self.hop1zuo1[side_index(side)],
+            kind: 0,
+        }
+    }
+}
+
+const fn kind_index(piece: ColorAndProf) -> usize {
+    piece.color as usize * 10 + piece.prof as usize
+}
+
+const fn side_index(side: AbsoluteSide) -> usize {
+    match side {
+        AbsoluteSide::IASide => 0,
+        AbsoluteSide::ASide => 1,
+    }
+}
+
+impl CetkaikRepresentation for CetkaikCompact {
+    type Perspective = Perspective;
+
+    type AbsoluteCoord = Square;
+    type RelativeCoord = relative::Coord;
+
+    type AbsoluteBoard = Board;
+    type RelativeBoard = relative::Board;
+
+    type AbsolutePiece = Piece;
+    type RelativePiece = relative::Piece;
+
+    type AbsoluteField = Field;
+    type RelativeField = relative::Field;
+
+    type RelativeSide = relative::Side;
+
+    fn to_absolute_coord(coord: relative::Coord, p: Perspective) -> Square {
+        let [row, column] = coord;
+        if p.ia_is_down() {
+            Square::new(row, column)
+        } else {
+            Square::new(8 - row, 8 - column)
+        }
+        .expect("a relative coordinate is always on the board")
+    }
+
+    fn to_relative_coord(coord: Square, p: Perspective) -> relative::Coord {
+        if p.ia_is_down() {
+            [coord.row(), coord.column()]
+        } else {
+            [8 - coord.row(), 8 - coord.column()]
+        }
+    }
+
+    fn add_delta(
+        coord: relative::Coord,
+        row_delta: isize,
+        col_delta: isize,
+    ) -> Option<relative::Coord> {
+        CetkaikNaive::add_delta(coord, row_delta, col_delta)
+    }
+
+    fn is_tam_hue_by_default(coord: relative::Coord) -> bool {
+        CetkaikNaive::is_tam_hue_by_default(coord)
+    }
+
+    fn relative_tam2() -> relative::Piece {
+        relative::Piece::Tam2
+    }
+
+    fn absolute_tam2() -> Piece {
+        Piece::TAM2
+    }
+
+    fn is_upward(s: relative::Side) -> bool {
+        s == relative::Side::Upward
+    }
+
+    fn as_board_absolute(field: &Field) -> &Board {
+        &field.board
+    }
+
+    fn as_board_mut_absolute(field: &mut Field) -> &mut Board {
+        &mut field.board
+    }
+
+    fn as_board_relative(field: &relative::Field) -> &relative::Board {
+        &field.current_board
+    }
+
+    fn is_water_relative(c: relative::Coord) -> bool {
+        relative::is_water(c)
+    }
+
+    fn is_water_absolute(c: Square) -> bool {
+        match (c.row(), c.column()) {
+            (4, column) => (2..=6).contains(&column),
+            (2 | 3 | 5 | 6, column) => column == 4,
+            _ => false,
+        }
+    }
+
+    fn loop_over_one_side_and_tam(
+        board: &relative::Board,
+        side: relative::Side,
+        f_tam_or_piece: &mut dyn FnMut(relative::Coord, Option<Profession>),
+    ) {
+        CetkaikNaive::loop_over_one_side_and_tam(board, side, f_tam_or_piece);
+    }
+
+    fn to_relative_field(field: Field, p: Perspective) -> relative::Field {
+        let mut current_board = relative::Board([[None; 9]; 9]);
+        for (square, piece) in field.board.pieces() {
+            let [row, column] = Self::to_relative_coord(square, p);
+            current_board.0[row][column] = Some(perspective::to_relative_piece(piece.into(), p));
+        }
+        let (upward, downward) = if p.ia_is_down() {
+            (AbsoluteSide::IASide, AbsoluteSide::ASide)
+        } else {
+            (AbsoluteSide::ASide, AbsoluteSide::IASide)
+        };
+        relative::Field {
+            current_board,
+            hop1zuo1of_upward: field
+                .hop1zuo1_of(upward)
+                .map(|ColorAndProf { color, prof }| relative::NonTam2PieceUpward { color, prof })
+                .collect(),
+            hop1zuo1of_downward: field
+                .hop1zuo1_of(downward)
+                .map(|ColorAndProf { color, prof }| relative::NonTam2PieceDownward { color, prof })
+                .collect(),
+        }
+    }
+
+    fn to_relative_side(side: AbsoluteSide, p: Perspective) -> relative::Side {
+        perspective::to_relative_side(side, p)
+    }
+
+    fn get_one_perspective() -> Perspective {
+        // arbitrary, but then `square_index` agrees with `Square::index`
+        // 任意だが、こうすると `square_index` が `Square::index` と一致する
+        Perspective::IaIsDownAndPointsUpward
+    }
+
+    fn absolute_distance(a: Square, b: Square) -> i32 {
+        let distance = a
+            .row()
+            .abs_diff(b.row())
+            .max(a.column().abs_diff(b.column()));
+        #[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)] // at most 8
+        {
+            distance as i32
+        }
+    }
+
+    fn absolute_same_direction(origin: Square, a: Square, b: Square) -> bool {
+        #[allow(clippy::cast_possible_wrap)] // less than 9
+        let delta = |c: Square| {
+            (
+                c.row() as isize - origin.row() as isize,
+                c.column() as isize - origin.column() as isize,
+            )
+        };
+        let (a_u, a_v) = delta(a);
+        let (b_u, b_v) = delta(b);
+        (a_u * b_u + a_v * b_v > 0) && (a_u * b_v - a_v * b_u == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, player, Config};
+    use cetkaik_fundamental::AbsoluteSide::{ASide, IASide};
+
+    #[test]
+    fn test_compact_representation_agrees_with_naive() {
+        use std::collections::HashSet;
+
+        let squares: Vec<(absolute::Coord, Square)> = (0..81)
+            .map(|i| {
+                let square = Square::try_from(i).unwrap();
+                (square.into(), square)
+            })
+            .collect();
+        for &(coord, square) in &squares {
+            assert_eq!(Square::from(coord), square);
+            assert_eq!(
+                CetkaikNaive::is_water_absolute(coord),
+                CetkaikCompact::is_water_absolute(square)
+            );
+            for p in [
+                Perspective::IaIsDownAndPointsUpward,
+                Perspective::IaIsUpAndPointsDownward,
+            ] {
+                let relative = CetkaikNaive::to_relative_coord(coord, p);
+                assert_eq!(CetkaikCompact::to_relative_coord(square, p), relative);
+                assert_eq!(CetkaikCompact::to_absolute_coord(relative, p), square);
+            }
+            for &(coord_a, a) in &squares {
+                assert_eq!(
+                    CetkaikNaive::absolute_distance(coord, coord_a),
+                    CetkaikCompact::absolute_distance(square, a)
+                );
+                for &(coord_b, b) in &squares {
+                    assert_eq!(
+                        CetkaikNaive::absolute_same_direction(coord, coord_a, coord_b),
+                        CetkaikCompact::absolute_same_direction(square, a, b)
+                    );
+                }
+            }
+        }
+
+        // Replays the same games with both representations, comparing every state and its candidates
+        // 同じ対局を両方の表現で再現し、全ての状態とその候補手を比べる
+        let sorted = |mut pieces: Vec<cetkaik_fundamental::ColorAndProf>| {
+            pieces.sort_by_key(|piece| piece.color as usize * 10 + piece.prof as usize);
+            pieces
+        };
+        for (config, seed) in [
+            (Config::cerke_online_alpha(), 3),
+            (Config::strict_y1_huap1(), 4),
+        ] {
+            let (record, _) = player::play_game::<CetkaikNaive>(
+                &mut player::RandomPlayer,
+                &mut player::HandGreedyPlayer,
+                config,
+                seed,
+            );
+            let naive_states = fixtures::positions::<CetkaikNaive>(&record);
+            let compact_states =
+                fixtures::positions::<CetkaikCompact>(&record.clone().map(Square::from));
+            assert_eq!(naive_states.len(), compact_states.len());
+            for (naive, compact) in naive_states.iter().zip(&compact_states) {
+                assert_eq!(naive.zobrist_hash, compact.zobrist_hash);
+                assert_eq!(
+                    naive.positions_in_this_season,
+                    compact.positions_in_this_season
+                );
+                assert_eq!(
+                    (naive.whose_turn, naive.season, naive.scores, naive.rate),
+                    (
+                        compact.whose_turn,
+                        compact.season,
+                        compact.scores,
+                        compact.rate
+                    )
+                );
+                assert_eq!(naive.f.board, absolute::Board::from(&compact.f.board));
+                for side in [IASide, ASide] {
+                    assert_eq!(
+                        sorted(naive.f.hop1zuo1_of(side).collect()),
+                        compact.f.hop1zuo1_of(side).collect::<Vec<_>>()
+                    );
+                }
+                let p = CetkaikNaive::get_one_perspective();
+                assert_eq!(
+                    CetkaikNaive::to_relative_field(naive.f.clone(), p).current_board,
+                    CetkaikCompact::to_relative_field(compact.f, p).current_board
+                );
+
+                let (naive_hop1zuo1, naive_others) = naive.get_candidates(config);
+                let (compact_hop1zuo1, compact_others) = compact.get_candidates(config);
+                let naive_candidates: HashSet<_> = naive_hop1zuo1
+                    .into_iter()
+                    .chain(naive_others)
+                    .map(|mv| mv.map(Square::from))
+                    .collect();
+                let compact_candidates: HashSet<_> =
+                    compact_hop1zuo1.into_iter().chain(compact_others).collect();
+                assert_eq!(naive_candidates, compact_candidates);
+            }
+        }
+    }
+}
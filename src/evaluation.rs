@@ -0,0 +1,367 @@
+use super::message::{NormalMove_, PureMove__};
+use super::{search, state, Config, Consequence, Season};
+use cetkaik_fundamental::{AbsoluteSide, ColorAndProf};
+use cetkaik_traits::{CetkaikRepresentation, IsAbsoluteField, IsBoard, IsPieceWithSide};
+use serde::{Deserialize, Serialize};
+
+/// The weight of each term of `StaticEvaluator`. When deserialized, a term that is missing keeps its default weight, and an unknown term is an error. `load` and `save` read and write it as JSON.
+/// ／`StaticEvaluator` の各項の重み。デシリアライズの際、欠けている項は既定の重みのままとなり、未知の項はエラーとなる。`load` と `save` は JSON として読み書きする。
+///
+/// ```
+/// use cetkaik_full_state_transition::evaluation::Weights;
+///
+/// let weights: Weights = serde_json::from_str(r#"{ "partial_hands": 2.0 }"#).unwrap();
+/// assert!((weights.partial_hands - 2.0).abs() < f64::EPSILON);
+/// assert_eq!(weights.scores, Weights::default().scores);
+/// assert!(serde_json::from_str::<Weights>(r#"{ "score": 2.0 }"#).is_err());
+/// ```
+#[derive(Clone, Debug, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Weights {
+    pub material_on_board: f64,
+    pub material_in_hop1zuo1: f64,
+    pub partial_hands: f64,
+    pub scores: f64,
+    pub rate: f64,
+    pub remaining_seasons: f64,
+    pub threatened: f64,
+    pub protected: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            material_on_board: 0.1,
+            material_in_hop1zuo1: 0.2,
+            partial_hands: 0.5,
+            scores: 1.0,
+            rate: 0.5,
+            remaining_seasons: 0.5,
+            threatened: 0.1,
+            protected: 0.05,
+        }
+    }
+}
+
+impl Weights {
+    /// # Errors
+    /// Fails if the file cannot be read or is malformed.
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// # Errors
+    /// Fails if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// A term of the evaluation. `feature` is measured from IA side's point of view (positive means good for IA side), and the term contributes `weight * feature`.
+/// ／評価の一項。`feature` は IA 側から見た値（正なら IA 側に有利）で、この項は `weight * feature` だけ寄与する。
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct Term {
+    pub name: &'static str,
+    pub feature: f64,
+    pub weight: f64,
+}
+
+impl Term {
+    #[must_use]
+    pub fn contribution(&self) -> f64 {
+        self.weight * self.feature
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Breakdown {
+    pub terms: Vec<Term>,
+}
+
+impl Breakdown {
+    /// The sum of the contributions, squashed into [-1, 1] by `tanh` so that it can be used as a value of `search`.
+    /// ／寄与の和を `tanh` で [-1, 1] に押し込めたもの。`search` の値として使える。
+    #[must_use]
+    pub fn total(&self) -> f64 {
+        self.terms
+            .iter()
+            .map(Term::contribution)
+            .sum::<f64>()
+            .tanh()
+    }
+}
+
+impl std::fmt::Display for Breakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for term in &self.terms {
+            writeln!(
+                f,
+                "{:<20} {:>+8.4} x {:>7.3} = {:>+8.4}",
+                term.name,
+                term.feature,
+                term.weight,
+                term.contribution()
+            )?;
+        }
+        write!(f, "{:<20} {:>+8.4}", "total", self.total())
+    }
+}
+
+/// Scores a `GroundState_` without searching. The features are:
+/// - `material_on_board`, `material_in_hop1zuo1`: the difference in the number of pieces, divided by 24.
+/// - `partial_hands`: the difference in how many points the best single capture would add to the hands in hop1zuo1, divided by 40.
+/// - `scores`: the difference in `Scores`, divided by 40.
+/// - `rate`: `partial_hands` multiplied by (`Rate::num` − 1), since a hand is worth that much more.
+/// - `remaining_seasons`: `scores` multiplied by the fraction of the seasons already gone, since a lead matters more as the game draws to an end.
+/// - `threatened`: the difference in the number of opponent's pieces that one can capture, divided by 24.
+/// - `protected`: the difference in the number of own threatened pieces that one can capture back, divided by 24. Only the first capture found for each piece is tried.
+///
+/// ／探索せずに `GroundState_` を評価する。特徴量は以下の通り。
+/// - `material_on_board`、`material_in_hop1zuo1`：駒数の差を24で割ったもの。
+/// - `partial_hands`：一つの駒を取ることで手駒の役が最大何点増えるかの差を40で割ったもの。
+/// - `scores`：`Scores` の差を40で割ったもの。
+/// - `rate`：役の価値がその分増えるので、`partial_hands` に (`Rate::num` − 1) を掛けたもの。
+/// - `remaining_seasons`：終盤ほどリードが重要になるので、`scores` に経過した季節の割合を掛けたもの。
+/// - `threatened`：取ることのできる相手の駒の数の差を24で割ったもの。
+/// - `protected`：脅かされている自分の駒のうち、取り返せるものの数の差を24で割ったもの。各駒について最初に見つかった取り方のみを試す。
+///
+/// ```
+/// use cetkaik_full_state_transition::evaluation::{StaticEvaluator, Weights};
+/// use cetkaik_full_state_transition::probabilistic::Probabilistic;
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let Probabilistic::WhoGoesFirst { ia_first, .. } = initial_state::<CetkaikNaive>() else {
+///     unreachable!()
+/// };
+/// let evaluator = StaticEvaluator::new(Config::cerke_online_alpha(), Weights::default());
+/// let breakdown = evaluator.breakdown(&ia_first);
+///
+/// // the initial position is symmetric
+/// assert!(breakdown.terms.iter().all(|term| term.feature.abs() < 1e-9));
+/// assert!(breakdown.total().abs() < 1e-9);
+/// ```
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct StaticEvaluator {
+    pub weights: Weights,
+    config: Config,
+}
+
+impl StaticEvaluator {
+    #[must_use]
+    pub const fn new(config: Config, weights: Weights) -> Self {
+        Self { weights, config }
+    }
+
+    #[must_use]
+    pub fn breakdown<T: CetkaikRepresentation>(&self, state: &state::GroundState_<T>) -> Breakdown {
+        let ia = self.side_features::<T>(&state.f, AbsoluteSide::IASide);
+        let a = self.side_features::<T>(&state.f, AbsoluteSide::ASide);
+        let scores = f64::from(state.scores.ia() - state.scores.a()) / 40.0;
+        let partial_hands = f64::from(ia.best_hand_gain - a.best_hand_gain) / 40.0;
+        let seasons_gone = match state.season {
+            Season::Iei2 => 0.0,
+            Season::Xo1 => 1.0,
+            Season::Kat2 => 2.0,
+            Season::Iat1 => 3.0,
+        } / 4.0;
+        let w = self.weights;
+        Breakdown {
+            terms: vec![
+                Term {
+                    name: "material_on_board",
+                    feature: f64::from(ia.on_board - a.on_board) / 24.0,
+                    weight: w.material_on_board,
+                },
+                Term {
+                    name: "material_in_hop1zuo1",
+                    feature: f64::from(ia.in_hop1zuo1 - a.in_hop1zuo1) / 24.0,
+                    weight: w.material_in_hop1zuo1,
+                },
+                Term {
+                    name: "partial_hands",
+                    feature: partial_hands,
+                    weight: w.partial_hands,
+                },
+                Term {
+                    name: "scores",
+                    feature: scores,
+                    weight: w.scores,
+                },
+                Term {
+                    name: "rate",
+                    feature: partial_hands * f64::from(state.rate.num() - 1),
+                    weight: w.rate,
+                },
+                Term {
+                    name: "remaining_seasons",
+                    feature: scores * seasons_gone,
+                    weight: w.remaining_seasons,
+                },
+                Term {
+                    name: "threatened",
+                    feature: f64::from(ia.threatening - a.threatening) / 24.0,
+                    weight: w.threatened,
+                },
+                Term {
+                    name: "protected",
+                    feature: f64::from(a.recapturable - ia.recapturable) / 24.0,
+                    weight: w.protected,
+                },
+            ],
+        }
+    }
+
+    fn side_features<T: CetkaikRepresentation>(
+        &self,
+        f: &T::AbsoluteField,
+        side: AbsoluteSide,
+    ) -> SideFeatures {
+        let board = T::as_board_absolute(f);
+        let mut on_board = 0;
+        let mut opponent_pieces: Vec<ColorAndProf> = vec![];
+        for coord in super::occupied_squares::<T>(f) {
+            let Some((piece, s)) = board.peek(coord).and_then(|piece| {
+                piece.match_on_piece_and_apply(&|| None, &|color, prof, s| {
+                    Some((ColorAndProf { color, prof }, s))
+                })
+            }) else {
+                continue;
+            };
+            if s == side {
+                on_board += 1;
+            } else if !opponent_pieces.contains(&piece) {
+                opponent_pieces.push(piece);
+            }
+        }
+        let best_hand_gain = opponent_pieces
+            .into_iter()
+            .map(|piece| super::hand_gain::<T>(f, side, piece))
+            .max()
+            .unwrap_or(0)
+            .max(0);
+
+        // The first capture found for each square: (src, dest)
+        // 各マスについて最初に見つかった取る手：(src, dest)
+        let mut captures: Vec<(T::AbsoluteCoord, T::AbsoluteCoord)> = vec![];
+        for (src, dest) in self.moves_onto::<T>(f, side) {
+            if super::captured_at::<T>(f, side, dest).is_some()
+                && !captures.iter().any(|(_, d)| *d == dest)
+            {
+                captures.push((src, dest));
+            }
+        }
+
+        // Whether `!side` can capture back after `side` has captured
+        // `side` が取った後に `!side` が取り返せるか
+        let recapturable = captures
+            .iter()
+            .filter(|(src, dest)| {
+                let mut after = f.clone();
+                let board = T::as_board_mut_absolute(&mut after);
+                let piece = board.pop(*src);
+                board.put(*dest, piece);
+                self.moves_onto::<T>(&after, !side)
+                    .any(|(_, recapture)| recapture == *dest)
+            })
+            .count();
+
+        SideFeatures {
+            on_board,
+            in_hop1zuo1: i32::try_from(f.hop1zuo1_of(side).count())
+                .expect("there are only 49 pieces"),
+            best_hand_gain,
+            threatening: i32::try_from(captures.len()).expect("there are only 49 pieces"),
+            recapturable: i32::try_from(recapturable).expect("there are only 49 pieces"),
+        }
+    }
+
+    /// `(src, dest)` of the moves of non-Tam2 pieces on the board, ignoring whose turn it is.
+    /// ／盤上の皇以外の駒の移動の `(src, dest)`。手番は無視する。
+    fn moves_onto<T: CetkaikRepresentation>(
+        &self,
+        f: &T::AbsoluteField,
+        side: AbsoluteSide,
+    ) -> impl Iterator<Item = (T::AbsoluteCoord, T::AbsoluteCoord)> {
+        cetkaik_yhuap_move_candidates::not_from_hop1zuo1_candidates_vec::<T>(
+            &cetkaik_yhuap_move_candidates::AllowKut2Tam2 {
+                allow_kut2tam2: self.config.kut2tam2 != Consequence::Forbidden,
+            },
+            self.config.tam_itself_is_tam_hue,
+            side,
+            f,
+        )
+        .into_iter()
+        .filter_map(|mv| match PureMove__::from(mv) {
+            PureMove__::NormalMove(
+                NormalMove_::NonTamMoveSrcDst { src, dest }
+                | NormalMove_::NonTamMoveSrcStepDstFinite { src, dest, .. },
+            ) => Some((src, dest)),
+            PureMove__::InfAfterStep(msg) => Some((msg.src, msg.planned_direction)),
+            PureMove__::NormalMove(_) => None,
+        })
+    }
+}
+
+struct SideFeatures {
+    on_board: i32,
+    in_hop1zuo1: i32,
+    best_hand_gain: i32,
+
+    /// How many of the opponent's pieces this side can capture
+    /// 相手の駒のうちいくつをこちらが取れるか
+    threatening: i32,
+
+    /// How many of the captures in `threatening` the opponent can capture back
+    /// `threatening` の取る手のうちいくつを相手が取り返せるか
+    recapturable: i32,
+}
+
+impl<T: CetkaikRepresentation> search::Evaluator<T> for StaticEvaluator {
+    fn evaluate(&self, state: &state::GroundState_<T>) -> f64 {
+        self.breakdown(state).total()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::position_with_a_winning_hand;
+
+    #[test]
+    fn test_static_evaluator() {
+        let weights: Weights =
+            serde_json::from_str(r#"{ "scores": 2, "threatened": 0.5 }"#).unwrap();
+        assert!(serde_json::from_str::<Weights>(r#"{ "scores": "2" }"#).is_err());
+        assert!(serde_json::from_str::<Weights>(r#"{ "score": 2 }"#).is_err());
+
+        let path =
+            std::env::temp_dir().join(format!("cetkaik-weights-{}.json", std::process::id()));
+        weights.save(&path).unwrap();
+        assert_eq!(Weights::load(&path).unwrap(), weights);
+        std::fs::write(&path, "scores = 2").unwrap();
+        assert_eq!(
+            Weights::load(&path).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let breakdown = StaticEvaluator::new(Config::cerke_online_alpha(), weights)
+            .breakdown(&position_with_a_winning_hand());
+        let feature = |name| {
+            breakdown
+                .terms
+                .iter()
+                .find(|term| term.name == name)
+                .unwrap()
+                .feature
+        };
+        assert!((feature("scores") - 0.8).abs() < 1e-9);
+        assert!(feature("partial_hands") > 0.0);
+        // each side can capture the other's only piece, and neither can capture back
+        // 互いに相手の唯一の駒を取ることができ、どちらも取り返せない
+        assert!(feature("threatened").abs() < 1e-9);
+        assert!(feature("protected").abs() < 1e-9);
+        assert!(breakdown.total() > 0.0);
+    }
+}
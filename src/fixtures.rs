@@ -0,0 +1,130 @@
+use super::state::{GroundState_, HandNotResolved_};
+use super::{hands, player, search, setup, zobrist, Config, Rate, Scores, Season};
+use cetkaik_fundamental::AbsoluteSide::{self, ASide, IASide};
+use cetkaik_fundamental::ColorAndProf;
+use cetkaik_naive_representation::absolute::{Coord, Field};
+use cetkaik_naive_representation::CetkaikNaive;
+use cetkaik_traits::CetkaikRepresentation;
+
+/// The beginning of spring on `f` at `rate`, where `whose_turn` moves first and the scores are `scores`.
+/// ／`f` 上で、`whose_turn` が先手で得点が `scores`、レートが `rate` の春の始まり。
+pub fn ground_state(
+    f: &Field,
+    whose_turn: AbsoluteSide,
+    scores: Scores,
+    rate: Rate,
+) -> GroundState_<CetkaikNaive> {
+    GroundState_::new(
+        f.clone(),
+        whose_turn,
+        Season::Iei2,
+        whose_turn,
+        scores,
+        rate,
+        false,
+    )
+}
+
+/// A game between two `player::RandomPlayer`s under `config`.
+/// ／`config` の下での二つの `player::RandomPlayer` の対局。
+pub fn random_game(config: Config, seed: u64) -> player::GameRecord<Coord> {
+    player::play_game::<CetkaikNaive>(
+        &mut player::RandomPlayer,
+        &mut player::RandomPlayer,
+        config,
+        seed,
+    )
+    .0
+}
+
+/// Every position in `record` where a player is about to move.
+/// ／`record` の中で、プレイヤーが手を指そうとしている全ての局面。
+pub fn positions<T: CetkaikRepresentation + Clone>(
+    record: &player::GameRecord<T::AbsoluteCoord>,
+) -> Vec<GroundState_<T>> {
+    let mut positions = vec![];
+    player::replay(
+        record,
+        &setup::Setup::yhuap_initial(),
+        |state: &GroundState_<T>, _| positions.push(state.clone()),
+    )
+    .unwrap();
+    positions
+}
+
+/// One in sixteen of `positions(record)`, up to eight. Checking every candidate of every position of a game takes too long.
+/// ／`positions(record)` の十六に一つを八局面まで。対局の全ての局面の全ての候補手を確かめると時間がかかりすぎる。
+pub fn sampled_positions<T: CetkaikRepresentation + Clone>(
+    record: &player::GameRecord<T::AbsoluteCoord>,
+) -> Vec<GroundState_<T>> {
+    positions(record).into_iter().step_by(16).take(8).collect()
+}
+
+/// `state` right after its player has made a move that captured `captured_piece`, which must already be in that player's hop1zuo1.
+/// ／`state` の手番の人が `captured_piece` を取る手を指した直後。`captured_piece` は既にその人の手駒に入っていなければならない。
+pub fn after_capture(
+    state: &GroundState_<CetkaikNaive>,
+    captured_piece: Option<ColorAndProf>,
+) -> HandNotResolved_<CetkaikNaive> {
+    let mut hand_not_resolved = HandNotResolved_ {
+        f: state.f.clone(),
+        whose_turn: state.whose_turn,
+        season: state.season,
+        season_first_mover: state.season_first_mover,
+        moves_in_this_season: state.moves_in_this_season,
+        positions_in_this_season: state.positions_in_this_season.clone(),
+        scores: state.scores,
+        rate: state.rate,
+        i_have_moved_tam_in_this_turn: false,
+        captured_piece,
+        dropped_piece: None,
+        kut2tam2_happened: false,
+        tam2tysak2_raw_penalty: 0,
+        tam2tysak2_will_trigger_taxottymok: false,
+        hands: hands::HandTracker::of_field_before_move::<CetkaikNaive>(
+            &state.f,
+            state.whose_turn,
+            captured_piece,
+            None,
+        ),
+        zobrist_hash: 0,
+    };
+    hand_not_resolved.zobrist_hash = zobrist::hash_of_hand_not_resolved(&hand_not_resolved);
+    hand_not_resolved
+}
+
+/// IA side, holding 36 points, can capture A side's Io and win the game by taxot.
+/// ／36点を持つIA側は、A側の王を取って、taxotで勝てる。
+pub fn position_with_a_winning_hand() -> GroundState_<CetkaikNaive> {
+    use cetkaik_fundamental::{Color, Profession};
+    use cetkaik_naive_representation::absolute::{Board, Column, Piece, Row};
+    let f = Field {
+        board: Board(maplit::hashmap! {
+            Coord(Row::O, Column::Z) => Piece::Tam2,
+            Coord(Row::AI, Column::K) => Piece::NonTam2Piece {
+                color: Color::Kok1,
+                prof: Profession::Kauk2,
+                side: IASide,
+            },
+            Coord(Row::Y, Column::K) => Piece::NonTam2Piece {
+                color: Color::Kok1,
+                prof: Profession::Io,
+                side: ASide,
+            },
+        }),
+        a_side_hop1zuo1: vec![],
+        ia_side_hop1zuo1: vec![],
+    };
+    ground_state(&f, IASide, Scores::from_ia_score(36).unwrap(), Rate::X1)
+}
+
+/// The capture that wins the game in `position_with_a_winning_hand`.
+/// ／`position_with_a_winning_hand` で勝ちとなる駒取り。
+pub const WINNING_CAPTURE: search::Decision<Coord> = {
+    use super::message::{NormalMove_, PureMove__};
+    use cetkaik_naive_representation::absolute::{Column, Row};
+    search::Decision::Move(PureMove__::NormalMove(NormalMove_::NonTamMoveSrcDst {
+        src: Coord(Row::AI, Column::K),
+        dest: Coord(Row::Y, Column::K),
+    }))
+};
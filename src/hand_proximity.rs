@@ -0,0 +1,211 @@
+use super::message::PureMove__;
+use super::state;
+use cetkaik_calculate_hand::calculate_hands_and_score_from_pieces;
+use cetkaik_fundamental::{AbsoluteSide, ColorAndProf};
+use cetkaik_traits::{CetkaikRepresentation, IsAbsoluteField};
+
+/// Hands that a side would newly form by capturing `pieces` in addition to its current hop1zuo1.
+/// ／現在の手駒に加えて `pieces` を取ることで新たに成立する役。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion<Coord> {
+    /// One or two pieces to capture.
+    /// ／取るべき一つまたは二つの駒。
+    pub pieces: Vec<ColorAndProf>,
+
+    /// The names of the hands newly formed, as given by `cetkaik_calculate_hand`, sorted.
+    /// ／新たに成立する役の名前（`cetkaik_calculate_hand` による）。ソート済み。
+    pub new_hands: Vec<String>,
+
+    /// How much the score would change at the current `Rate` if the side chose taxot right after the captures.
+    /// ／取った直後に終季したときに、現在の `Rate` で得点がどれだけ変わるか。
+    pub score_change: i32,
+
+    /// The squares where the opponent has a piece listed in `pieces`.
+    /// ／`pieces` に挙げられた駒を相手が置いているマス。
+    pub squares: Vec<Coord>,
+
+    /// The candidate moves that capture a piece listed in `pieces` right now. A move that needs a stepping-over cast is assumed to reach its planned destination.
+    /// ／`pieces` に挙げられた駒を今すぐ取る候補手。踏越え判定が必要な手は予定の移動先に到達するとみなす。
+    pub capturing_moves: Vec<PureMove__<Coord>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandProximity<Coord> {
+    pub side: AbsoluteSide,
+    pub one_capture_away: Vec<Completion<Coord>>,
+
+    /// Only the hands that no single capture of the two pieces would form.
+    /// ／二つの駒のどちらか一方を取るだけでは成立しない役のみ。
+    pub two_captures_away: Vec<Completion<Coord>>,
+}
+
+/// Lists, for IA side and then for A side, the hands that are one or two captures away.
+/// The capturing moves come from `get_candidates`. For the side not to move, they are the candidates it would have if it were its turn, with Tam2 not having moved in the previous turn.
+/// ／IA 側、A 側の順に、あと一つまたは二つ駒を取れば成立する役を列挙する。
+/// 取る手は `get_candidates` による。手番でない側については、皇が前の手番で動いていないものとして、手番であったならば得られる候補手とする。
+///
+/// ```
+/// use cetkaik_full_state_transition::probabilistic::Probabilistic;
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let Probabilistic::WhoGoesFirst { ia_first, .. } = initial_state::<CetkaikNaive>() else {
+///     unreachable!()
+/// };
+/// let [ia, a] = hand_proximity::analyze(&ia_first, Config::cerke_online_alpha());
+///
+/// // Capturing the Io is a hand by itself, but nothing can capture it in the initial position
+/// // 王を取ることはそれ自体で役だが、初期配置では取れない
+/// let io = ia.one_capture_away.iter().find(|c| c.new_hands == ["王"]).unwrap();
+/// assert_eq!(io.score_change, 5);
+/// assert!(io.capturing_moves.is_empty());
+/// assert_eq!(a.one_capture_away.len(), ia.one_capture_away.len());
+/// ```
+#[must_use]
+pub fn analyze<T: CetkaikRepresentation + Clone>(
+    state: &state::GroundState_<T>,
+    config: super::Config,
+) -> [HandProximity<T::AbsoluteCoord>; 2] {
+    [AbsoluteSide::IASide, AbsoluteSide::ASide].map(|side| {
+        let as_if_to_move = if side == state.whose_turn {
+            state.clone()
+        } else {
+            state::GroundState_::new(
+                state.f.clone(),
+                side,
+                state.season,
+                state.season_first_mover,
+                state.scores,
+                state.rate,
+                false,
+            )
+        };
+        analyze_side(&as_if_to_move, config)
+    })
+}
+
+fn analyze_side<T: CetkaikRepresentation>(
+    state: &state::GroundState_<T>,
+    config: super::Config,
+) -> HandProximity<T::AbsoluteCoord> {
+    let side = state.whose_turn;
+    let hop1zuo1: Vec<ColorAndProf> = state.f.hop1zuo1_of(side).collect();
+    let hands_and_score = |extra: &[ColorAndProf]| {
+        let pieces: Vec<ColorAndProf> = hop1zuo1.iter().chain(extra).copied().collect();
+        calculate_hands_and_score_from_pieces(&pieces).map_or((vec![], 0), |ans| {
+            let mut hands: Vec<String> = ans.hands.into_iter().collect();
+            hands.sort();
+            (hands, ans.score)
+        })
+    };
+    let (current_hands, current_score) = hands_and_score(&[]);
+
+    // The opponent's pieces on the board, grouped by kind
+    // 盤上の相手の駒を種類ごとにまとめたもの
+    let mut opponent_pieces: Vec<(ColorAndProf, Vec<T::AbsoluteCoord>)> = vec![];
+    for coord in super::occupied_squares::<T>(&state.f) {
+        let Some(piece) = super::captured_at::<T>(&state.f, side, coord) else {
+            continue;
+        };
+        match opponent_pieces.iter_mut().find(|(p, _)| *p == piece) {
+            Some((_, squares)) => squares.push(coord),
+            None => opponent_pieces.push((piece, vec![coord])),
+        }
+    }
+
+    let (hop1zuo1_candidates, candidates) = state.get_candidates(config);
+    let moves: Vec<_> = hop1zuo1_candidates.into_iter().chain(candidates).collect();
+    let completion = |pieces: Vec<ColorAndProf>, already: &[String]| {
+        let (hands, score) = hands_and_score(&pieces);
+        let new_hands: Vec<String> = hands
+            .into_iter()
+            .filter(|h| !current_hands.contains(h) && !already.contains(h))
+            .collect();
+        if new_hands.is_empty() {
+            return None;
+        }
+        let mut squares = vec![];
+        for (piece, coords) in &opponent_pieces {
+            if pieces.contains(piece) {
+                squares.extend(coords.iter().copied());
+            }
+        }
+        let mut capturing_moves = vec![];
+        for mv in &moves {
+            if super::captured_by(state, *mv).is_some_and(|p| pieces.contains(&p))
+                && !capturing_moves.contains(mv)
+            {
+                capturing_moves.push(*mv);
+            }
+        }
+        Some(Completion {
+            pieces,
+            new_hands,
+            score_change: (score - current_score) * state.rate.num(),
+            squares,
+            capturing_moves,
+        })
+    };
+
+    let one_capture_away: Vec<_> = opponent_pieces
+        .iter()
+        .filter_map(|(piece, _)| completion(vec![*piece], &[]))
+        .collect();
+
+    let mut two_captures_away = vec![];
+    for (i, (first, first_squares)) in opponent_pieces.iter().enumerate() {
+        for (j, (second, _)) in opponent_pieces.iter().enumerate().skip(i) {
+            if i == j && first_squares.len() < 2 {
+                continue;
+            }
+            let already: Vec<String> = one_capture_away
+                .iter()
+                .filter(|c| c.pieces[0] == *first || c.pieces[0] == *second)
+                .flat_map(|c| c.new_hands.iter().cloned())
+                .collect();
+            if let Some(c) = completion(vec![*first, *second], &already) {
+                two_captures_away.push(c);
+            }
+        }
+    }
+
+    HandProximity {
+        side,
+        one_capture_away,
+        two_captures_away,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{position_with_a_winning_hand, WINNING_CAPTURE};
+    use crate::{search, Config};
+    use cetkaik_fundamental::AbsoluteSide::IASide;
+    use cetkaik_naive_representation::absolute::{Column, Coord, Row};
+
+    #[test]
+    fn test_hand_proximity() {
+        let [ia, a] = analyze(
+            &position_with_a_winning_hand(),
+            Config::cerke_online_alpha(),
+        );
+        assert_eq!(ia.side, IASide);
+        assert_eq!(ia.one_capture_away.len(), 1);
+        let io = &ia.one_capture_away[0];
+        assert_eq!(io.new_hands, ["王"]);
+        assert_eq!(io.squares, [Coord(Row::Y, Column::K)]);
+        assert_eq!(
+            io.capturing_moves
+                .iter()
+                .map(|mv| search::Decision::Move(*mv))
+                .collect::<Vec<_>>(),
+            [WINNING_CAPTURE]
+        );
+        assert!(ia.two_captures_away.is_empty());
+
+        // a single Kauk2 forms no hand
+        // 兵一つでは役にならない
+        assert!(a.one_capture_away.is_empty() && a.two_captures_away.is_empty());
+    }
+}
@@ -0,0 +1,502 @@
+use cetkaik_calculate_hand::PositiveHand;
+use cetkaik_fundamental::{AbsoluteSide, Color, ColorAndProf, Profession};
+use cetkaik_traits::{CetkaikRepresentation, IsAbsoluteField};
+
+/// Every hand, in the order of the variants of `PositiveHand`.
+/// ／全ての役。`PositiveHand` のバリアントの順。
+const ALL_HANDS: [PositiveHand; 19] = [
+    PositiveHand::Io,
+    PositiveHand::Saup1,
+    PositiveHand::BapPokSaup1,
+    PositiveHand::Huep2Hia1,
+    PositiveHand::BapPokHuep2Hia1,
+    PositiveHand::Maun1Gua2Kauk2,
+    PositiveHand::BapPokMaun1Gua2Kauk2,
+    PositiveHand::Uaip2Hi1,
+    PositiveHand::BapPokUaip2Hi1,
+    PositiveHand::KaikDat2,
+    PositiveHand::BapPokKaikDat2,
+    PositiveHand::Mok1Mok1,
+    PositiveHand::BapPokMok1Mok1,
+    PositiveHand::Kua2Kauk2Mun1Aum2,
+    PositiveHand::BapPokKua2Kauk2Mun1Aum2,
+    PositiveHand::HuetKaikADat2,
+    PositiveHand::BapPokHuetKaikADat2,
+    PositiveHand::Mun1Mak1Mok1Hue,
+    PositiveHand::BapPokMun1Mak1Mok1Hue,
+];
+
+/// How many pieces of each color and profession a hop1zuo1 holds, indexed by `Color as usize` and `Profession as usize`.
+/// ／手駒が各色・各職業の駒をいくつ持っているか。`Color as usize` と `Profession as usize` で添字付けられる。
+type Counts = [[u8; 10]; 2];
+
+/// The hands that one side's hop1zuo1 forms, as a set of `PositiveHand`.
+/// ／片方の手駒がなす役。`PositiveHand` の集合。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Hands(u32);
+
+impl Hands {
+    const fn bit(hand: PositiveHand) -> u32 {
+        1 << hand as u32
+    }
+
+    const fn with(self, hand: PositiveHand) -> Self {
+        Self(self.0 | Self::bit(hand))
+    }
+
+    /// Calculates the hands of `pieces` from scratch.
+    /// ／`pieces` の役を一から計算する。
+    #[must_use]
+    pub fn of_pieces(pieces: &[ColorAndProf]) -> Self {
+        let mut counts = [[0; 10]; 2];
+        for piece in pieces {
+            counts[piece.color as usize][piece.prof as usize] += 1;
+        }
+        of_counts(&counts)
+    }
+
+    #[must_use]
+    pub const fn contains(self, hand: PositiveHand) -> bool {
+        self.0 & Self::bit(hand) != 0
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[must_use]
+    pub const fn is_subset(self, other: Self) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = PositiveHand> {
+        ALL_HANDS
+            .into_iter()
+            .filter(move |hand| self.contains(*hand))
+    }
+
+    #[must_use]
+    pub fn score(self) -> i32 {
+        self.iter().map(PositiveHand::hand_to_score).sum()
+    }
+}
+
+/// Same as `cetkaik_calculate_hand::calculate_hands_and_score_from_pieces`, but on the counts, so that nothing is allocated.
+/// ／`cetkaik_calculate_hand::calculate_hands_and_score_from_pieces` と同じだが、個数に対して計算するので何も確保しない。
+fn of_counts(counts: &Counts) -> Hands {
+    if counts[Color::Kok1 as usize][Profession::Io as usize] == 0
+        && counts[Color::Huok2 as usize][Profession::Io as usize] == 0
+    {
+        of_counts_with_no_king(counts)
+    } else {
+        of_counts_with_king(counts)
+    }
+}
+
+fn total(counts: &Counts, prof: Profession) -> u8 {
+    counts[0][prof as usize] + counts[1][prof as usize]
+}
+
+/// Adds `bap_pok` if `profs` are all present in the same color, and otherwise adds `hand` if they are all present.
+/// ／`profs` が全て同じ色で揃っていれば `bap_pok` を、そうでなくとも全て揃っていれば `hand` を加える。
+fn with_set(
+    hands: Hands,
+    counts: &Counts,
+    profs: &[Profession],
+    bap_pok: PositiveHand,
+    hand: PositiveHand,
+) -> Hands {
+    if counts
+        .iter()
+        .any(|of_color| profs.iter().all(|prof| of_color[*prof as usize] >= 1))
+    {
+        hands.with(bap_pok)
+    } else if profs.iter().all(|prof| total(counts, *prof) >= 1) {
+        hands.with(hand)
+    } else {
+        hands
+    }
+}
+
+/// Adds `bap_pok` if `prof` and two Kauk2 are present in the same color, and otherwise adds `hand` if they are present.
+/// ／`prof` と兵二つが同じ色で揃っていれば `bap_pok` を、そうでなくとも揃っていれば `hand` を加える。
+fn with_two_kauk2(
+    hands: Hands,
+    counts: &Counts,
+    prof: Profession,
+    bap_pok: PositiveHand,
+    hand: PositiveHand,
+) -> Hands {
+    let kauk2 = Profession::Kauk2 as usize;
+    if counts
+        .iter()
+        .any(|of_color| of_color[prof as usize] >= 1 && of_color[kauk2] >= 2)
+    {
+        hands.with(bap_pok)
+    } else if total(counts, prof) >= 1 && total(counts, Profession::Kauk2) >= 2 {
+        hands.with(hand)
+    } else {
+        hands
+    }
+}
+
+fn of_counts_with_no_king(counts: &Counts) -> Hands {
+    let mut hands = Hands::default();
+    let kauk2 = Profession::Kauk2 as usize;
+    if counts.iter().any(|of_color| of_color[kauk2] >= 5) {
+        hands = hands.with(PositiveHand::BapPokHuetKaikADat2);
+    } else if total(counts, Profession::Kauk2) >= 5 {
+        hands = hands.with(PositiveHand::HuetKaikADat2);
+    }
+    hands = with_two_kauk2(
+        hands,
+        counts,
+        Profession::Kaun1,
+        PositiveHand::BapPokUaip2Hi1,
+        PositiveHand::Uaip2Hi1,
+    );
+    hands = with_two_kauk2(
+        hands,
+        counts,
+        Profession::Uai1,
+        PositiveHand::BapPokKaikDat2,
+        PositiveHand::KaikDat2,
+    );
+    for (profs, bap_pok, hand) in [
+        (
+            &[Profession::Dau2, Profession::Maun1][..],
+            PositiveHand::BapPokSaup1,
+            PositiveHand::Saup1,
+        ),
+        (
+            &[Profession::Nuak1, Profession::Kaun1, Profession::Maun1],
+            PositiveHand::BapPokMok1Mok1,
+            PositiveHand::Mok1Mok1,
+        ),
+        (
+            &[Profession::Kauk2, Profession::Gua2, Profession::Maun1],
+            PositiveHand::BapPokMaun1Gua2Kauk2,
+            PositiveHand::Maun1Gua2Kauk2,
+        ),
+        (
+            &[Profession::Kua2, Profession::Tuk2, Profession::Uai1],
+            PositiveHand::BapPokHuep2Hia1,
+            PositiveHand::Huep2Hia1,
+        ),
+        (
+            &[
+                Profession::Kauk2,
+                Profession::Gua2,
+                Profession::Uai1,
+                Profession::Kua2,
+                Profession::Tuk2,
+            ],
+            PositiveHand::BapPokKua2Kauk2Mun1Aum2,
+            PositiveHand::Kua2Kauk2Mun1Aum2,
+        ),
+    ] {
+        hands = with_set(hands, counts, profs, bap_pok, hand);
+    }
+    hands
+}
+
+fn of_counts_with_king(counts: &Counts) -> Hands {
+    let mut hands = with_set(
+        Hands::default().with(PositiveHand::Io),
+        counts,
+        &crate::PROFESSIONS,
+        PositiveHand::BapPokMun1Mak1Mok1Hue,
+        PositiveHand::Mun1Mak1Mok1Hue,
+    );
+
+    // An Io stands in for any other profession of its color
+    // 王は同じ色の他の任意の職業の代わりとなる
+    let io = Profession::Io as usize;
+    for color in 0..2 {
+        if counts[color][io] != 1 {
+            continue;
+        }
+        let mut wildcard = *counts;
+        wildcard[color][io] = 0;
+        for prof in crate::PROFESSIONS {
+            if prof == Profession::Io {
+                continue;
+            }
+            wildcard[color][prof as usize] += 1;
+            hands.0 |= of_counts(&wildcard).0;
+            wildcard[color][prof as usize] -= 1;
+        }
+    }
+
+    // A hand is not counted when its flash counterpart is
+    // 同色の役が成立しているときは、同色でない方は数えない
+    for (bap_pok, hand) in [
+        (PositiveHand::BapPokUaip2Hi1, PositiveHand::Uaip2Hi1),
+        (PositiveHand::BapPokHuep2Hia1, PositiveHand::Huep2Hia1),
+        (PositiveHand::BapPokKaikDat2, PositiveHand::KaikDat2),
+        (
+            PositiveHand::BapPokMaun1Gua2Kauk2,
+            PositiveHand::Maun1Gua2Kauk2,
+        ),
+        (PositiveHand::BapPokSaup1, PositiveHand::Saup1),
+        (PositiveHand::BapPokMok1Mok1, PositiveHand::Mok1Mok1),
+        (
+            PositiveHand::BapPokKua2Kauk2Mun1Aum2,
+            PositiveHand::Kua2Kauk2Mun1Aum2,
+        ),
+        (
+            PositiveHand::BapPokHuetKaikADat2,
+            PositiveHand::HuetKaikADat2,
+        ),
+        (
+            PositiveHand::BapPokMun1Mak1Mok1Hue,
+            PositiveHand::Mun1Mak1Mok1Hue,
+        ),
+    ] {
+        if hands.contains(bap_pok) {
+            hands.0 &= !Hands::bit(hand);
+        }
+    }
+    hands
+}
+
+/// Both sides' hop1zuo1 as counts, along with the `Hands` they form. The transition functions update it by the single piece captured or dropped, without looking at the rest of the hop1zuo1, so that `resolve` does not have to examine the whole hop1zuo1.
+/// ／両者の手駒の個数と、それがなす `Hands`。状態遷移関数は、手駒の残りを見ることなく、取られた駒や打たれた駒一つによってこれを更新する。これにより `resolve` は手駒全体を調べなくて済む。
+///
+/// ```
+/// use cetkaik_calculate_hand::PositiveHand;
+/// use cetkaik_full_state_transition::hands::{HandTracker, Hands};
+/// use cetkaik_fundamental::{AbsoluteSide, Color, ColorAndProf, Profession};
+///
+/// let io = ColorAndProf { color: Color::Kok1, prof: Profession::Io };
+/// let kauk2 = ColorAndProf { color: Color::Huok2, prof: Profession::Kauk2 };
+/// let mut tracker = HandTracker::default();
+///
+/// assert!(!tracker.capture(AbsoluteSide::ASide, kauk2));
+/// assert!(tracker.capture(AbsoluteSide::ASide, io));
+/// assert!(tracker.of(AbsoluteSide::ASide).contains(PositiveHand::Io));
+/// assert_eq!(tracker.of(AbsoluteSide::ASide).score(), 5);
+///
+/// tracker.drop_piece(AbsoluteSide::ASide, io);
+/// assert_eq!(tracker.of(AbsoluteSide::ASide), Hands::default());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HandTracker {
+    counts: [Counts; 2],
+    hands: [Hands; 2],
+}
+
+const fn side_index(side: AbsoluteSide) -> usize {
+    match side {
+        AbsoluteSide::IASide => 0,
+        AbsoluteSide::ASide => 1,
+    }
+}
+
+impl HandTracker {
+    /// Counts both sides' hop1zuo1 in `f` from scratch.
+    /// ／`f` における両者の手駒を一から数える。
+    #[must_use]
+    pub fn of_field<T: CetkaikRepresentation>(f: &T::AbsoluteField) -> Self {
+        let mut tracker = Self::default();
+        for side in [AbsoluteSide::IASide, AbsoluteSide::ASide] {
+            for piece in f.hop1zuo1_of(side) {
+                tracker.counts[side_index(side)][piece.color as usize][piece.prof as usize] += 1;
+            }
+            tracker.hands[side_index(side)] = of_counts(&tracker.counts[side_index(side)]);
+        }
+        tracker
+    }
+
+    /// The hands before `side` made the move that led to `f`: `captured`, which `side`'s hop1zuo1 in `f` contains, is not counted, and `dropped`, which it no longer contains, is. This is what `HandNotResolved_` holds.
+    /// ／`side` が `f` に至る手を指す前の役。`f` における `side` の手駒に含まれる `captured` を数えず、もう含まれない `dropped` を数える。`HandNotResolved_` が持つのはこれである。
+    #[must_use]
+    pub fn of_field_before_move<T: CetkaikRepresentation>(
+        f: &T::AbsoluteField,
+        side: AbsoluteSide,
+        captured: Option<ColorAndProf>,
+        dropped: Option<ColorAndProf>,
+    ) -> Self {
+        let mut counts = Self::of_field::<T>(f).counts;
+        let i = side_index(side);
+        if let Some(piece) = captured {
+            counts[i][piece.color as usize][piece.prof as usize] -= 1;
+        }
+        if let Some(piece) = dropped {
+            counts[i][piece.color as usize][piece.prof as usize] += 1;
+        }
+        Self {
+            counts,
+            hands: [of_counts(&counts[0]), of_counts(&counts[1])],
+        }
+    }
+
+    #[must_use]
+    pub const fn of(&self, side: AbsoluteSide) -> Hands {
+        self.hands[side_index(side)]
+    }
+
+    /// Counts `piece`, which `side` has just captured. Returns whether it has formed a hand that `side` did not have before.
+    /// ／`side` が取ったばかりの `piece` を数える。それによって `side` がそれまで持っていなかった役ができたかを返す。
+    pub fn capture(&mut self, side: AbsoluteSide, piece: ColorAndProf) -> bool {
+        let i = side_index(side);
+        self.counts[i][piece.color as usize][piece.prof as usize] += 1;
+        let hands = of_counts(&self.counts[i]);
+        let formed = !hands.is_subset(self.hands[i]);
+        self.hands[i] = hands;
+        formed
+    }
+
+    /// Stops counting `piece`, which `side` has just dropped. Returns whether it has formed a hand that `side` did not have before, which happens when dropping breaks a 同色 hand into the plain one. Dropping forms no hand from none, so the hands are not calculated again unless `side` has had one.
+    /// ／`side` が打ったばかりの `piece` を数えるのをやめる。それによって `side` がそれまで持っていなかった役ができたかを返す。これは打つことで同色の役が崩れて同色でない役になるときに起こる。役がないところから打って役ができることはないので、`side` が役を持っていなければ役を計算し直さない。
+    pub fn drop_piece(&mut self, side: AbsoluteSide, piece: ColorAndProf) -> bool {
+        let i = side_index(side);
+        let count = &mut self.counts[i][piece.color as usize][piece.prof as usize];
+        debug_assert!(*count > 0, "dropped a piece that is not in the hop1zuo1");
+        *count -= 1;
+        if self.hands[i].is_empty() {
+            return false;
+        }
+        let hands = of_counts(&self.counts[i]);
+        let formed = !hands.is_subset(self.hands[i]);
+        self.hands[i] = hands;
+        formed
+    }
+}
+
+#[cfg(all(test, feature = "naive"))]
+mod tests {
+    use super::*;
+    use crate::compact::{CetkaikCompact, Square};
+    use crate::{
+        apply_normal_move, fixtures, message, player, resolve, state, Config, COLORS, PROFESSIONS,
+    };
+    use cetkaik_fundamental::AbsoluteSide::{ASide, IASide};
+    use cetkaik_naive_representation::CetkaikNaive;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_hands_are_tracked_incrementally() {
+        fn check<T: CetkaikRepresentation + Clone>(record: &player::GameRecord<T::AbsoluteCoord>) {
+            let config = record.config;
+            for state in fixtures::positions::<T>(record) {
+                assert_eq!(state.hands, HandTracker::of_field::<T>(&state.f));
+            }
+            for state in fixtures::sampled_positions::<T>(record) {
+                let (hop1zuo1_candidates, candidates) = state.get_candidates(config);
+                for cand in hop1zuo1_candidates.into_iter().chain(candidates) {
+                    let message::PureMove__::NormalMove(msg) = cand else {
+                        continue;
+                    };
+                    for (hnr, _) in apply_normal_move(&state, msg, config)
+                        .unwrap()
+                        .into_outcomes()
+                    {
+                        assert_eq!(
+                            hnr.hands,
+                            HandTracker::of_field_before_move::<T>(
+                                &hnr.f,
+                                hnr.whose_turn,
+                                hnr.captured_piece,
+                                hnr.dropped_piece
+                            )
+                        );
+
+                        // As in 1.3.1, a hand is formed if the hop1zuo1 after the move, calculated from scratch, has a hand that the one before did not
+                        // 1.3.1 と同様に、一から計算した手を指した後の手駒に、指す前の手駒になかった役があれば役ができたとする
+                        let from_scratch = |f: &T::AbsoluteField| {
+                            let pieces: Vec<_> = f.hop1zuo1_of(hnr.whose_turn).collect();
+                            cetkaik_calculate_hand::calculate_hands_and_score_from_pieces(&pieces)
+                                .unwrap()
+                        };
+                        let before = from_scratch(&state.f);
+                        let after = from_scratch(&hnr.f);
+                        let newly_acquired = after
+                            .hands
+                            .iter()
+                            .any(|hand| !before.hands.contains(hand))
+                            .then_some(after.score);
+                        let (penalty, tymoxtaxot, hands) = crate::score_of_the_turn(&hnr, config);
+                        assert_eq!(hands, HandTracker::of_field::<T>(&hnr.f));
+                        assert_eq!(
+                            tymoxtaxot.map_or(0, |score| score - penalty),
+                            newly_acquired.unwrap_or(0)
+                        );
+                        if newly_acquired.is_some() {
+                            assert!(matches!(
+                                resolve(&hnr, config),
+                                state::HandResolved_::HandExists { .. }
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Captures and drops in a random order, starting with every piece on the board
+        // 全ての駒が盤上にある状態から、無作為な順に駒を取ったり打ったりする
+        let all_pieces: Vec<ColorAndProf> = COLORS
+            .iter()
+            .flat_map(|&color| {
+                PROFESSIONS.iter().flat_map(move |&prof| {
+                    let count = match prof {
+                        Profession::Kauk2 => 8,
+                        Profession::Io | Profession::Nuak1 => 1,
+                        _ => 2,
+                    };
+                    std::iter::repeat(ColorAndProf { color, prof }).take(count)
+                })
+            })
+            .collect();
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let mut on_board = all_pieces.clone();
+            let mut hop1zuo1 = [vec![], vec![]];
+            let mut tracker = HandTracker::default();
+            for _ in 0..80 {
+                let i = rng.gen_range(0..2);
+                let side = [ASide, IASide][i];
+                if !hop1zuo1[i].is_empty() && rng.gen_bool(0.3) {
+                    let index = rng.gen_range(0..hop1zuo1[i].len());
+                    let piece = hop1zuo1[i].swap_remove(index);
+                    on_board.push(piece);
+                    tracker.drop_piece(side, piece);
+                } else if !on_board.is_empty() {
+                    let before = Hands::of_pieces(&hop1zuo1[i]);
+                    let index = rng.gen_range(0..on_board.len());
+                    let piece = on_board.swap_remove(index);
+                    hop1zuo1[i].push(piece);
+                    let after = Hands::of_pieces(&hop1zuo1[i]);
+                    assert_eq!(tracker.capture(side, piece), !after.is_subset(before));
+                }
+                for (side, pieces) in [(ASide, &hop1zuo1[0]), (IASide, &hop1zuo1[1])] {
+                    // Agrees with the library that calculates the hands from scratch
+                    // 役を一から計算するライブラリと一致する
+                    let expected =
+                        cetkaik_calculate_hand::calculate_hands_and_score_from_pieces(pieces)
+                            .unwrap();
+                    let hands = tracker.of(side);
+                    assert_eq!(hands, Hands::of_pieces(pieces));
+                    assert_eq!(
+                        hands
+                            .iter()
+                            .map(|hand| hand.to_string())
+                            .collect::<HashSet<_>>(),
+                        expected.hands.into_iter().collect::<HashSet<_>>()
+                    );
+                    assert_eq!(hands.score(), expected.score);
+                }
+            }
+        }
+
+        // The states that the transition functions produce
+        // 状態遷移関数が作る状態
+        for config in [Config::cerke_online_alpha(), Config::strict_y1_huap1()] {
+            let record = fixtures::random_game(config, 3);
+            check::<CetkaikNaive>(&record);
+            check::<CetkaikCompact>(&record.map(Square::from));
+        }
+    }
+}
@@ -57,6 +57,201 @@ fn piece_on_field_at<T: CetkaikRepresentation>(
     T::as_board_absolute(f).peek(coord)
 }
 
+/// The piece that `whose_turn` would capture by moving onto `dest`, if any.
+/// ／`whose_turn` が `dest` に動いたときに取る駒（あれば）。
+fn captured_at<T: CetkaikRepresentation>(
+    f: &T::AbsoluteField,
+    whose_turn: AbsoluteSide,
+    dest: T::AbsoluteCoord,
+) -> Option<cetkaik_fundamental::ColorAndProf> {
+    T::as_board_absolute(f).peek(dest).and_then(|piece| {
+        piece.match_on_piece_and_apply(&|| None, &|color, prof, side| {
+            (side != whose_turn).then_some(cetkaik_fundamental::ColorAndProf { color, prof })
+        })
+    })
+}
+
+/// The piece that a move captures, if any. A move that needs a stepping-over cast is assumed to reach its planned destination.
+/// ／その手で取る駒（あれば）。踏越え判定が必要な手は予定の移動先に到達するとみなす。
+fn captured_by<T: CetkaikRepresentation>(
+    state: &state::GroundState_<T>,
+    mv: message::PureMove__<T::AbsoluteCoord>,
+) -> Option<cetkaik_fundamental::ColorAndProf> {
+    let dest = match mv {
+        message::PureMove__::NormalMove(
+            message::NormalMove_::NonTamMoveSrcDst { dest, .. }
+            | message::NormalMove_::NonTamMoveSrcStepDstFinite { dest, .. },
+        ) => dest,
+        message::PureMove__::InfAfterStep(msg) => msg.planned_direction,
+        message::PureMove__::NormalMove(
+            message::NormalMove_::NonTamMoveFromHopZuo { .. }
+            | message::NormalMove_::TamMoveNoStep { .. }
+            | message::NormalMove_::TamMoveStepsDuringFormer { .. }
+            | message::NormalMove_::TamMoveStepsDuringLatter { .. },
+        ) => return None,
+    };
+    captured_at::<T>(&state.f, state.whose_turn, dest)
+}
+
+/// How many points the hands in `whose_turn`'s hop1zuo1 would gain by capturing `piece`.
+/// ／`piece` を取ることで `whose_turn` の手駒の役が何点増えるか。
+fn hand_gain<T: CetkaikRepresentation>(
+    f: &T::AbsoluteField,
+    whose_turn: AbsoluteSide,
+    piece: cetkaik_fundamental::ColorAndProf,
+) -> i32 {
+    let score_of = |pieces: &[cetkaik_fundamental::ColorAndProf]| {
+        cetkaik_calculate_hand::calculate_hands_and_score_from_pieces(pieces)
+            .map_or(0, |ans| ans.score)
+    };
+    let mut pieces: Vec<_> = f.hop1zuo1_of(whose_turn).collect();
+    let before = score_of(&pieces);
+    pieces.push(piece);
+    score_of(&pieces) - before
+}
+
+/// `cetkaik_traits` has no way to directly enumerate the pieces on an absolute board, so we go through a relative board.
+/// ／`cetkaik_traits` には絶対座標の盤上の駒を直接列挙する手段がないので、相対座標の盤を経由する。
+fn occupied_squares<T: CetkaikRepresentation>(f: &T::AbsoluteField) -> Vec<T::AbsoluteCoord> {
+    let perspective = T::get_one_perspective();
+    let relative_field = T::to_relative_field(f.clone(), perspective);
+    let mut ans = vec![];
+    for side in [IASide, ASide] {
+        T::loop_over_one_side_and_tam(
+            T::as_board_relative(&relative_field),
+            T::to_relative_side(side, perspective),
+            &mut |coord, maybe_prof| {
+                // Tam2 is visited from both sides
+                // 皇は両側から訪れられる
+                if maybe_prof.is_some() || side == IASide {
+                    ans.push(T::to_absolute_coord(coord, perspective));
+                }
+            },
+        );
+    }
+    ans
+}
+
+/// Counts how many times we can go up from `coord`. `cetkaik_traits` only exposes relative coordinates through `add_delta`, so this is how we learn which row a square lies in.
+/// ／`coord` から何回上に行けるかを数える。`cetkaik_traits` は相対座標を `add_delta` を通じてしか見せないので、こうやって行を知る。
+fn relative_row<T: CetkaikRepresentation>(coord: T::RelativeCoord) -> usize {
+    let mut row = 0;
+    let mut c = coord;
+    while let Some(above) = T::add_delta(c, -1, 0) {
+        row += 1;
+        c = above;
+    }
+    row
+}
+
+/// Counts how many times we can go left from `coord`. See `relative_row`.
+/// ／`coord` から何回左に行けるかを数える。`relative_row` を参照。
+fn relative_column<T: CetkaikRepresentation>(coord: T::RelativeCoord) -> usize {
+    let mut column = 0;
+    let mut c = coord;
+    while let Some(left) = T::add_delta(c, 0, -1) {
+        column += 1;
+        c = left;
+    }
+    column
+}
+
+/// Numbers the 81 squares from 0 to 80, so that a square can be used where `T::AbsoluteCoord` cannot (e.g. as a key of a hash table).
+/// ／81マスに0から80の番号を振る。`T::AbsoluteCoord` を使えないところ（ハッシュ表のキーなど）でマスを使うため。
+fn square_index<T: CetkaikRepresentation>(coord: T::AbsoluteCoord) -> usize {
+    let relative = T::to_relative_coord(coord, T::get_one_perspective());
+    relative_row::<T>(relative) * 9 + relative_column::<T>(relative)
+}
+
+/// The colors and the professions in the order of their discriminants, so that they can be decoded from a number.
+/// ／色と職業を判別値の順に並べたもの。数から復号するため。
+const COLORS: [cetkaik_fundamental::Color; 2] = [
+    cetkaik_fundamental::Color::Kok1,
+    cetkaik_fundamental::Color::Huok2,
+];
+
+const PROFESSIONS: [cetkaik_fundamental::Profession; 10] = [
+    cetkaik_fundamental::Profession::Nuak1,
+    cetkaik_fundamental::Profession::Kauk2,
+    cetkaik_fundamental::Profession::Gua2,
+    cetkaik_fundamental::Profession::Kaun1,
+    cetkaik_fundamental::Profession::Dau2,
+    cetkaik_fundamental::Profession::Maun1,
+    cetkaik_fundamental::Profession::Kua2,
+    cetkaik_fundamental::Profession::Tuk2,
+    cetkaik_fundamental::Profession::Uai1,
+    cetkaik_fundamental::Profession::Io,
+];
+
+/// How the Zobrist hash changes when the squares in `touched` change from how they are in `old` to how they are in `new`.
+/// ／`touched` のマスが `old` での状態から `new` での状態に変わるときの Zobrist ハッシュの変化。
+fn zobrist_delta_on_board<T: CetkaikRepresentation>(
+    old: &T::AbsoluteField,
+    new: &T::AbsoluteField,
+    touched: &[T::AbsoluteCoord],
+) -> u64 {
+    let key_at = |f: &T::AbsoluteField, coord| {
+        T::as_board_absolute(f)
+            .peek(coord)
+            .map_or(0, |piece| zobrist::piece::<T>(coord, piece))
+    };
+    let mut delta = 0;
+    for (i, &coord) in touched.iter().enumerate() {
+        if !touched[..i].contains(&coord) {
+            delta ^= key_at(old, coord) ^ key_at(new, coord);
+        }
+    }
+    delta
+}
+
+/// How the Zobrist hash changes when `piece` is added to (if `added`) or removed from (otherwise) `side`'s hop1zuo1 in `f`.
+/// ／`f` における `side` の手駒に `piece` を加える（`added` のとき）か取り除く（それ以外のとき）ときの Zobrist ハッシュの変化。
+fn zobrist_delta_on_hop1zuo1<T: CetkaikRepresentation>(
+    f: &T::AbsoluteField,
+    side: AbsoluteSide,
+    piece: cetkaik_fundamental::ColorAndProf,
+    added: bool,
+) -> u64 {
+    let count = f.hop1zuo1_of(side).filter(|p| *p == piece).count();
+    zobrist::hop1zuo1(side, piece, if added { count } else { count - 1 })
+}
+
+/// Returns a predicate telling whether placing a piece from hop1zuo1 at a square is forbidden by `config.drop_restriction`.
+/// ／持ち駒をあるマスに打つことが `config.drop_restriction` により禁じられているかを判定する述語を返す。
+fn drop_is_restricted<T: CetkaikRepresentation>(
+    config: Config,
+    whose_turn: AbsoluteSide,
+    f: &T::AbsoluteField,
+) -> impl Fn(T::AbsoluteCoord) -> bool {
+    let perspective = T::get_one_perspective();
+    let relative_board = *T::as_board_relative(&T::to_relative_field(f.clone(), perspective));
+
+    // The upward-pointing side starts at the bottom three rows, so its opponent's home rows are the top three rows
+    // 上向きの側は下の三列から始まるので、その相手の陣地は上の三列である
+    let opponent_home_rows = if T::is_upward(T::to_relative_side(whose_turn, perspective)) {
+        0..=2
+    } else {
+        6..=8
+    };
+    let DropRestriction {
+        no_drop_into_tam_hue,
+        no_drop_into_water,
+        no_drop_onto_opponent_home_rows,
+    } = config.drop_restriction;
+    move |dest| {
+        let relative_dest = T::to_relative_coord(dest, perspective);
+        (no_drop_into_water && T::is_water_absolute(dest))
+            || (no_drop_onto_opponent_home_rows
+                && opponent_home_rows.contains(&relative_row::<T>(relative_dest)))
+            || (no_drop_into_tam_hue
+                && cetkaik_yhuap_move_candidates::is_tam_hue_relative::<T>(
+                    relative_dest,
+                    relative_board,
+                    config.tam_itself_is_tam_hue,
+                ))
+    }
+}
+
 impl Season {
     #[must_use]
     pub const fn next(self) -> Option<Self> {
@@ -85,12 +280,16 @@ impl Season {
 pub mod state;
 
 impl<T: CetkaikRepresentation> state::ExcitedState_<T> {
+    /// # Panics
+    /// Panics if the `ExcitedState` is invalid, i.e. if there is no piece at `flying_piece_src`.
     #[must_use]
     pub fn piece_at_flying_piece_src(&self) -> T::AbsolutePiece {
         piece_on_field_at::<T>(&self.c.f, self.c.flying_piece_src)
             .expect("Invalid `state::ExcitedState`: at `flying_piece_src` there is no piece")
     }
 
+    /// # Panics
+    /// Panics if the `ExcitedState` is invalid, i.e. if there is no piece at `flying_piece_step`.
     #[must_use]
     pub fn piece_at_flying_piece_step(&self) -> T::AbsolutePiece {
         piece_on_field_at::<T>(&self.c.f, self.c.flying_piece_step)
@@ -200,8 +399,12 @@ fn apply_tam_move<T: CetkaikRepresentation>(
     T::as_board_mut_absolute(&mut new_field).put(second_dest, Some(T::absolute_tam2()));
 
     Ok(Probabilistic::Pure(state::HandNotResolved_ {
-        previous_a_side_hop1zuo1: old_state.f.hop1zuo1_of(ASide).collect(),
-        previous_ia_side_hop1zuo1: old_state.f.hop1zuo1_of(IASide).collect(),
+        hands: old_state.hands,
+        zobrist_hash: old_state.zobrist_hash
+            ^ zobrist::tam_has_moved_previously(old_state.tam_has_moved_previously)
+            ^ zobrist_delta_on_board::<T>(&old_state.f, &new_field, &[src, second_dest]),
+        captured_piece: None,
+        dropped_piece: None,
 
         // When Tam2 moves, Tam2 is never stepped on (this assumption fails with the two-tam rule, which is not yet supported.)
         // 皇の動きで撃皇が発生することはない（二皇の場合は修正が必要）
@@ -211,6 +414,9 @@ fn apply_tam_move<T: CetkaikRepresentation>(
         rate: old_state.rate,
         i_have_moved_tam_in_this_turn: true,
         season: old_state.season,
+        season_first_mover: old_state.season_first_mover,
+        moves_in_this_season: old_state.moves_in_this_season,
+        positions_in_this_season: old_state.positions_in_this_season.clone(),
         scores: old_state.scores,
         whose_turn: old_state.whose_turn,
         f: new_field,
@@ -224,16 +430,25 @@ fn apply_nontam_move<T: CetkaikRepresentation>(
     step: Option<T::AbsoluteCoord>,
     config: Config,
 ) -> Result<Probabilistic<state::HandNotResolved_<T>>, &'static str> {
+    // The hash of `old_state` without `tam_has_moved_previously`
+    // `old_state` のハッシュ値から `tam_has_moved_previously` を除いたもの
+    let zobrist_hash = old_state.zobrist_hash
+        ^ zobrist::tam_has_moved_previously(old_state.tam_has_moved_previously);
     let nothing_happened = state::HandNotResolved_ {
-        previous_a_side_hop1zuo1: old_state.f.hop1zuo1_of(ASide).collect(),
-        previous_ia_side_hop1zuo1: old_state.f.hop1zuo1_of(IASide).collect(),
+        hands: old_state.hands,
+        zobrist_hash,
+        captured_piece: None,
+        dropped_piece: None,
         kut2tam2_happened: !config.failure_to_complete_the_move_means_exempt_from_kut2_tam2
-            && step.map_or(false, |step| {
+            && step.is_some_and(|step| {
                 T::as_board_absolute(&old_state.f).peek(step) == Some(T::absolute_tam2())
             }),
         rate: old_state.rate,
         i_have_moved_tam_in_this_turn: false,
         season: old_state.season,
+        season_first_mover: old_state.season_first_mover,
+        moves_in_this_season: old_state.moves_in_this_season,
+        positions_in_this_season: old_state.positions_in_this_season.clone(),
         scores: old_state.scores,
         whose_turn: old_state.whose_turn,
         f: old_state.f.clone(),
@@ -259,15 +474,25 @@ fn apply_nontam_move<T: CetkaikRepresentation>(
             old_state.whose_turn,
         )?;
 
+    let captured_piece = captured_at::<T>(&old_state.f, old_state.whose_turn, dest);
     let success = state::HandNotResolved_ {
-        previous_a_side_hop1zuo1: old_state.f.hop1zuo1_of(ASide).collect(),
-        previous_ia_side_hop1zuo1: old_state.f.hop1zuo1_of(IASide).collect(),
-        kut2tam2_happened: step.map_or(false, |step| {
+        hands: old_state.hands,
+        zobrist_hash: zobrist_hash
+            ^ zobrist_delta_on_board::<T>(&old_state.f, &new_field, &[src, dest])
+            ^ captured_piece.map_or(0, |piece| {
+                zobrist_delta_on_hop1zuo1::<T>(&old_state.f, old_state.whose_turn, piece, true)
+            }),
+        captured_piece,
+        dropped_piece: None,
+        kut2tam2_happened: step.is_some_and(|step| {
             piece_on_field_at::<T>(&old_state.f, step) == Some(T::absolute_tam2())
         }),
         rate: old_state.rate,
         i_have_moved_tam_in_this_turn: false,
         season: old_state.season,
+        season_first_mover: old_state.season_first_mover,
+        moves_in_this_season: old_state.moves_in_this_season,
+        positions_in_this_season: old_state.positions_in_this_season.clone(),
         scores: old_state.scores,
         whose_turn: old_state.whose_turn,
         f: new_field,
@@ -290,53 +515,293 @@ fn apply_nontam_move<T: CetkaikRepresentation>(
     Ok(Probabilistic::Pure(success))
 }
 
-/// When completely stuck, call this function to end the game.
-/// ／完全に手詰まりのときは、この関数を呼び出すことで即時決着がつく。
-pub fn no_move_possible_at_all<T: CetkaikRepresentation>(
+/// When completely stuck, call this function to end the game. If `config.player_without_any_legal_move_passes` is set, the player instead passes and the game goes on.
+/// ／完全に手詰まりのときは、この関数を呼び出すことで即時決着がつく。`config.player_without_any_legal_move_passes` が設定されているときは、代わりにパスとなりゲームが続く。
+pub fn no_move_possible_at_all<T: CetkaikRepresentation + Clone>(
+    old_state: &state::GroundState_<T>,
+    config: Config,
+) -> Result<state::HandResolved_<T>, &'static str> {
+    no_move_possible_at_all_with_setup(old_state, config, &setup::Setup::yhuap_initial())
+}
+
+/// Same as `no_move_possible_at_all`, except that the next season (if the season ends by `config.move_limit`) starts from the position described by `setup`.
+/// ／`no_move_possible_at_all` と同様だが、（`config.move_limit` により季節が終わるなら）次の季節は `setup` の局面から始まる。
+pub fn no_move_possible_at_all_with_setup<T: CetkaikRepresentation + Clone>(
     old_state: &state::GroundState_<T>,
     config: Config,
+    setup: &setup::Setup<T>,
 ) -> Result<state::HandResolved_<T>, &'static str> {
-    let (hop1zuo1_candidates, candidates) = old_state.get_candidates(config);
-    if hop1zuo1_candidates.is_empty() && candidates.is_empty() {
+    if old_state.has_any_legal_move(config) {
+        return Err("At least one valid move exists");
+    }
+
+    if config.player_without_any_legal_move_passes {
+        Ok(hand_over_the_turn(
+            state_after_pass(old_state),
+            config,
+            setup,
+        ))
+    } else {
         Ok(state::HandResolved_::GameEndsWithoutTymokTaxot(
             old_state.scores.which_side_is_winning(),
         ))
-    } else {
-        Err("At least one valid move exists")
     }
 }
 
+/// `Pass` sends `GroundState` directly to `HandResolved` without resolving any hand, since no piece moves and hence no hand can be formed.
+/// A pass is allowed either when `config.voluntary_pass_is_allowed` is set, or when the player has no valid move and `config.player_without_any_legal_move_passes` is set.
+/// ／`Pass` は役の判定を経ずに `GroundState` を直接 `HandResolved` に送る。駒が動かないので役は発生しえない。
+/// パスが許されるのは、`config.voluntary_pass_is_allowed` が設定されているときか、有効な手が存在せず `config.player_without_any_legal_move_passes` が設定されているときである。
+pub fn apply_pass<T: CetkaikRepresentation + Clone>(
+    old_state: &state::GroundState_<T>,
+    msg: message::Pass,
+    config: Config,
+) -> Result<state::HandResolved_<T>, &'static str> {
+    apply_pass_with_setup(old_state, msg, config, &setup::Setup::yhuap_initial())
+}
+
+/// Same as `apply_pass`, except that the next season (if the season ends by `config.move_limit`) starts from the position described by `setup`.
+/// ／`apply_pass` と同様だが、（`config.move_limit` により季節が終わるなら）次の季節は `setup` の局面から始まる。
+pub fn apply_pass_with_setup<T: CetkaikRepresentation + Clone>(
+    old_state: &state::GroundState_<T>,
+    _msg: message::Pass,
+    config: Config,
+    setup: &setup::Setup<T>,
+) -> Result<state::HandResolved_<T>, &'static str> {
+    if !config.voluntary_pass_is_allowed {
+        if old_state.has_any_legal_move(config) {
+            return Err("By config, it is prohibited to pass while a valid move exists.");
+        }
+        if !config.player_without_any_legal_move_passes {
+            return Err("By config, a player without any valid move cannot pass; call `no_move_possible_at_all` to end the game.");
+        }
+    }
+    Ok(hand_over_the_turn(
+        state_after_pass(old_state),
+        config,
+        setup,
+    ))
+}
+
+fn state_after_pass<T: CetkaikRepresentation>(
+    old_state: &state::GroundState_<T>,
+) -> state::GroundState_<T> {
+    state::GroundState_ {
+        hands: old_state.hands,
+        legal_move_cache: state::LegalMoveCache::default(),
+        zobrist_hash: old_state.zobrist_hash
+            ^ zobrist::side_to_move(old_state.whose_turn)
+            ^ zobrist::side_to_move(!old_state.whose_turn)
+            ^ zobrist::tam_has_moved_previously(old_state.tam_has_moved_previously),
+        f: old_state.f.clone(),
+        whose_turn: !old_state.whose_turn, /* hand the turn to the next person */
+        season: old_state.season,
+        season_first_mover: old_state.season_first_mover,
+        moves_in_this_season: old_state.moves_in_this_season + 1,
+        positions_in_this_season: old_state.positions_in_this_season.clone(),
+        scores: old_state.scores,
+        rate: old_state.rate,
+
+        // The player who passed did not move Tam2, so the next player may freely move it
+        // パスした人は皇を動かしていないので、次の人は皇を自由に動かしてよい
+        tam_has_moved_previously: false,
+    }
+}
+
+/// Called whenever the turn is handed over without any hand being formed. Records the new position, and applies `config.repetition` and `config.move_limit`.
+/// ／役が成立せずに手番が渡るときに呼ばれる。新しい局面を記録し、`config.repetition` と `config.move_limit` を適用する。
+fn hand_over_the_turn<T: CetkaikRepresentation + Clone>(
+    mut next: state::GroundState_<T>,
+    config: Config,
+    setup: &setup::Setup<T>,
+) -> state::HandResolved_<T> {
+    let key = repetition::position_key_of_state(&next);
+    next.positions_in_this_season.push(key);
+    let occurrences = next.positions_in_this_season.count(key);
+    if occurrences >= config.repetition.nth {
+        match config.repetition.consequence {
+            RepetitionConsequence::Allowed => {}
+            RepetitionConsequence::Penalized { penalty } => {
+                /* the side that has just moved is the one that repeated the position */
+                match next.scores.edit(penalty, !next.whose_turn, next.rate) {
+                    Err(victor) => return state::HandResolved_::GameEndsWithoutTymokTaxot(victor),
+                    Ok(new_scores) => {
+                        next.zobrist_hash ^=
+                            zobrist::scores(next.scores) ^ zobrist::scores(new_scores);
+                        next.scores = new_scores;
+                    }
+                }
+            }
+            RepetitionConsequence::SeasonEndsWithoutScoreChange => {
+                return state::HandResolved_::SeasonEndsWithoutTymokTaxot(end_of_season(
+                    next.season,
+                    next.scores,
+                    None,
+                    next.season_first_mover,
+                    config,
+                    setup,
+                ));
+            }
+            RepetitionConsequence::GameEndsInADraw => {
+                return state::HandResolved_::GameEndsWithoutTymokTaxot(Victor(None));
+            }
+        }
+    }
+
+    match config.move_limit {
+        Some(MoveLimit { moves, outcome }) if next.moves_in_this_season >= moves => {
+            let season_winner = match outcome {
+                MoveLimitOutcome::GameEndsInADraw => {
+                    return state::HandResolved_::GameEndsWithoutTymokTaxot(Victor(None));
+                }
+                MoveLimitOutcome::SeasonEndsWithoutScoreChange => None,
+                MoveLimitOutcome::SideAheadWinsTheSeason => next.scores.which_side_is_winning().0,
+            };
+            state::HandResolved_::SeasonEndsWithoutTymokTaxot(end_of_season(
+                next.season,
+                next.scores,
+                season_winner,
+                next.season_first_mover,
+                config,
+                setup,
+            ))
+        }
+        _ => state::HandResolved_::NeitherTymokNorTaxot(next),
+    }
+}
+
+/// Either proceeds to the next season or, if the current season was the last one, ends the game.
+/// ／次の季節に進むか、最後の季節であればゲームを終える。
+fn end_of_season<T: CetkaikRepresentation + Clone>(
+    season: Season,
+    new_scores: Scores,
+    season_winner: Option<AbsoluteSide>,
+    season_first_mover: AbsoluteSide,
+    config: Config,
+    setup: &setup::Setup<T>,
+) -> IfTaxot_<T> {
+    season.next().map_or(
+        /* All seasons have ended */
+        IfTaxot_::VictoriousSide(new_scores.which_side_is_winning()),
+        /* The next season exists */
+        |next_season| {
+            IfTaxot_::NextSeason(beginning_of_season(
+                &setup.f,
+                next_season,
+                new_scores,
+                Rate::X1,
+                config
+                    .who_goes_first
+                    .decide(season_winner, season_first_mover),
+            ))
+        },
+    )
+}
+
 /// `NormalMove` sends `GroundState` to `Probabilistic<HandNotResolved>`
 pub fn apply_normal_move<T: CetkaikRepresentation>(
     old_state: &state::GroundState_<T>,
     msg: message::NormalMove_<T::AbsoluteCoord>,
     config: Config,
 ) -> Result<Probabilistic<state::HandNotResolved_<T>>, &'static str> {
-    let (hop1zuo1_candidates, candidates) = old_state.get_candidates(config);
+    let mv = message::PureMove__::NormalMove(msg);
+    let accepted = match msg {
+        message::NormalMove_::NonTamMoveFromHopZuo { dest, .. } => {
+            if drop_is_restricted::<T>(config, old_state.whose_turn, &old_state.f)(dest) {
+                return Err(
+                    "By config, it is prohibited to place a piece from hop1zuo1 onto this square.",
+                );
+            }
+
+            // All illegal moves from hop1zuo1 are trivially illegal
+            // (that is, "you can't place onto a non-empty square" and "you can't place what you don't have").
+            // 持ち駒から打つ際の違法手というのは自明なもの（「空いていないマスには打てない」「持っていない駒は打てない」）しかない。
+            if !old_state.legal_move_set(config).contains(mv) {
+                return Err(
+                    "Cannot find an adequate piece to place, or the destination is occupied",
+                );
+            }
+            Ok(())
+        }
+        message::NormalMove_::TamMoveNoStep { .. } => {
+            if old_state.legal_move_set(config).contains(mv) {
+                Ok(())
+            } else {
+                Err("The provided TamMoveNoStep was rejected by the crate `cetkaik_yhuap_move_candidates`.")
+            }
+        }
+        message::NormalMove_::TamMoveStepsDuringFormer { .. } => {
+            if old_state.legal_move_set(config).contains(mv) {
+                Ok(())
+            } else {
+                Err("The provided TamMoveStepsDuringFormer was rejected by the crate `cetkaik_yhuap_move_candidates`.")
+            }
+        }
+        message::NormalMove_::TamMoveStepsDuringLatter { .. } => {
+            if old_state.legal_move_set(config).contains(mv) {
+                Ok(())
+            } else {
+                Err("The provided TamMoveStepsDuringLatter was rejected by the crate `cetkaik_yhuap_move_candidates`.")
+            }
+        }
+        message::NormalMove_::NonTamMoveSrcDst { .. } => {
+            if old_state.legal_move_set(config).contains(mv) {
+                Ok(())
+            } else {
+                Err("The provided NonTamMoveSrcDst was rejected by the crate `cetkaik_yhuap_move_candidates`.")
+            }
+        }
+        message::NormalMove_::NonTamMoveSrcStepDstFinite { .. } => {
+            if old_state.legal_move_set(config).contains(mv) {
+                Ok(())
+            } else {
+                Err("The provided NonTamMoveSrcStepDstFinite was rejected by the crate `cetkaik_yhuap_move_candidates`.")
+            }
+        }
+    };
+    accepted?;
+    apply_normal_move_unchecked(old_state, msg, config)
+}
+
+/// Same as `apply_normal_move`, but without checking the move again: `legal` was found legal by `GroundState_::legal_moves`.
+/// ／`apply_normal_move` と同じだが手を再検査しない。`legal` は `GroundState_::legal_moves` によって合法と分かっている。
+///
+/// # Panics
+/// Never panics, unless `cetkaik_yhuap_move_candidates` generated a move that this crate cannot apply.
+#[must_use]
+pub fn apply_legal_normal_move<T: CetkaikRepresentation>(
+    legal: state::Legal<'_, T, message::NormalMove_<T::AbsoluteCoord>>,
+) -> Probabilistic<state::HandNotResolved_<T>> {
+    apply_normal_move_unchecked(legal.state(), legal.msg(), legal.config())
+        .expect("inconsistencies found between cetkaik_yhuap_move_candidates and cetkaik_full_state_transition")
+}
+
+/// The part of `apply_normal_move` after `msg` is known to be one of `old_state.get_candidates(config)`.
+/// ／`apply_normal_move` のうち、`msg` が `old_state.get_candidates(config)` の一つであると分かった後の部分。
+fn apply_normal_move_unchecked<T: CetkaikRepresentation>(
+    old_state: &state::GroundState_<T>,
+    msg: message::NormalMove_<T::AbsoluteCoord>,
+    config: Config,
+) -> Result<Probabilistic<state::HandNotResolved_<T>>, &'static str> {
     match msg {
         message::NormalMove_::NonTamMoveFromHopZuo { color, prof, dest } => {
             let new_field = old_state
                 .f
                 .search_from_hop1zuo1_and_parachute_at(color, prof, old_state.whose_turn, dest)
                 .ok_or("Cannot find an adequate piece to place, or the destination is occupied")?;
-
-            // For the sake of consistency, `cetkaik_yhuap_move_candidates` is called,
-            // but since all illegal moves from hop1zuo1 are those that are trivially illegal
-            // (that is, "you can't place onto a non-empty square" and "you can't place what you don't have"),
-            // `cetkaik_yhuap_move_candidates` should never report a failure.
-            // that is why it is not `return Err` but is `unreachable`.
-            // 念のため `cetkaik_yhuap_move_candidates` を呼び出しておくが、
-            // 持ち駒から打つ際の違法手というのが実は自明なものを除いて不存在なので、
-            // ここで弾かれるとしたらコードがバグっているということになる。
-            // したがって、 return Err ではなく unreachable としてある。
-
-            if !hop1zuo1_candidates.contains(&message::PureMove__::NormalMove(msg)) {
-                unreachable!("inconsistencies found between cetkaik_yhuap_move_candidates::PureMove::NonTamMoveFromHopZuo and cetkaik_full_state_transition::apply_nontam_move");
-            }
-
             Ok(Probabilistic::Pure(state::HandNotResolved_ {
-                previous_a_side_hop1zuo1: old_state.f.hop1zuo1_of(ASide).collect(),
-                previous_ia_side_hop1zuo1: old_state.f.hop1zuo1_of(IASide).collect(),
+                hands: old_state.hands,
+                zobrist_hash: old_state.zobrist_hash
+                    ^ zobrist::tam_has_moved_previously(old_state.tam_has_moved_previously)
+                    ^ zobrist_delta_on_board::<T>(&old_state.f, &new_field, &[dest])
+                    ^ zobrist_delta_on_hop1zuo1::<T>(
+                        &old_state.f,
+                        old_state.whose_turn,
+                        cetkaik_fundamental::ColorAndProf { color, prof },
+                        false,
+                    ),
+                captured_piece: None,
+                dropped_piece: Some(cetkaik_fundamental::ColorAndProf { color, prof }),
 
                 // The stepping of Tam2 never occurs if you are playing from hop1zuo1
                 // 持ち駒から打つ際には撃皇は決して起こらない
@@ -344,6 +809,9 @@ pub fn apply_normal_move<T: CetkaikRepresentation>(
                 rate: old_state.rate,
                 i_have_moved_tam_in_this_turn: false,
                 season: old_state.season,
+                season_first_mover: old_state.season_first_mover,
+                moves_in_this_season: old_state.moves_in_this_season,
+                positions_in_this_season: old_state.positions_in_this_season.clone(),
                 scores: old_state.scores,
                 whose_turn: old_state.whose_turn,
                 f: new_field,
@@ -355,55 +823,30 @@ pub fn apply_normal_move<T: CetkaikRepresentation>(
             src,
             first_dest,
             second_dest,
-        } => {
-            if candidates.contains(&message::PureMove__::NormalMove(msg)) {
-                apply_tam_move::<T>(old_state, src, first_dest, second_dest, None, config)
-            } else {
-                Err("The provided TamMoveNoStep was rejected by the crate `cetkaik_yhuap_move_candidates`.")
-            }
-        }
+        } => apply_tam_move::<T>(old_state, src, first_dest, second_dest, None, config),
         message::NormalMove_::TamMoveStepsDuringFormer {
             src,
             first_dest,
             second_dest,
             step,
-        } => {
-            if candidates.contains(&message::PureMove__::NormalMove(msg)) {
-                apply_tam_move::<T>(old_state, src, first_dest, second_dest, Some(step), config)
-            } else {
-                Err("The provided TamMoveStepsDuringFormer was rejected by the crate `cetkaik_yhuap_move_candidates`.")
-            }
         }
-        message::NormalMove_::TamMoveStepsDuringLatter {
+        | message::NormalMove_::TamMoveStepsDuringLatter {
             src,
             first_dest,
             second_dest,
             step,
-        } => {
-            if candidates.contains(&message::PureMove__::NormalMove(msg)) {
-                apply_tam_move(old_state, src, first_dest, second_dest, Some(step), config)
-            } else {
-                Err("The provided TamMoveStepsDuringLatter was rejected by the crate `cetkaik_yhuap_move_candidates`.")
-            }
-        }
-
+        } => apply_tam_move::<T>(old_state, src, first_dest, second_dest, Some(step), config),
         message::NormalMove_::NonTamMoveSrcDst { src, dest } => {
-            if candidates.contains(&message::PureMove__::NormalMove(msg)) {
-                apply_nontam_move(old_state, src, dest, None, config)
-            } else {
-                Err("The provided NonTamMoveSrcDst was rejected by the crate `cetkaik_yhuap_move_candidates`.")
-            }
+            apply_nontam_move(old_state, src, dest, None, config)
         }
         message::NormalMove_::NonTamMoveSrcStepDstFinite { src, step, dest } => {
-            if candidates.contains(&message::PureMove__::NormalMove(msg)) {
-                apply_nontam_move(old_state, src, dest, Some(step), config)
-            } else {
-                Err("The provided NonTamMoveSrcStepDstFinite was rejected by the crate `cetkaik_yhuap_move_candidates`.")
-            }
+            apply_nontam_move(old_state, src, dest, Some(step), config)
         }
     }
 }
 
+/// `InfAfterStep` sends `GroundState` to `Probabilistic<ExcitedState>`
+///
 /// ```
 /// use cetkaik_fundamental::*;
 /// use cetkaik_full_state_transition::message::InfAfterStep_;
@@ -413,23 +856,22 @@ pub fn apply_normal_move<T: CetkaikRepresentation>(
 /// use cetkaik_naive_representation::absolute::Row::*;
 /// use cetkaik_naive_representation::absolute::Column::*;
 /// use cetkaik_naive_representation::CetkaikNaive;
-/// let ia_first = state::GroundState_::<CetkaikNaive> {
-///     whose_turn: AbsoluteSide::IASide,
-///     scores: Scores::new(),
-///     rate: Rate::X1,
-///     season: Season::Iei2,
-///     tam_has_moved_previously: false,
-///     f: absolute::Field {
+/// let ia_first = state::GroundState_::<CetkaikNaive>::new(
+///     absolute::Field {
 ///         a_side_hop1zuo1: vec![],
 ///         ia_side_hop1zuo1: vec![],
 ///         board: cetkaik_naive_representation::absolute::yhuap_initial_board(),
 ///     },
-/// };
+///     AbsoluteSide::IASide,
+///     Season::Iei2,
+///     AbsoluteSide::IASide,
+///     Scores::new(),
+///     Rate::X1,
+///     false,
+/// );
 /// let inf_after_step = InfAfterStep_ { src: Coord(AU, L), step: Coord(AU, K), planned_direction: Coord(AU, L) };
 /// apply_inf_after_step(&ia_first, inf_after_step, Config::cerke_online_alpha()).unwrap();
 /// ```
-
-/// `InfAfterStep` sends `GroundState` to `Probabilistic<ExcitedState>`
 pub fn apply_inf_after_step<T: CetkaikRepresentation + Clone>(
     old_state: &state::GroundState_<T>,
     msg: message::InfAfterStep_<T::AbsoluteCoord>,
@@ -443,33 +885,53 @@ pub fn apply_inf_after_step<T: CetkaikRepresentation + Clone>(
         return Err("In InfAfterStep, `step` is not occupied; illegal");
     }
 
-    let (_hop1zuo1, candidates) = old_state.get_candidates(config);
-
-    if !candidates.into_iter().any(|cand| match cand {
-        message::PureMove__::InfAfterStep(message::InfAfterStep_ {
-            src,
-            step,
-            planned_direction: _,
-        }) => src == msg.src && step == msg.step,
-        message::PureMove__::NormalMove(_) => false,
-    }) {
+    // `legal_move_set` looks only at `src` and `step` of an `InfAfterStep`, so `planned_direction` is not checked
+    // `legal_move_set` は `InfAfterStep` の `src` と `step` しか見ないので、`planned_direction` は確かめない
+    if !old_state
+        .legal_move_set(config)
+        .contains(message::PureMove__::InfAfterStep(msg))
+    {
         return Err(
             "The provided InfAfterStep was rejected by the crate `cetkaik_yhuap_move_candidates`.",
         );
     }
 
+    Ok(apply_inf_after_step_unchecked(old_state, msg))
+}
+
+/// Same as `apply_inf_after_step`, but without checking the move again: `legal` was found legal by `GroundState_::legal_moves`.
+/// ／`apply_inf_after_step` と同じだが手を再検査しない。`legal` は `GroundState_::legal_moves` によって合法と分かっている。
+#[must_use]
+pub fn apply_legal_inf_after_step<T: CetkaikRepresentation + Clone>(
+    legal: state::Legal<'_, T, message::InfAfterStep_<T::AbsoluteCoord>>,
+) -> Probabilistic<state::ExcitedState_<T>> {
+    apply_inf_after_step_unchecked(legal.state(), legal.msg())
+}
+
+/// The part of `apply_inf_after_step` after `msg` is known to be legal.
+/// ／`apply_inf_after_step` のうち、`msg` が合法であると分かった後の部分。
+fn apply_inf_after_step_unchecked<T: CetkaikRepresentation + Clone>(
+    old_state: &state::GroundState_<T>,
+    msg: message::InfAfterStep_<T::AbsoluteCoord>,
+) -> Probabilistic<state::ExcitedState_<T>> {
     let c: state::ExcitedStateWithoutCiurl_<T> = state::ExcitedStateWithoutCiurl_ {
+        hands: old_state.hands,
+        zobrist_hash: old_state.zobrist_hash
+            ^ zobrist::tam_has_moved_previously(old_state.tam_has_moved_previously),
         f: old_state.f.clone(),
         whose_turn: old_state.whose_turn,
         flying_piece_src: msg.src,
         flying_piece_step: msg.step,
         flying_piece_planned_direction: msg.planned_direction,
         season: old_state.season,
+        season_first_mover: old_state.season_first_mover,
+        moves_in_this_season: old_state.moves_in_this_season,
+        positions_in_this_season: old_state.positions_in_this_season.clone(),
         scores: old_state.scores,
         rate: old_state.rate,
     };
 
-    Ok(Probabilistic::Sticks {
+    Probabilistic::Sticks {
         s0: state::ExcitedState_ {
             c: c.clone(),
             ciurl: 0,
@@ -491,13 +953,85 @@ pub fn apply_inf_after_step<T: CetkaikRepresentation + Clone>(
             ciurl: 4,
         },
         s5: state::ExcitedState_ { c, ciurl: 5 },
-    })
+    }
 }
 
 mod score;
 
 pub use score::Scores;
 
+/// Describes custom initial positions, such as textbook positions and handicaps.
+/// ／教本の局面や駒落ちなど、独自の開始局面を表現する。
+pub mod setup;
+
+/// Detects the repetition of positions within a season.
+/// ／季節内での局面の繰り返し（千日手）を検出する。
+pub mod repetition;
+
+/// Searches the game tree, taking the chance nodes (the casts of sticks) into account.
+/// ／偶然手番（投げ棒）を考慮してゲーム木を探索する。
+pub mod search;
+
+/// A Monte Carlo Tree Search player.
+/// ／モンテカルロ木探索によるプレイヤー。
+pub mod mcts;
+
+/// A common interface for bots, an arena to play them against each other, and a few reference players.
+/// ／ボットの共通インターフェース、ボット同士を対局させる場、およびいくつかの参考用プレイヤー。
+pub mod player;
+
+/// Runs tournaments between players and estimates their Elo ratings.
+/// ／プレイヤー同士の大会を行い、Elo レーティングを推定する。
+pub mod tournament;
+
+/// Counts the tree of legal moves for regression testing.
+/// ／回帰テストのために合法手の木を数える。
+pub mod perft;
+
+/// Scores a state without searching, with tunable weights.
+/// ／重みを調整できる、探索を伴わない状態の評価。
+pub mod evaluation;
+
+/// Finds the hands that are one or two captures away, and the moves that capture toward them.
+/// ／あと一つか二つ駒を取れば成立する役と、そのために駒を取る手を求める。
+pub mod hand_proximity;
+
+/// Advises whether to choose tymok or taxot.
+/// ／再行と終季のどちらを選ぶべきかを助言する。
+pub mod advisor;
+
+/// Zobrist hashing of states, maintained incrementally by the transition functions.
+/// ／状態の Zobrist ハッシュ。状態遷移関数によって差分更新される。
+pub mod zobrist;
+
+/// A transposition table for the search, which can be shared across threads.
+/// ／スレッド間で共有できる、探索のための置換表。
+pub mod transposition;
+
+/// An opening book built from game records, with a compact file format.
+/// ／対局の記録から作る定跡。コンパクトなファイル形式を持つ。
+pub mod book;
+
+/// The probability of winning the game, estimated from the scores alone.
+/// ／得点のみから見積もったゲームの勝率。
+pub mod win_probability;
+
+/// Applying a move in place and taking it back, for searches that visit many positions.
+/// ／多くの局面を訪れる探索のために、手をその場で適用し、取り消す。
+pub mod make_unmake;
+
+/// A representation with fixed-size arrays, which is cheaper to clone and to hash than `cetkaik_naive_representation`.
+/// ／固定長の配列による表現。`cetkaik_naive_representation` よりも複製とハッシュが安い。
+#[cfg(feature = "naive")]
+pub mod compact;
+
+/// The hands that each side's hop1zuo1 forms, maintained incrementally as pieces are captured and dropped.
+/// ／両者の手駒がなす役。駒が取られたり打たれたりするたびに差分更新される。
+pub mod hands;
+
+#[cfg(test)]
+mod fixtures;
+
 /// `AfterHalfAcceptance` sends `ExcitedState` to `Probabilistic<HandNotResolved>`
 pub fn apply_after_half_acceptance<T: CetkaikRepresentation>(
     old_state: &state::ExcitedState_<T>,
@@ -505,13 +1039,18 @@ pub fn apply_after_half_acceptance<T: CetkaikRepresentation>(
     config: Config,
 ) -> Result<Probabilistic<state::HandNotResolved_<T>>, &'static str> {
     let nothing_happened = state::HandNotResolved_ {
-        previous_a_side_hop1zuo1: old_state.c.f.hop1zuo1_of(ASide).collect(),
-        previous_ia_side_hop1zuo1: old_state.c.f.hop1zuo1_of(IASide).collect(),
+        hands: old_state.c.hands,
+        zobrist_hash: old_state.c.zobrist_hash,
+        captured_piece: None,
+        dropped_piece: None,
         kut2tam2_happened: !config.failure_to_complete_the_move_means_exempt_from_kut2_tam2
             && old_state.piece_at_flying_piece_step() == T::absolute_tam2(),
         rate: old_state.c.rate,
         i_have_moved_tam_in_this_turn: false,
         season: old_state.c.season,
+        season_first_mover: old_state.c.season_first_mover,
+        moves_in_this_season: old_state.c.moves_in_this_season,
+        positions_in_this_season: old_state.c.positions_in_this_season.clone(),
         scores: old_state.c.scores,
         whose_turn: old_state.c.whose_turn,
         f: old_state.c.f.clone(),
@@ -539,13 +1078,32 @@ pub fn apply_after_half_acceptance<T: CetkaikRepresentation>(
                 old_state.c.whose_turn,
             )?;
 
+        let captured_piece = captured_at::<T>(&old_state.c.f, old_state.c.whose_turn, dest);
         let success = state::HandNotResolved_ {
-            previous_a_side_hop1zuo1: old_state.c.f.hop1zuo1_of(ASide).collect(),
-            previous_ia_side_hop1zuo1: old_state.c.f.hop1zuo1_of(IASide).collect(),
+            hands: old_state.c.hands,
+            zobrist_hash: old_state.c.zobrist_hash
+                ^ zobrist_delta_on_board::<T>(
+                    &old_state.c.f,
+                    &new_field,
+                    &[old_state.c.flying_piece_src, dest],
+                )
+                ^ captured_piece.map_or(0, |piece| {
+                    zobrist_delta_on_hop1zuo1::<T>(
+                        &old_state.c.f,
+                        old_state.c.whose_turn,
+                        piece,
+                        true,
+                    )
+                }),
+            captured_piece,
+            dropped_piece: None,
             kut2tam2_happened: old_state.piece_at_flying_piece_step() == T::absolute_tam2(),
             rate: old_state.c.rate,
             i_have_moved_tam_in_this_turn: false,
             season: old_state.c.season,
+            season_first_mover: old_state.c.season_first_mover,
+            moves_in_this_season: old_state.c.moves_in_this_season,
+            positions_in_this_season: old_state.c.positions_in_this_season.clone(),
             scores: old_state.c.scores,
             whose_turn: old_state.c.whose_turn,
             f: new_field,
@@ -598,22 +1156,22 @@ pub enum IfTaxot_<T: CetkaikRepresentation> {
 #[readonly::make]
 #[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
 pub struct Config {
-    /// Describes whether the Stepping of Tam2 is considered a hand. If `false`, the Stepping of Tam2 results in the immediate subtraction of 5 points and does not trigger the taxot / tymok unless another hand is simultaneously created.
-    /// ／撃皇が役であるかどうかのフラグ。`false`である場合、撃皇は即時5点減点であり、同時に他の役が成立していない限り終季・再行の判定を発生させない。
-    pub step_tam_is_a_hand: bool,
+    /// Describes how the Stepping of Tam2 is treated. `Penalized { penalty: -5, is_a_hand: true }` makes it a hand worth -5 that triggers the taxot / tymok by itself; with `is_a_hand: false`, the penalty is subtracted immediately and the taxot / tymok is not triggered unless another hand is simultaneously created. `Forbidden` removes the moves that step on Tam2 from the candidates.
+    /// ／撃皇の扱い。`Penalized { penalty: -5, is_a_hand: true }` ならば撃皇は-5点の役であり、それのみで終季・再行の判定を発生させる。`is_a_hand: false` ならば即時減点であり、同時に他の役が成立していない限り終季・再行の判定を発生させない。`Forbidden` ならば皇を踏む手は候補から除かれる。
+    pub kut2tam2: Consequence,
 
     /// Described whether the square that Tam2 itself is in is considered as a tam2 hue. This matters only when you are stepping a Tam2.
     /// ／皇のあるマス自身が皇処になるかどうかのフラグ。撃皇をするときにのみ関係のあるフラグ。
     pub tam_itself_is_tam_hue: bool,
 
     /// hsjoihs 2020/02/18
-    /// 「@SY 皇をもとの位置に戻す皇再来と、相手が動かした後の皇動かしによる皇再来を言い分けたいときってどうするんだろう（cerke_onlineは後者のみを禁じており、前者に関しては無罰則）」
+    /// 「@SY 皇をもとの位置に戻す皇再来と、相手が動かした後の皇動かしによる皇再来を言い分けたいときってどうするんだろう（`cerke_online`は後者のみを禁じており、前者に関しては無罰則）」
     /// SY 2020/02/18 - 2020/02/19
     /// 「前者は皇無行とかっぽそう。後者が狭義の皇再来なのかもしれん。ただややこしい」
     pub moving_tam_immediately_after_tam_has_moved: Consequence,
 
     /// hsjoihs 2020/02/18
-    /// 「@SY 皇をもとの位置に戻す皇再来と、相手が動かした後の皇動かしによる皇再来を言い分けたいときってどうするんだろう（cerke_onlineは後者のみを禁じており、前者に関しては無罰則）」
+    /// 「@SY 皇をもとの位置に戻す皇再来と、相手が動かした後の皇動かしによる皇再来を言い分けたいときってどうするんだろう（`cerke_online`は後者のみを禁じており、前者に関しては無罰則）」
     /// SY 2020/02/18 - 2020/02/19
     /// 「前者は皇無行とかっぽそう。後者が狭義の皇再来なのかもしれん。ただややこしい」
     pub tam_mun_mok: Consequence,
@@ -626,6 +1184,110 @@ pub struct Config {
 
     /// 投げ棒を投げる前になにを表明しなければならないのか。None ならなにも表明しなくてよく、ExactDestination なら目的地を宣言し、Direction なら方向を宣言する
     pub what_to_say_before_casting_sticks: Option<Plan>,
+
+    /// Describes who moves first in each season. Only `FirstMover::Fixed` decides the first season; otherwise it is decided at random (see `FirstMover::decide_first_season`).
+    /// ／各季節の先手の決め方。最初の季節の先手を決めるのは `FirstMover::Fixed` のみであり、それ以外では無作為に決まる（`FirstMover::decide_first_season` を参照）。
+    pub who_goes_first: FirstMover,
+
+    /// Whether a player may pass from `GroundState` even when a valid move exists.
+    /// ／有効な手が存在するときにも `GroundState` からパスしてよいかどうか。
+    pub voluntary_pass_is_allowed: bool,
+
+    /// Whether a player without any valid move passes instead of ending the game.
+    /// Should neither player have any valid move, they would pass forever; use `repetition` or `move_limit` to end such a season. Since a pass frees Tam2 for the next player, this hardly ever happens.
+    /// ／有効な手が一つもない場合に、ゲームを終わらせる代わりにパスするかどうか。
+    /// 両者とも有効な手がなければ永遠にパスし続けることになるので、そのような季節を終わらせるには `repetition` か `move_limit` を用いる。パスの後は次の人が皇を自由に動かせるので、これはまず起こらない。
+    pub player_without_any_legal_move_passes: bool,
+
+    /// Describes where a piece from hop1zuo1 cannot be placed.
+    /// ／持ち駒を打てない場所。
+    pub drop_restriction: DropRestriction,
+
+    /// Describes what happens when a season lasts too long. `None` means that a season may last forever.
+    /// ／季節が長引きすぎたときにどうなるか。`None` ならば季節はいつまでも続きうる。
+    pub move_limit: Option<MoveLimit>,
+
+    /// Describes what happens when the same position appears again and again within a season.
+    /// ／季節内で同じ局面が何度も現れたときにどうなるか。
+    pub repetition: RepetitionRule,
+}
+
+/// A position counts as the same if the board, both hop1zuo1, the side to move and whether Tam2 has moved in the previous turn all agree. The rule applies when the position appears for the `nth` time (and every time after that).
+/// ／盤面・両者の手駒・手番・前の手番で皇が動いたかが全て一致すれば同じ局面とみなす。`nth` 回目に（およびそれ以降毎回）局面が現れたときに規定が適用される。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub struct RepetitionRule {
+    pub nth: usize,
+    pub consequence: RepetitionConsequence,
+}
+
+/// Describes what happens to the player who has repeated a position.
+/// ／局面を繰り返したプレイヤーに何が起こるかを表現する型。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum RepetitionConsequence {
+    Allowed,
+
+    /// The player who has just made the move loses `-penalty` points (multiplied by the rate).
+    /// ／その手を指したプレイヤーが（レート倍された）`-penalty` 点を失う。
+    Penalized {
+        penalty: i32,
+    },
+
+    /// The season ends with no score change, and neither side is regarded as the winner of the season.
+    /// ／点の移動なしに季節が終わり、どちらもその季節の勝者とはみなされない。
+    SeasonEndsWithoutScoreChange,
+
+    /// The game ends immediately as a draw.
+    /// ／ゲームが引き分けで即座に終わる。
+    GameEndsInADraw,
+}
+
+/// Describes the maximum number of moves in a season, and what happens when it is reached without any hand being formed.
+/// ／一つの季節で指せる手数の上限と、役が成立しないままそれに達したときにどうなるか。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub struct MoveLimit {
+    /// Counts the moves of both players, including passes.
+    /// ／両プレイヤーの手を、パスも含めて数える。
+    pub moves: u32,
+    pub outcome: MoveLimitOutcome,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum MoveLimitOutcome {
+    /// The season ends with no score change, and neither side is regarded as the winner of the season.
+    /// ／点の移動なしに季節が終わり、どちらもその季節の勝者とはみなされない。
+    SeasonEndsWithoutScoreChange,
+
+    /// The season ends with no score change, and the side with more points is regarded as the winner of the season (which matters for `FirstMover`).
+    /// ／点の移動なしに季節が終わり、点の多い側がその季節の勝者とみなされる（`FirstMover` に関係する）。
+    SideAheadWinsTheSeason,
+
+    /// The game ends immediately as a draw.
+    /// ／ゲームが引き分けで即座に終わる。
+    GameEndsInADraw,
+}
+
+/// Describes the house rules that forbid placing a piece from hop1zuo1 onto certain squares. The official rule has no such restriction.
+/// ／持ち駒を特定のマスに打つことを禁じる独自ルールを表現する型。官定にはこのような制限はない。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DropRestriction {
+    /// 皇処に打てない
+    pub no_drop_into_tam_hue: bool,
+
+    /// 皇水に打てない
+    pub no_drop_into_water: bool,
+
+    /// 相手の陣地（相手の駒が初期配置で置かれている三列）に打てない
+    pub no_drop_onto_opponent_home_rows: bool,
+}
+
+impl DropRestriction {
+    /// No restriction at all, as in the official rule.
+    /// ／官定どおり、一切の制限なし。
+    pub const NONE: Self = Self {
+        no_drop_into_tam_hue: false,
+        no_drop_into_water: false,
+        no_drop_onto_opponent_home_rows: false,
+    };
 }
 
 #[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
@@ -634,6 +1296,64 @@ pub enum Plan {
     ExactDestination,
 }
 
+/// Describes how the first mover of a new season is decided.
+/// ／新しい季節の先手の決め方を表現する型。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum FirstMover {
+    /// Decided at random, e.g. by casting sticks.
+    /// ／投げ棒などで無作為に決める。
+    Random,
+
+    /// The side that lost points in the previous season moves first.
+    /// ／前の季節で点を失った側が先手。
+    LoserOfPreviousSeason,
+
+    /// The side that gained points in the previous season moves first.
+    /// ／前の季節で点を得た側が先手。
+    WinnerOfPreviousSeason,
+
+    /// The side that moved second in the previous season moves first.
+    /// ／前の季節で後手だった側が先手。
+    Alternate,
+
+    /// The specified side always moves first.
+    /// ／常に指定した側が先手。
+    Fixed(AbsoluteSide),
+}
+
+impl FirstMover {
+    /// Decides the first mover of the next season. `None` means that it is decided at random.
+    /// When the previous season ended without any score change, there is neither a winner nor a loser, and hence `LoserOfPreviousSeason` and `WinnerOfPreviousSeason` also fall back to `None`. This includes a season ended by a hand whose raw score (penalties included) is zero, as well as `MoveLimitOutcome::SeasonEndsWithoutScoreChange` and `RepetitionConsequence::SeasonEndsWithoutScoreChange`.
+    /// ／次の季節の先手を決める。`None` は無作為に決まることを表す。前の季節が点の移動なしに終わった場合は勝者も敗者もいないので、`LoserOfPreviousSeason` と `WinnerOfPreviousSeason` も `None` となる。（減点込みの）素点が 0 の役で終季した場合や、`MoveLimitOutcome::SeasonEndsWithoutScoreChange` と `RepetitionConsequence::SeasonEndsWithoutScoreChange` の場合もこれに当たる。
+    #[must_use]
+    pub fn decide(
+        self,
+        previous_season_winner: Option<AbsoluteSide>,
+        previous_season_first_mover: AbsoluteSide,
+    ) -> Option<AbsoluteSide> {
+        match self {
+            Self::Random => None,
+            Self::LoserOfPreviousSeason => previous_season_winner.map(|winner| !winner),
+            Self::WinnerOfPreviousSeason => previous_season_winner,
+            Self::Alternate => Some(!previous_season_first_mover),
+            Self::Fixed(side) => Some(side),
+        }
+    }
+
+    /// Decides the first mover of the first season. `None` means that it is decided at random. Since there is no previous season, only `Fixed` decides the first mover.
+    /// ／最初の季節の先手を決める。`None` は無作為に決まることを表す。前の季節がないので、先手を決めるのは `Fixed` のみである。
+    #[must_use]
+    pub const fn decide_first_season(self) -> Option<AbsoluteSide> {
+        match self {
+            Self::Fixed(side) => Some(side),
+            Self::Random
+            | Self::LoserOfPreviousSeason
+            | Self::WinnerOfPreviousSeason
+            | Self::Alternate => None,
+        }
+    }
+}
+
 /// Describes whether an action is forbidden, penalized, or allowed without any penalty.
 /// 行為が禁止されるか、罰則付きであるか、それとも許容されるかを表現する型。
 #[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
@@ -648,13 +1368,22 @@ impl Config {
     #[must_use]
     pub const fn cerke_online_alpha() -> Self {
         Self {
-            step_tam_is_a_hand: false,
+            kut2tam2: Consequence::Allowed,
             tam_itself_is_tam_hue: true,
             moving_tam_immediately_after_tam_has_moved: Consequence::Forbidden,
             tam_mun_mok: Consequence::Allowed,
             failure_to_complete_the_move_means_exempt_from_kut2_tam2: false,
             game_can_end_without_tymok_taxot_because_of_negative_hand: true,
             what_to_say_before_casting_sticks: Some(Plan::Direction),
+            who_goes_first: FirstMover::Random,
+            voluntary_pass_is_allowed: false,
+            player_without_any_legal_move_passes: false,
+            drop_restriction: DropRestriction::NONE,
+            move_limit: None,
+            repetition: RepetitionRule {
+                nth: 3,
+                consequence: RepetitionConsequence::Allowed,
+            },
         }
     }
 
@@ -662,7 +1391,10 @@ impl Config {
     #[must_use]
     pub const fn strict_y1_huap1() -> Self {
         Self {
-            step_tam_is_a_hand: true,
+            kut2tam2: Consequence::Penalized {
+                penalty: -5,
+                is_a_hand: true,
+            },
             tam_itself_is_tam_hue: false,
             moving_tam_immediately_after_tam_has_moved: Consequence::Penalized {
                 penalty: -3,
@@ -675,156 +1407,808 @@ impl Config {
             failure_to_complete_the_move_means_exempt_from_kut2_tam2: false,
             game_can_end_without_tymok_taxot_because_of_negative_hand: false,
             what_to_say_before_casting_sticks: Some(Plan::ExactDestination),
+            who_goes_first: FirstMover::Random,
+            voluntary_pass_is_allowed: false,
+            player_without_any_legal_move_passes: false,
+            drop_restriction: DropRestriction::NONE,
+            move_limit: None,
+            repetition: RepetitionRule {
+                nth: 3,
+                consequence: RepetitionConsequence::Allowed,
+            },
         }
     }
+
+    /// Replaces the rule that decides who moves first in the seasons after the first one.
+    /// ／2季目以降の先手の決め方を差し替える。
+    #[must_use]
+    pub const fn with_who_goes_first(self, who_goes_first: FirstMover) -> Self {
+        Self {
+            who_goes_first,
+            ..self
+        }
+    }
+
+    /// Replaces the rules on passing: whether a voluntary pass is allowed, and whether a player without any valid move passes instead of ending the game.
+    /// ／パスに関するルールを差し替える。任意のパスを許すかどうかと、有効な手がない場合にゲームを終わらせる代わりにパスするかどうか。
+    #[must_use]
+    pub const fn with_pass(
+        self,
+        voluntary_pass_is_allowed: bool,
+        player_without_any_legal_move_passes: bool,
+    ) -> Self {
+        Self {
+            voluntary_pass_is_allowed,
+            player_without_any_legal_move_passes,
+            ..self
+        }
+    }
+
+    /// Replaces the restriction on where a piece from hop1zuo1 can be placed.
+    /// ／持ち駒を打てる場所の制限を差し替える。
+    #[must_use]
+    pub const fn with_drop_restriction(self, drop_restriction: DropRestriction) -> Self {
+        Self {
+            drop_restriction,
+            ..self
+        }
+    }
+
+    /// Replaces the limit on the number of moves in a season.
+    /// ／一つの季節の手数の上限を差し替える。
+    #[must_use]
+    pub const fn with_move_limit(self, move_limit: Option<MoveLimit>) -> Self {
+        Self { move_limit, ..self }
+    }
+
+    /// Replaces the rule on repeated positions.
+    /// ／千日手の規定を差し替える。
+    #[must_use]
+    pub const fn with_repetition(self, repetition: RepetitionRule) -> Self {
+        Self { repetition, ..self }
+    }
 }
 
-/// Sends `HandNotResolved` to `HandResolved`.
-#[must_use]
-pub fn resolve<T: CetkaikRepresentation + Clone>(
+/// Returns the penalty that the player who has just moved pays in any case, the raw score of the hand (including the penalty) if a hand has been formed, and the hands that count the captured piece.
+/// ／手を指したプレイヤーがいずれにせよ支払う減点と、役が成立していればその（減点込みの）素点と、取った駒を数えた役を返す。
+fn score_of_the_turn<T: CetkaikRepresentation>(
     state: &state::HandNotResolved_<T>,
     config: Config,
-) -> state::HandResolved_<T> {
-    use cetkaik_calculate_hand::{calculate_hands_and_score_from_pieces, ScoreAndHands};
-    let tymoxtaxot_because_of_kut2tam2 = state.kut2tam2_happened && config.step_tam_is_a_hand;
-
-    let tymoxtaxot_because_of_newly_acquired: Option<i32> = match state.whose_turn {
-        AbsoluteSide::ASide => {
-            // logically incorrect (should compare a Set, not a Vec), but works
-            if state.previous_a_side_hop1zuo1 == state.f.hop1zuo1_of(state.whose_turn).collect::<Vec<_>>() {
-                None
-            } else {
-                let ScoreAndHands {
-                    score: _,
-                    hands: old_hands,
-                } = calculate_hands_and_score_from_pieces(&state.previous_a_side_hop1zuo1)
-                    .expect("cannot fail, since the supplied list of piece should not exceed the limit on the number of piece");
-                let ScoreAndHands {
-                    score: new_score,
-                    hands: new_hands,
-                } = calculate_hands_and_score_from_pieces(&state.f.hop1zuo1_of(state.whose_turn).collect::<Vec<_>>())
-                    .expect("cannot fail, since the supplied list of piece should not exceed the limit on the number of piece");
-
-                // whether newly-acquired hand exists
-                if new_hands.difference(&old_hands).next().is_some() {
-                    Some(new_score)
-                } else {
-                    None
-                }
-            }
-        }
-        AbsoluteSide::IASide => {
-            // logically incorrect (should compare a Set, not a Vec), but works
-            if state.previous_ia_side_hop1zuo1 == state.f.hop1zuo1_of(state.whose_turn).collect::<Vec<_>>() {
-                None
-            } else {
-                let ScoreAndHands {
-                    score: _,
-                    hands: old_hands,
-                } = calculate_hands_and_score_from_pieces(&state.previous_ia_side_hop1zuo1)
-                .expect("cannot fail, since the supplied list of piece should not exceed the limit on the number of piece");
-                let ScoreAndHands {
-                    score: new_score,
-                    hands: new_hands,
-                } = calculate_hands_and_score_from_pieces(&state.f.hop1zuo1_of(state.whose_turn).collect::<Vec<_>>())
-                .expect("cannot fail, since the supplied list of piece should not exceed the limit on the number of piece");
-
-                // whether newly-acquired hand exists
-                if new_hands.difference(&old_hands).count() > 0 {
-                    Some(new_score)
-                } else {
-                    None
-                }
-            }
+) -> (i32, Option<i32>, hands::HandTracker) {
+    let (kut2tam2_penalty, tymoxtaxot_because_of_kut2tam2) = if state.kut2tam2_happened {
+        match config.kut2tam2 {
+            Consequence::Penalized { penalty, is_a_hand } => (penalty, is_a_hand),
+
+            // `Forbidden` never reaches here, since the candidates do not contain any kut2tam2
+            // 撃皇が禁止されているときは候補手に撃皇が含まれないので、ここには来ない
+            Consequence::Allowed | Consequence::Forbidden => (0, false),
         }
+    } else {
+        (0, false)
     };
 
+    let mut hands = state.hands;
+    let formed_by_capture = state
+        .captured_piece
+        .is_some_and(|captured| hands.capture(state.whose_turn, captured));
+    let formed_by_drop = state
+        .dropped_piece
+        .is_some_and(|dropped| hands.drop_piece(state.whose_turn, dropped));
+    let tymoxtaxot_because_of_newly_acquired =
+        (formed_by_capture || formed_by_drop).then(|| hands.of(state.whose_turn).score());
+
+    let penalty = state.tam2tysak2_raw_penalty + kut2tam2_penalty;
     if !tymoxtaxot_because_of_kut2tam2
         && tymoxtaxot_because_of_newly_acquired.is_none()
         && !state.tam2tysak2_will_trigger_taxottymok
     {
+        (penalty, None, hands)
+    } else {
+        (
+            penalty,
+            Some(penalty + tymoxtaxot_because_of_newly_acquired.unwrap_or(0)),
+            hands,
+        )
+    }
+}
+
+/// The scores that taxot would lead to, or the victor if taxot would end the game by knocking out a player. `None` if no hand has been formed. Unlike `IfTaxot_`, this tells the scores even when the game ends at the end of winter.
+/// ／終季した場合の得点。終季によって片方の点が尽きてゲームが終わるなら勝者。役が成立していなければ `None`。`IfTaxot_` と異なり、冬の終わりでゲームが終わる場合にも得点がわかる。
+/// # Panics
+/// Panics if a hop1zuo1 contains more pieces than the game has.
+#[must_use]
+pub fn scores_if_taxot<T: CetkaikRepresentation>(
+    state: &state::HandNotResolved_<T>,
+    config: Config,
+) -> Option<Result<Scores, Victor>> {
+    let (_, raw_score, _) = score_of_the_turn(state, config);
+    raw_score.map(|raw_score| state.scores.edit(raw_score, state.whose_turn, state.rate))
+}
+
+/// Sends `HandNotResolved` to `HandResolved`.
+/// # Panics
+/// Panics if a hop1zuo1 contains more pieces than the game has.
+#[must_use]
+pub fn resolve<T: CetkaikRepresentation + Clone>(
+    state: &state::HandNotResolved_<T>,
+    config: Config,
+) -> state::HandResolved_<T> {
+    resolve_with_setup(state, config, &setup::Setup::yhuap_initial())
+}
+
+/// Same as `resolve`, except that the next season starts from the position described by `setup`.
+/// ／`resolve` と同様だが、次の季節は `setup` の局面から始まる。
+/// # Panics
+/// Panics if a hop1zuo1 contains more pieces than the game has.
+#[must_use]
+pub fn resolve_with_setup<T: CetkaikRepresentation + Clone>(
+    state: &state::HandNotResolved_<T>,
+    config: Config,
+    setup: &setup::Setup<T>,
+) -> state::HandResolved_<T> {
+    let (penalty, raw_score, hands) = score_of_the_turn(state, config);
+    let Some(raw_score) = raw_score else {
         // nothing happened; hand the turn to the next person
         // 役ができていないので、次の人に手番を渡す
         // 減点分×レートは引く。
-        match state
-            .scores
-            .edit(state.tam2tysak2_raw_penalty, state.whose_turn, state.rate)
-        {
-            Ok(new_scores) => {
-                return state::HandResolved_::NeitherTymokNorTaxot(state::GroundState_ {
+        return match state.scores.edit(penalty, state.whose_turn, state.rate) {
+            Ok(new_scores) => hand_over_the_turn(
+                state::GroundState_ {
+                    hands,
+                    legal_move_cache: state::LegalMoveCache::default(),
+                    zobrist_hash: state.zobrist_hash
+                        ^ zobrist::side_to_move(state.whose_turn)
+                        ^ zobrist::side_to_move(!state.whose_turn)
+                        ^ zobrist::scores(state.scores)
+                        ^ zobrist::scores(new_scores)
+                        ^ zobrist::tam_has_moved_previously(state.i_have_moved_tam_in_this_turn),
                     f: state.f.clone(),
                     whose_turn: !state.whose_turn, /* hand the turn to the next person */
                     season: state.season,
+                    season_first_mover: state.season_first_mover,
+                    moves_in_this_season: state.moves_in_this_season + 1,
+                    positions_in_this_season: state.positions_in_this_season.clone(),
                     scores: new_scores,
                     rate: state.rate,
                     tam_has_moved_previously: state.i_have_moved_tam_in_this_turn,
-                });
-            }
-
-            Err(victor) => return state::HandResolved_::GameEndsWithoutTymokTaxot(victor),
-        }
-    }
+                },
+                config,
+                setup,
+            ),
+            Err(victor) => state::HandResolved_::GameEndsWithoutTymokTaxot(victor),
+        };
+    };
 
     // In all the other cases, a hand exists due to some reason; hence tymok/taxot
     // それ以外の場合、なんらかの理由で役が存在するので、終季・再行を行わねばならない
-    let raw_score = state.tam2tysak2_raw_penalty
-        + if tymoxtaxot_because_of_kut2tam2 {
-            -5
-        } else {
-            0
-        }
-        + tymoxtaxot_because_of_newly_acquired.unwrap_or(0);
+    let season_winner = match raw_score.cmp(&0) {
+        std::cmp::Ordering::Greater => Some(state.whose_turn),
+        std::cmp::Ordering::Less => Some(!state.whose_turn),
+        std::cmp::Ordering::Equal => None,
+    };
 
     let if_taxot = match state.scores.edit(raw_score, state.whose_turn, state.rate) {
         Err(victor) => IfTaxot_::VictoriousSide(victor),
-        Ok(new_scores) => {
-            state.season.next().map_or(
-                /* All seasons have ended */
-                IfTaxot_::VictoriousSide(new_scores.which_side_is_winning()),
-                /* The next season exists */
-                |next_season| IfTaxot_::NextSeason(beginning_of_season(next_season, new_scores)),
-            )
-        }
+        Ok(new_scores) => end_of_season(
+            state.season,
+            new_scores,
+            season_winner,
+            state.season_first_mover,
+            config,
+            setup,
+        ),
     };
 
-    state::HandResolved_::HandExists {
-        if_tymok: state::GroundState_ {
-            f: state.f.clone(),
-            whose_turn: !state.whose_turn, /* hand the turn to the next person */
-            season: state.season,
-            scores: state.scores,
-            rate: state.rate.next(), /* double the stake */
-            tam_has_moved_previously: state.i_have_moved_tam_in_this_turn,
-        },
+    let mut if_tymok = state::GroundState_ {
+        hands,
+        legal_move_cache: state::LegalMoveCache::default(),
+        zobrist_hash: state.zobrist_hash
+            ^ zobrist::side_to_move(state.whose_turn)
+            ^ zobrist::side_to_move(!state.whose_turn)
+            ^ zobrist::rate(state.rate)
+            ^ zobrist::rate(state.rate.next())
+            ^ zobrist::tam_has_moved_previously(state.i_have_moved_tam_in_this_turn),
+        f: state.f.clone(),
+        whose_turn: !state.whose_turn, /* hand the turn to the next person */
+        season: state.season,
+        season_first_mover: state.season_first_mover,
+        moves_in_this_season: state.moves_in_this_season + 1,
+        positions_in_this_season: state.positions_in_this_season.clone(),
+        scores: state.scores,
+        rate: state.rate.next(), /* double the stake */
+        tam_has_moved_previously: state.i_have_moved_tam_in_this_turn,
+    };
+    let key = repetition::position_key_of_state(&if_tymok);
+    if_tymok.positions_in_this_season.push(key);
 
-        if_taxot,
-    }
+    state::HandResolved_::HandExists { if_tymok, if_taxot }
 }
 
-/// Start of the game, with the season in spring and each player holding 20 points
-/// ／ゲーム開始、季節は春で所持点は20
+/// Start of the game, with the season in spring and each player holding 20 points. The first mover is decided at random; use `initial_state_with_config` to decide it by `Config::who_goes_first`.
+/// ／ゲーム開始、季節は春で所持点は20。先手は無作為に決まる。`Config::who_goes_first` によって決めるには `initial_state_with_config` を使う。
 #[must_use]
 pub fn initial_state<T: CetkaikRepresentation + Clone>() -> Probabilistic<state::GroundState_<T>> {
-    beginning_of_season(Season::Iei2, Scores::new())
+    initial_state_with_config(Config::cerke_online_alpha())
+}
+
+/// Same as `initial_state`, except that the first mover is decided by `config.who_goes_first` (see `FirstMover::decide_first_season`).
+/// ／`initial_state` と同様だが、先手は `config.who_goes_first` によって決まる（`FirstMover::decide_first_season` を参照）。
+#[must_use]
+pub fn initial_state_with_config<T: CetkaikRepresentation + Clone>(
+    config: Config,
+) -> Probabilistic<state::GroundState_<T>> {
+    initial_state_from_setup(&setup::Setup::yhuap_initial(), config)
 }
 
+/// Start of the game from the position, the season, the scores and the rate described by `setup`. The first mover is `setup.whose_turn` if it is specified, and is otherwise decided by `config.who_goes_first` (see `FirstMover::decide_first_season`).
+/// ／`setup` の局面・季節・得点・レートからのゲーム開始。先手は `setup.whose_turn` が指定されていればそれであり、そうでなければ `config.who_goes_first` によって決まる（`FirstMover::decide_first_season` を参照）。
+#[must_use]
+pub fn initial_state_from_setup<T: CetkaikRepresentation + Clone>(
+    setup: &setup::Setup<T>,
+    config: Config,
+) -> Probabilistic<state::GroundState_<T>> {
+    beginning_of_season(
+        &setup.f,
+        setup.season,
+        setup.scores,
+        setup.rate,
+        setup
+            .whose_turn
+            .or_else(|| config.who_goes_first.decide_first_season()),
+    )
+}
+
+/// `first_mover: None` means that the first mover is decided at random.
 fn beginning_of_season<T: CetkaikRepresentation + Clone>(
+    f: &T::AbsoluteField,
     season: Season,
     scores: Scores,
+    rate: Rate,
+    first_mover: Option<AbsoluteSide>,
 ) -> Probabilistic<state::GroundState_<T>> {
-    let ia_first = state::GroundState_ {
-        whose_turn: AbsoluteSide::IASide,
-        scores,
-        rate: Rate::X1,
-        season,
-        tam_has_moved_previously: false,
-        f: <T::AbsoluteField as IsAbsoluteField>::yhuap_initial(),
+    let state_where = |first_mover| {
+        state::GroundState_::new(
+            f.clone(),
+            first_mover,
+            season,
+            first_mover,
+            scores,
+            rate,
+            false,
+        )
     };
-    Probabilistic::WhoGoesFirst {
-        a_first: state::GroundState_ {
-            whose_turn: AbsoluteSide::ASide,
-            ..ia_first.clone()
+    match first_mover {
+        Some(first_mover) => Probabilistic::Pure(state_where(first_mover)),
+        None => Probabilistic::WhoGoesFirst {
+            ia_first: state_where(AbsoluteSide::IASide),
+            a_first: state_where(AbsoluteSide::ASide),
         },
-        ia_first,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kut2tam2_penalty_that_is_not_a_hand() {
+        let f = <cetkaik_naive_representation::absolute::Field as IsAbsoluteField>::yhuap_initial();
+        let mut state = fixtures::after_capture(
+            &fixtures::ground_state(&f, IASide, Scores::new(), Rate::X2),
+            None,
+        );
+        state.season_first_mover = ASide;
+        state.kut2tam2_happened = true;
+
+        let config = Config {
+            kut2tam2: Consequence::Penalized {
+                penalty: -5,
+                is_a_hand: false,
+            },
+            ..Config::cerke_online_alpha()
+        };
+        match resolve(&state, config) {
+            state::HandResolved_::NeitherTymokNorTaxot(s) => {
+                assert_eq!(s.scores.ia(), 10);
+                assert_eq!(s.whose_turn, ASide);
+            }
+            _ => panic!("a kut2tam2 that is not a hand must not trigger tymok / taxot"),
+        }
+
+        match resolve(&state, Config::strict_y1_huap1()) {
+            state::HandResolved_::HandExists { if_tymok, .. } => {
+                assert_eq!(if_tymok.rate, Rate::X4);
+            }
+            _ => panic!("a kut2tam2 that is a hand must trigger tymok / taxot"),
+        }
+
+        assert!(matches!(
+            resolve(&state, Config::cerke_online_alpha()),
+            state::HandResolved_::NeitherTymokNorTaxot(s) if s.scores == Scores::new()
+        ));
+    }
+
+    #[test]
+    fn test_who_goes_first() {
+        use cetkaik_fundamental::{Color, ColorAndProf, Profession};
+        use cetkaik_naive_representation::CetkaikNaive;
+        let mut f =
+            <cetkaik_naive_representation::absolute::Field as IsAbsoluteField>::yhuap_initial();
+        f.ia_side_hop1zuo1 = vec![
+            ColorAndProf {
+                color: Color::Kok1,
+                prof: Profession::Kauk2,
+            };
+            5
+        ];
+        let mut state = fixtures::after_capture(
+            &fixtures::ground_state(&f, IASide, Scores::new(), Rate::X1),
+            Some(ColorAndProf {
+                color: Color::Kok1,
+                prof: Profession::Kauk2,
+            }),
+        );
+        state.season_first_mover = ASide;
+
+        let first_mover_of_next_season = |who_goes_first| match resolve(
+            &state,
+            Config::cerke_online_alpha().with_who_goes_first(who_goes_first),
+        ) {
+            state::HandResolved_::HandExists {
+                if_taxot: IfTaxot_::NextSeason(Probabilistic::Pure(s)),
+                ..
+            } => {
+                assert_eq!(s.season, Season::Xo1);
+                assert_eq!(s.season_first_mover, s.whose_turn);
+                Some(s.whose_turn)
+            }
+            state::HandResolved_::HandExists {
+                if_taxot: IfTaxot_::NextSeason(Probabilistic::WhoGoesFirst { .. }),
+                ..
+            } => None,
+            _ => panic!("a hand should have been formed"),
+        };
+
+        assert_eq!(first_mover_of_next_season(FirstMover::Random), None);
+        assert_eq!(
+            first_mover_of_next_season(FirstMover::LoserOfPreviousSeason),
+            Some(ASide)
+        );
+        assert_eq!(
+            first_mover_of_next_season(FirstMover::WinnerOfPreviousSeason),
+            Some(IASide)
+        );
+        assert_eq!(
+            first_mover_of_next_season(FirstMover::Alternate),
+            Some(IASide)
+        );
+        assert_eq!(
+            first_mover_of_next_season(FirstMover::Fixed(ASide)),
+            Some(ASide)
+        );
+
+        // The first season, where only `Fixed` decides the first mover unless the setup does
+        // 最初の季節では、初期配置が決めない限り `Fixed` のみが先手を決める
+        let first_mover_of_first_season =
+            |who_goes_first, setup: &setup::Setup<CetkaikNaive>| match initial_state_from_setup(
+                setup,
+                Config::cerke_online_alpha().with_who_goes_first(who_goes_first),
+            ) {
+                Probabilistic::Pure(s) => {
+                    assert_eq!(s.season_first_mover, s.whose_turn);
+                    Some(s.whose_turn)
+                }
+                Probabilistic::WhoGoesFirst { .. } => None,
+                _ => unreachable!(),
+            };
+        let yhuap_initial = setup::Setup::yhuap_initial();
+        assert_eq!(
+            first_mover_of_first_season(FirstMover::Fixed(ASide), &yhuap_initial),
+            Some(ASide)
+        );
+        assert_eq!(
+            first_mover_of_first_season(FirstMover::Fixed(IASide), &yhuap_initial),
+            Some(IASide)
+        );
+        for who_goes_first in [
+            FirstMover::Random,
+            FirstMover::LoserOfPreviousSeason,
+            FirstMover::WinnerOfPreviousSeason,
+            FirstMover::Alternate,
+        ] {
+            assert_eq!(
+                first_mover_of_first_season(who_goes_first, &yhuap_initial),
+                None
+            );
+        }
+        assert_eq!(
+            first_mover_of_first_season(
+                FirstMover::Fixed(ASide),
+                &yhuap_initial.clone().with_whose_turn(Some(IASide))
+            ),
+            Some(IASide)
+        );
+        assert!(matches!(
+            initial_state_with_config::<CetkaikNaive>(
+                Config::cerke_online_alpha().with_who_goes_first(FirstMover::Fixed(ASide))
+            ),
+            Probabilistic::Pure(s) if s.whose_turn == ASide
+        ));
+    }
+
+    #[test]
+    fn test_pass() {
+        let state = state::GroundState_::<cetkaik_naive_representation::CetkaikNaive>::new(
+            <cetkaik_naive_representation::absolute::Field as IsAbsoluteField>::yhuap_initial(),
+            IASide,
+            Season::Iei2,
+            IASide,
+            Scores::new(),
+            Rate::X1,
+            true,
+        );
+
+        assert!(apply_pass(&state, message::Pass, Config::cerke_online_alpha()).is_err());
+        assert!(apply_pass(
+            &state,
+            message::Pass,
+            Config::cerke_online_alpha().with_pass(false, true)
+        )
+        .is_err());
+
+        let state::HandResolved_::NeitherTymokNorTaxot(new_state) = apply_pass(
+            &state,
+            message::Pass,
+            Config::cerke_online_alpha().with_pass(true, false),
+        )
+        .unwrap() else {
+            panic!("a pass must simply hand over the turn")
+        };
+        assert_eq!(new_state.whose_turn, ASide);
+        assert!(!new_state.tam_has_moved_previously);
+    }
+
+    #[test]
+    fn test_forced_pass() {
+        use cetkaik_fundamental::{Color, Profession};
+        use cetkaik_naive_representation::absolute::{Board, Column, Coord, Field, Piece, Row};
+        // A side has nothing but Tam2, which it has just moved into a corner walled in by IA side's Kauk2
+        // A側は皇しか動かせないが、その皇は直前に動かされたもので、IA側の兵に囲まれた隅にいる
+        let mut board = Board(maplit::hashmap! { Coord(Row::A, Column::K) => Piece::Tam2 });
+        for row in [Row::A, Row::E, Row::I] {
+            for column in [Column::K, Column::L, Column::N] {
+                board
+                    .0
+                    .entry(Coord(row, column))
+                    .or_insert(Piece::NonTam2Piece {
+                        color: Color::Kok1,
+                        prof: Profession::Kauk2,
+                        side: IASide,
+                    });
+            }
+        }
+        let f = Field {
+            board,
+            a_side_hop1zuo1: vec![],
+            ia_side_hop1zuo1: vec![],
+        };
+        let state = state::GroundState_::<cetkaik_naive_representation::CetkaikNaive>::new(
+            f,
+            ASide,
+            Season::Iei2,
+            ASide,
+            Scores::from_ia_score(25).unwrap(),
+            Rate::X1,
+            true,
+        );
+        assert!(!state.has_any_legal_move(Config::cerke_online_alpha()));
+
+        // By default, being stuck ends the game, and passing is not allowed
+        // 既定では手詰まりでゲームが終わり、パスは許されない
+        assert!(apply_pass(&state, message::Pass, Config::cerke_online_alpha()).is_err());
+        assert!(matches!(
+            no_move_possible_at_all(&state, Config::cerke_online_alpha()).unwrap(),
+            state::HandResolved_::GameEndsWithoutTymokTaxot(score::Victor(Some(IASide)))
+        ));
+
+        // Otherwise, the stuck player passes, whether explicitly or by `no_move_possible_at_all`
+        // そうでなければ、明示的にであれ `no_move_possible_at_all` によってであれ、手詰まりの人はパスする
+        let config = Config::cerke_online_alpha().with_pass(false, true);
+        for resolved in [
+            apply_pass(&state, message::Pass, config).unwrap(),
+            no_move_possible_at_all(&state, config).unwrap(),
+        ] {
+            let state::HandResolved_::NeitherTymokNorTaxot(new_state) = resolved else {
+                panic!("a forced pass must simply hand over the turn")
+            };
+            assert_eq!(new_state.whose_turn, IASide);
+            assert_eq!(new_state.moves_in_this_season, 1);
+            assert!(!new_state.tam_has_moved_previously);
+            assert!(new_state.has_any_legal_move(config));
+        }
+    }
+
+    #[test]
+    fn test_drop_restriction() {
+        use cetkaik_fundamental::{Color, ColorAndProf, Profession};
+        use cetkaik_naive_representation::absolute::{Column, Coord, Row};
+        let mut f =
+            <cetkaik_naive_representation::absolute::Field as IsAbsoluteField>::yhuap_initial();
+        f.ia_side_hop1zuo1 = vec![ColorAndProf {
+            color: Color::Kok1,
+            prof: Profession::Kauk2,
+        }];
+        let state = fixtures::ground_state(&f, IASide, Scores::new(), Rate::X1);
+        let drop_at = |dest| message::NormalMove_::NonTamMoveFromHopZuo {
+            color: Color::Kok1,
+            prof: Profession::Kauk2,
+            dest,
+        };
+        let config = Config::cerke_online_alpha().with_drop_restriction(DropRestriction {
+            no_drop_into_tam_hue: false,
+            no_drop_into_water: true,
+            no_drop_onto_opponent_home_rows: true,
+        });
+
+        let (hop1zuo1_candidates, _) = state.get_candidates(config);
+        assert!(!hop1zuo1_candidates.is_empty());
+        for cand in hop1zuo1_candidates {
+            let message::PureMove__::NormalMove(message::NormalMove_::NonTamMoveFromHopZuo {
+                dest: dest @ Coord(row, _),
+                ..
+            }) = cand
+            else {
+                panic!("expected a move from hop1zuo1")
+            };
+            assert!(!cetkaik_naive_representation::absolute::is_water(dest));
+            assert!(!matches!(row, Row::A | Row::E | Row::I));
+        }
+
+        for dest in [Coord(Row::O, Column::N), Coord(Row::E, Column::C)] {
+            assert!(apply_normal_move(&state, drop_at(dest), Config::cerke_online_alpha()).is_ok());
+            assert_eq!(
+                apply_normal_move(&state, drop_at(dest), config).unwrap_err(),
+                "By config, it is prohibited to place a piece from hop1zuo1 onto this square."
+            );
+        }
+        assert!(apply_normal_move(&state, drop_at(Coord(Row::U, Column::K)), config).is_ok());
+
+        let config = Config::cerke_online_alpha().with_drop_restriction(DropRestriction {
+            no_drop_into_tam_hue: true,
+            ..DropRestriction::NONE
+        });
+        let tam_hue = [Coord(Row::U, Column::T), Coord(Row::Y, Column::X)];
+        let (hop1zuo1_candidates, _) = state.get_candidates(config);
+        assert!(!hop1zuo1_candidates.is_empty());
+        assert!(!hop1zuo1_candidates.iter().any(|cand| matches!(
+            cand,
+            message::PureMove__::NormalMove(message::NormalMove_::NonTamMoveFromHopZuo { dest, .. })
+                if tam_hue.contains(dest)
+        )));
+        for dest in tam_hue {
+            assert!(apply_normal_move(&state, drop_at(dest), Config::cerke_online_alpha()).is_ok());
+            assert_eq!(
+                apply_normal_move(&state, drop_at(dest), config).unwrap_err(),
+                "By config, it is prohibited to place a piece from hop1zuo1 onto this square."
+            );
+        }
+        assert!(apply_normal_move(&state, drop_at(Coord(Row::U, Column::K)), config).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "naive")]
+    fn test_drop_that_breaks_a_same_colour_hand() {
+        use cetkaik_fundamental::{Color, ColorAndProf, Profession};
+        use cetkaik_naive_representation::absolute::{Column, Coord, Row};
+        let mut f =
+            <cetkaik_naive_representation::absolute::Field as IsAbsoluteField>::yhuap_initial();
+
+        // Red Dau2 and red Maun1 form 同色獣, which hides the plain 獣
+        // 赤虎と赤馬が同色獣をなし、同色でない獣は隠れる
+        f.ia_side_hop1zuo1 = vec![
+            ColorAndProf {
+                color: Color::Kok1,
+                prof: Profession::Dau2,
+            },
+            ColorAndProf {
+                color: Color::Kok1,
+                prof: Profession::Maun1,
+            },
+            ColorAndProf {
+                color: Color::Huok2,
+                prof: Profession::Dau2,
+            },
+        ];
+        let state = fixtures::ground_state(&f, IASide, Scores::new(), Rate::X1);
+        let config = Config::cerke_online_alpha();
+        let drop = |color| message::NormalMove_::NonTamMoveFromHopZuo {
+            color,
+            prof: Profession::Dau2,
+            dest: Coord(Row::U, Column::K),
+        };
+
+        // Dropping the red Dau2 leaves the black Dau2 and the red Maun1, which form 獣, a hand that was not there before, as in 1.3.1
+        // 赤虎を打つと黒虎と赤馬が残り、それまでなかった獣ができる。1.3.1 と同じである
+        let Probabilistic::Pure(hand_not_resolved) =
+            apply_normal_move(&state, drop(Color::Kok1), config).unwrap()
+        else {
+            panic!("a drop should be certain")
+        };
+        assert!(matches!(
+            resolve(&hand_not_resolved, config),
+            state::HandResolved_::HandExists { .. }
+        ));
+        let mut made = state.clone();
+        assert!(made
+            .make(
+                make_unmake::Action::NormalMove {
+                    msg: drop(Color::Kok1),
+                    water_entry_succeeds: true,
+                },
+                config,
+            )
+            .is_none());
+        assert_eq!(made, state);
+
+        // Dropping the black Dau2 keeps 同色獣 and forms nothing new
+        // 黒虎を打っても同色獣が残るだけで、新しい役はできない
+        let Probabilistic::Pure(hand_not_resolved) =
+            apply_normal_move(&state, drop(Color::Huok2), config).unwrap()
+        else {
+            panic!("a drop should be certain")
+        };
+        assert!(matches!(
+            resolve(&hand_not_resolved, config),
+            state::HandResolved_::NeitherTymokNorTaxot(_)
+        ));
+        assert!(made
+            .make(
+                make_unmake::Action::NormalMove {
+                    msg: drop(Color::Huok2),
+                    water_entry_succeeds: true,
+                },
+                config,
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn test_move_limit() {
+        let config = |outcome| {
+            Config::cerke_online_alpha()
+                .with_pass(true, false)
+                .with_who_goes_first(FirstMover::WinnerOfPreviousSeason)
+                .with_move_limit(Some(MoveLimit { moves: 10, outcome }))
+        };
+
+        // Nine passes, after which A side is to make the tenth move
+        // 九回パスして、A側が十手目を指す番になる
+        let mut state = fixtures::ground_state(
+            &<cetkaik_naive_representation::absolute::Field as IsAbsoluteField>::yhuap_initial(),
+            IASide,
+            Scores::from_ia_score(25).unwrap(),
+            Rate::X1,
+        );
+        for _ in 0..9 {
+            let state::HandResolved_::NeitherTymokNorTaxot(next) = apply_pass(
+                &state,
+                message::Pass,
+                config(MoveLimitOutcome::GameEndsInADraw),
+            )
+            .unwrap() else {
+                panic!("the season must go on before the tenth move")
+            };
+            state = next;
+        }
+        assert_eq!(state.moves_in_this_season, 9);
+
+        match apply_pass(
+            &state,
+            message::Pass,
+            config(MoveLimitOutcome::SideAheadWinsTheSeason),
+        )
+        .unwrap()
+        {
+            state::HandResolved_::SeasonEndsWithoutTymokTaxot(IfTaxot_::NextSeason(
+                probabilistic::Probabilistic::Pure(next),
+            )) => {
+                assert_eq!(next.season, Season::Xo1);
+                assert_eq!(next.whose_turn, IASide);
+                assert_eq!(next.moves_in_this_season, 0);
+                assert_eq!(next.scores.ia(), 25);
+            }
+            _ => panic!("the season must end"),
+        }
+
+        assert!(matches!(
+            apply_pass(
+                &state,
+                message::Pass,
+                config(MoveLimitOutcome::SeasonEndsWithoutScoreChange)
+            )
+            .unwrap(),
+            state::HandResolved_::SeasonEndsWithoutTymokTaxot(IfTaxot_::NextSeason(
+                probabilistic::Probabilistic::WhoGoesFirst { .. }
+            ))
+        ));
+
+        assert!(matches!(
+            apply_pass(
+                &state,
+                message::Pass,
+                config(MoveLimitOutcome::GameEndsInADraw)
+            )
+            .unwrap(),
+            state::HandResolved_::GameEndsWithoutTymokTaxot(Victor(None))
+        ));
+    }
+
+    #[test]
+    fn test_repetition() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        let state = fixtures::ground_state(
+            &<cetkaik_naive_representation::absolute::Field as IsAbsoluteField>::yhuap_initial(),
+            IASide,
+            Scores::new(),
+            Rate::X1,
+        );
+        let pass = |state: &state::GroundState_<CetkaikNaive>, consequence| {
+            apply_pass(
+                state,
+                message::Pass,
+                Config::cerke_online_alpha()
+                    .with_pass(true, false)
+                    .with_repetition(RepetitionRule {
+                        nth: 3,
+                        consequence,
+                    }),
+            )
+            .unwrap()
+        };
+        let pass_and_continue =
+            |state: &state::GroundState_<CetkaikNaive>, consequence| match pass(state, consequence)
+            {
+                state::HandResolved_::NeitherTymokNorTaxot(next) => next,
+                _ => panic!("the game must go on"),
+            };
+
+        // the initial position appears for the second time
+        let mut second = state.clone();
+        for _ in 0..2 {
+            second = pass_and_continue(&second, RepetitionConsequence::GameEndsInADraw);
+        }
+        let before_third = pass_and_continue(&second, RepetitionConsequence::GameEndsInADraw);
+
+        assert!(matches!(
+            pass(&before_third, RepetitionConsequence::GameEndsInADraw),
+            state::HandResolved_::GameEndsWithoutTymokTaxot(Victor(None))
+        ));
+        assert!(matches!(
+            pass(
+                &before_third,
+                RepetitionConsequence::SeasonEndsWithoutScoreChange
+            ),
+            state::HandResolved_::SeasonEndsWithoutTymokTaxot(IfTaxot_::NextSeason(_))
+        ));
+        let penalized = pass_and_continue(
+            &before_third,
+            RepetitionConsequence::Penalized { penalty: -5 },
+        );
+        assert_eq!(penalized.scores.a(), 15);
+        let allowed = pass_and_continue(&before_third, RepetitionConsequence::Allowed);
+        assert_eq!(allowed.scores, Scores::new());
+        assert_eq!(allowed.positions_in_this_season.len(), 5);
+
+        // A long history, partly shared, is dropped without overflowing the stack
+        // 一部が共有された長い履歴も、スタックを溢れさせずに解放される
+        let long: repetition::PositionHistory = (0..1_000_000).collect();
+        let mut extended = long.clone();
+        extended.push(0);
+        assert_eq!(extended.count(0), 2);
+        assert_eq!(long.count(0), 1);
+        assert_eq!(extended.count(999_999), 1);
+        assert_eq!(extended.count(1_000_000), 0);
+        drop(long);
+        drop(extended);
     }
 }
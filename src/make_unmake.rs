@@ -0,0 +1,551 @@
+use super::message::{InfAfterStep_, NormalMove_};
+use super::setup::Hop1Zuo1Editable;
+use super::state::{GroundState_, LegalMoveCache};
+use super::{
+    captured_at, piece_on_field_at, zobrist, zobrist_delta_on_hop1zuo1, Config, Consequence,
+    MoveLimit, RepetitionConsequence, Scores,
+};
+use cetkaik_fundamental::ColorAndProf;
+use cetkaik_traits::{CetkaikRepresentation, IsBoard, IsPieceWithSide};
+
+/// A move together with the outcome of every cast of sticks that it needs, so that applying it involves no chance.
+/// ／手と、それに必要な棒の判定の結果全て。これを適用するのに偶然は関わらない。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action<Coord> {
+    /// `water_entry_succeeds` is ignored unless the move needs a water-entry cast.
+    /// ／`water_entry_succeeds` は入水判定が必要な手でなければ無視される。
+    NormalMove {
+        msg: NormalMove_<Coord>,
+        water_entry_succeeds: bool,
+    },
+
+    /// `dest` is the one chosen after the stepping-over cast, as in `AfterHalfAcceptance_`. `water_entry_succeeds` is ignored unless the move needs a water-entry cast.
+    /// ／`dest` は `AfterHalfAcceptance_` と同様に踏越え判定の後で選ばれたもの。`water_entry_succeeds` は入水判定が必要な手でなければ無視される。
+    InfAfterStep {
+        msg: InfAfterStep_<Coord>,
+        dest: Option<Coord>,
+        water_entry_succeeds: bool,
+    },
+}
+
+/// What `GroundState_::unmake` needs to restore the state exactly, except that a piece that was dropped returns to the end of the hop1zuo1.
+/// ／`GroundState_::unmake` が状態を正確に復元するのに必要なもの。ただし打たれた駒は手駒の末尾に戻る。
+#[derive(Clone, Debug)]
+pub struct Undo<T: CetkaikRepresentation> {
+    /// The piece that moved on the board, as (src, dest)
+    /// ／盤上で動いた駒の (src, dest)
+    moved: Option<(T::AbsoluteCoord, T::AbsoluteCoord)>,
+
+    /// The piece captured at the dest of `moved`
+    /// ／`moved` の dest で取られた駒
+    captured: Option<ColorAndProf>,
+
+    /// The piece dropped from hop1zuo1, and where
+    /// ／手駒から打たれた駒と、打たれた場所
+    dropped: Option<(ColorAndProf, T::AbsoluteCoord)>,
+
+    scores: Scores,
+    tam_has_moved_previously: bool,
+    zobrist_hash: u64,
+
+    /// The legal move set that the state had kept before the action, which does not hold after it
+    /// ／手を指す前に状態が保持していた合法手の集合。手を指した後には成り立たない
+    legal_move_cache: LegalMoveCache,
+}
+
+/// (penalty, whether the penalty is a hand, whether Tam2 has moved)
+/// ／（減点, 減点が役であるか, 皇が動いたか）
+type Effects = (i32, bool, bool);
+
+impl<T: Hop1Zuo1Editable> GroundState_<T> {
+    /// Applies `action` in place, if all it does is to hand the turn over to the other player (possibly with a penalty), i.e. if `resolve` would give `HandResolved_::NeitherTymokNorTaxot`. Returns what `unmake` needs to take it back.
+    /// Otherwise, i.e. if `action` forms a hand or ends the season or the game, returns `None` and leaves the state untouched. Use `apply_normal_move` (or `apply_inf_after_step` and `apply_after_half_acceptance`) followed by `resolve` in that case.
+    /// Unlike those functions, this does not check that the move is legal; take it from `candidates`, and take `dest` of `Action::InfAfterStep` from `ExcitedState_::get_candidates`. The board and the hop1zuo1 are changed in place, without building a new field.
+    /// ／`action` がもう片方のプレイヤーに（場合によっては減点付きで）手番を渡すだけならば、つまり `resolve` が `HandResolved_::NeitherTymokNorTaxot` を与えるならば、その場で適用する。`unmake` がそれを取り消すのに必要なものを返す。
+    /// そうでない場合、つまり `action` が役を作るか季節かゲームを終える場合は、`None` を返し状態には手を付けない。その場合は `apply_normal_move`（または `apply_inf_after_step` と `apply_after_half_acceptance`）に続けて `resolve` を使うこと。
+    /// これらの関数と異なり、手が合法であるかは確認しない。手は `candidates` から、`Action::InfAfterStep` の `dest` は `ExcitedState_::get_candidates` から取ること。盤と手駒はその場で変更し、新しい盤は作らない。
+    ///
+    /// ```
+    /// use cetkaik_full_state_transition::make_unmake::Action;
+    /// use cetkaik_full_state_transition::message::NormalMove_;
+    /// use cetkaik_full_state_transition::probabilistic::Probabilistic;
+    /// use cetkaik_full_state_transition::*;
+    /// use cetkaik_naive_representation::absolute::{Column, Coord, Row};
+    /// use cetkaik_naive_representation::CetkaikNaive;
+    ///
+    /// let Probabilistic::WhoGoesFirst { mut ia_first, .. } = initial_state::<CetkaikNaive>() else {
+    ///     unreachable!()
+    /// };
+    /// let before = ia_first.clone();
+    /// let msg = NormalMove_::NonTamMoveSrcDst {
+    ///     src: Coord(Row::AI, Column::K),
+    ///     dest: Coord(Row::Y, Column::K),
+    /// };
+    /// let undo = ia_first
+    ///     .make(Action::NormalMove { msg, water_entry_succeeds: true }, Config::cerke_online_alpha())
+    ///     .unwrap();
+    /// assert_eq!(ia_first.zobrist_hash(), zobrist::hash_of_ground_state(&ia_first));
+    /// assert_eq!(ia_first.moves_in_this_season(), 1);
+    ///
+    /// ia_first.unmake(undo);
+    /// assert_eq!(ia_first, before);
+    /// assert_eq!(ia_first.positions_in_this_season(), before.positions_in_this_season());
+    /// ```
+    pub fn make(&mut self, action: Action<T::AbsoluteCoord>, config: Config) -> Option<Undo<T>> {
+        let mut undo = Undo {
+            moved: None,
+            captured: None,
+            dropped: None,
+            scores: self.scores,
+            tam_has_moved_previously: self.tam_has_moved_previously,
+            zobrist_hash: self.zobrist_hash,
+            legal_move_cache: LegalMoveCache::default(),
+        };
+        let effects = match action {
+            Action::NormalMove {
+                msg: NormalMove_::NonTamMoveFromHopZuo { color, prof, dest },
+                ..
+            } => {
+                let dropped = ColorAndProf { color, prof };
+                if piece_on_field_at::<T>(&self.f, dest).is_some()
+                    || !T::take_from_hop1zuo1(&mut self.f, self.whose_turn, dropped)
+                {
+                    return None;
+                }
+                let piece = T::piece_of(dropped, self.whose_turn);
+                T::as_board_mut_absolute(&mut self.f).put(dest, Some(piece));
+
+                // Adding `dropped` back to the hop1zuo1 it was taken from changes the hash in the same way as taking it
+                // `dropped` を取り出した後の手駒にそれを加えるときのハッシュの変化は、取り出すときと同じ
+                self.zobrist_hash ^= zobrist::piece::<T>(dest, piece)
+                    ^ zobrist_delta_on_hop1zuo1::<T>(&self.f, self.whose_turn, dropped, true);
+                undo.dropped = Some((dropped, dest));
+
+                // Dropping can break a 同色 hand into the plain one
+                // 打つと同色の役が崩れて同色でない役ができることがある
+                let formed = self.hands.drop_piece(self.whose_turn, dropped);
+                Some((0, formed, false))
+            }
+            Action::NormalMove {
+                msg:
+                    NormalMove_::TamMoveNoStep {
+                        src, second_dest, ..
+                    }
+                    | NormalMove_::TamMoveStepsDuringFormer {
+                        src, second_dest, ..
+                    }
+                    | NormalMove_::TamMoveStepsDuringLatter {
+                        src, second_dest, ..
+                    },
+                ..
+            } => self.make_tam_move(src, second_dest, config, &mut undo),
+            Action::NormalMove {
+                msg: NormalMove_::NonTamMoveSrcDst { src, dest },
+                water_entry_succeeds,
+            } => self.make_nontam_move(
+                src,
+                None,
+                Some(dest),
+                water_entry_succeeds,
+                config,
+                &mut undo,
+            ),
+            Action::NormalMove {
+                msg: NormalMove_::NonTamMoveSrcStepDstFinite { src, step, dest },
+                water_entry_succeeds,
+            } => self.make_nontam_move(
+                src,
+                Some(step),
+                Some(dest),
+                water_entry_succeeds,
+                config,
+                &mut undo,
+            ),
+            Action::InfAfterStep {
+                msg,
+                dest,
+                water_entry_succeeds,
+            } => self.make_nontam_move(
+                msg.src,
+                Some(msg.step),
+                dest,
+                water_entry_succeeds,
+                config,
+                &mut undo,
+            ),
+        };
+
+        let Some((penalty, false, i_have_moved_tam)) = effects else {
+            self.restore_board(&mut undo);
+            return None;
+        };
+        let Ok(new_scores) = self.scores.edit(penalty, self.whose_turn, self.rate) else {
+            self.restore_board(&mut undo);
+            return None;
+        };
+        let zobrist_hash = self.zobrist_hash
+            ^ zobrist::tam_has_moved_previously(self.tam_has_moved_previously)
+            ^ zobrist::side_to_move(self.whose_turn)
+            ^ zobrist::side_to_move(!self.whose_turn)
+            ^ zobrist::scores(self.scores)
+            ^ zobrist::scores(new_scores)
+            ^ zobrist::tam_has_moved_previously(i_have_moved_tam);
+
+        // The same as `repetition::position_key_of_state` of the next state
+        // 次の状態の `repetition::position_key_of_state` と同じ
+        let key = zobrist_hash
+            ^ zobrist::season(self.season)
+            ^ zobrist::scores(new_scores)
+            ^ zobrist::rate(self.rate);
+        let occurrences = 1 + self.positions_in_this_season.count(key);
+        let repetition_penalty = if occurrences >= config.repetition.nth {
+            match config.repetition.consequence {
+                RepetitionConsequence::Allowed => 0,
+                RepetitionConsequence::Penalized { penalty } => penalty,
+                RepetitionConsequence::SeasonEndsWithoutScoreChange
+                | RepetitionConsequence::GameEndsInADraw => {
+                    self.restore_board(&mut undo);
+                    return None;
+                }
+            }
+        } else {
+            0
+        };
+        let Ok(final_scores) = new_scores.edit(repetition_penalty, self.whose_turn, self.rate)
+        else {
+            self.restore_board(&mut undo);
+            return None;
+        };
+        if config
+            .move_limit
+            .is_some_and(|MoveLimit { moves, .. }| self.moves_in_this_season + 1 >= moves)
+        {
+            self.restore_board(&mut undo);
+            return None;
+        }
+
+        self.zobrist_hash =
+            zobrist_hash ^ zobrist::scores(new_scores) ^ zobrist::scores(final_scores);
+        self.scores = final_scores;
+        self.whose_turn = !self.whose_turn;
+        self.moves_in_this_season += 1;
+        self.tam_has_moved_previously = i_have_moved_tam;
+        self.positions_in_this_season.push(key);
+        undo.legal_move_cache = std::mem::take(&mut self.legal_move_cache);
+        Some(undo)
+    }
+
+    /// Takes back the action that `make` returned `undo` for. Actions must be taken back in the reverse order.
+    /// ／`make` が `undo` を返した手を取り消す。手は逆順に取り消さねばならない。
+    pub fn unmake(&mut self, mut undo: Undo<T>) {
+        self.positions_in_this_season.pop();
+        self.moves_in_this_season -= 1;
+        self.whose_turn = !self.whose_turn;
+        self.tam_has_moved_previously = undo.tam_has_moved_previously;
+        self.scores = undo.scores;
+        self.legal_move_cache = std::mem::take(&mut undo.legal_move_cache);
+        self.restore_board(&mut undo);
+    }
+
+    /// Takes back the changes to the board, the hop1zuo1 and the hands, which `whose_turn` has made.
+    /// ／`whose_turn` が盤・手駒・役に加えた変更を取り消す。
+    fn restore_board(&mut self, undo: &mut Undo<T>) {
+        if let Some((src, dest)) = undo.moved.take() {
+            T::as_board_mut_absolute(&mut self.f).mov(dest, src);
+            if let Some(captured) = undo.captured.take() {
+                T::take_from_hop1zuo1(&mut self.f, self.whose_turn, captured);
+                T::as_board_mut_absolute(&mut self.f)
+                    .put(dest, Some(T::piece_of(captured, !self.whose_turn)));
+                self.hands.drop_piece(self.whose_turn, captured);
+            }
+        }
+        if let Some((dropped, dest)) = undo.dropped.take() {
+            T::as_board_mut_absolute(&mut self.f).put(dest, None);
+            T::put_into_hop1zuo1(&mut self.f, self.whose_turn, dropped);
+            self.hands.capture(self.whose_turn, dropped);
+        }
+        self.zobrist_hash = undo.zobrist_hash;
+    }
+
+    fn make_tam_move(
+        &mut self,
+        src: T::AbsoluteCoord,
+        second_dest: T::AbsoluteCoord,
+        config: Config,
+        undo: &mut Undo<T>,
+    ) -> Option<Effects> {
+        let consequence = |applies: bool, consequence: Consequence| {
+            if !applies {
+                return Some((0, false));
+            }
+            match consequence {
+                Consequence::Allowed => Some((0, false)),
+                Consequence::Penalized { penalty, is_a_hand } => Some((penalty, is_a_hand)),
+                Consequence::Forbidden => None,
+            }
+        };
+        let (penalty1, is_a_hand1) = consequence(
+            self.tam_has_moved_previously,
+            config.moving_tam_immediately_after_tam_has_moved,
+        )?;
+        let (penalty2, is_a_hand2) = consequence(src == second_dest, config.tam_mun_mok)?;
+
+        if src != second_dest {
+            let tam = T::absolute_tam2();
+            self.zobrist_hash ^=
+                zobrist::piece::<T>(src, tam) ^ zobrist::piece::<T>(second_dest, tam);
+            T::as_board_mut_absolute(&mut self.f).mov(src, second_dest);
+            undo.moved = Some((src, second_dest));
+        }
+        Some((penalty1 + penalty2, is_a_hand1 || is_a_hand2, true))
+    }
+
+    /// `dest` is `None` if the stepping-over cast did not allow the piece to go anywhere.
+    /// ／踏越え判定により駒がどこにも行けなかった場合は `dest` は `None`。
+    fn make_nontam_move(
+        &mut self,
+        src: T::AbsoluteCoord,
+        step: Option<T::AbsoluteCoord>,
+        dest: Option<T::AbsoluteCoord>,
+        water_entry_succeeds: bool,
+        config: Config,
+        undo: &mut Undo<T>,
+    ) -> Option<Effects> {
+        let piece = piece_on_field_at::<T>(&self.f, src)?;
+        let steps_on_tam = step
+            .is_some_and(|step| piece_on_field_at::<T>(&self.f, step) == Some(T::absolute_tam2()));
+        let needs_water_entry_cast = |dest| {
+            !T::is_water_absolute(src)
+                && !piece.has_prof(cetkaik_fundamental::Profession::Nuak1)
+                && T::is_water_absolute(dest)
+        };
+        let dest = dest.filter(|&dest| water_entry_succeeds || !needs_water_entry_cast(dest));
+
+        let kut2tam2_happened = match dest {
+            Some(dest) => {
+                if let Some(captured) = captured_at::<T>(&self.f, self.whose_turn, dest) {
+                    let captured_piece = piece_on_field_at::<T>(&self.f, dest)?;
+                    self.zobrist_hash ^= zobrist::piece::<T>(src, piece)
+                        ^ zobrist::piece::<T>(dest, captured_piece)
+                        ^ zobrist::piece::<T>(dest, piece)
+                        ^ zobrist_delta_on_hop1zuo1::<T>(&self.f, self.whose_turn, captured, true);
+                    T::put_into_hop1zuo1(&mut self.f, self.whose_turn, captured);
+                    let board = T::as_board_mut_absolute(&mut self.f);
+                    board.pop(dest);
+                    board.mov(src, dest);
+                    undo.moved = Some((src, dest));
+                    undo.captured = Some(captured);
+                    if self.hands.capture(self.whose_turn, captured) {
+                        return Some((0, true, false));
+                    }
+                } else if src != dest {
+                    if piece_on_field_at::<T>(&self.f, dest).is_some() {
+                        return None;
+                    }
+                    self.zobrist_hash ^=
+                        zobrist::piece::<T>(src, piece) ^ zobrist::piece::<T>(dest, piece);
+                    T::as_board_mut_absolute(&mut self.f).mov(src, dest);
+                    undo.moved = Some((src, dest));
+                }
+                steps_on_tam
+            }
+            None => {
+                !config.failure_to_complete_the_move_means_exempt_from_kut2_tam2 && steps_on_tam
+            }
+        };
+
+        Some(match (kut2tam2_happened, config.kut2tam2) {
+            (true, Consequence::Penalized { penalty, is_a_hand }) => (penalty, is_a_hand, false),
+            _ => (0, false, false),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "naive"))]
+mod tests {
+    use super::*;
+    use crate::compact::{CetkaikCompact, Square};
+    use crate::probabilistic::Probabilistic;
+    use crate::{
+        apply_after_half_acceptance, apply_inf_after_step, apply_normal_move, fixtures, message,
+        resolve, state, MoveLimitOutcome, Rate, RepetitionRule,
+    };
+    use cetkaik_naive_representation::CetkaikNaive;
+
+    /// Checks that `state.make(action, config)` agrees with `resolve(hnr, config)`, and that `unmake` restores `state`.
+    /// ／`state.make(action, config)` が `resolve(hnr, config)` と一致し、`unmake` が `state` を元に戻すことを確かめる。
+    fn check<T: Hop1Zuo1Editable + Clone + std::fmt::Debug>(
+        state: &GroundState_<T>,
+        action: Action<T::AbsoluteCoord>,
+        hnr: &state::HandNotResolved_<T>,
+        config: Config,
+    ) {
+        let mut made = state.clone();
+        let undo = made.make(action, config);
+        match resolve(hnr, config) {
+            state::HandResolved_::NeitherTymokNorTaxot(next) => {
+                assert_eq!(made, next);
+                assert_eq!(made.zobrist_hash, zobrist::hash_of_ground_state(&made));
+                assert_eq!(made.hands, next.hands);
+                assert_eq!(made.moves_in_this_season, next.moves_in_this_season);
+                assert_eq!(made.positions_in_this_season, next.positions_in_this_season);
+                made.unmake(undo.unwrap());
+            }
+            _ => assert!(undo.is_none()),
+        }
+        assert_eq!(&made, state);
+        assert_eq!(made.hands, state.hands);
+        assert_eq!(made.moves_in_this_season, state.moves_in_this_season);
+        assert_eq!(
+            made.positions_in_this_season,
+            state.positions_in_this_season
+        );
+    }
+
+    /// The outcomes of `p` with whether the water-entry cast succeeded.
+    /// ／`p` の結果と、入水判定が成功したかどうか。
+    fn water_outcomes<S>(p: Probabilistic<S>) -> Vec<(S, bool)> {
+        match p {
+            Probabilistic::Pure(s) => vec![(s, true)],
+            Probabilistic::Water { success, failure } => {
+                vec![(success, true), (failure, false)]
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Runs `check` on every action in `fixtures::sampled_positions(record)`.
+    /// ／`fixtures::sampled_positions(record)` の全ての手に `check` を適用する。
+    fn check_sampled_positions<T: Hop1Zuo1Editable + Clone + std::fmt::Debug>(
+        record: &crate::player::GameRecord<T::AbsoluteCoord>,
+    ) {
+        let config = record.config;
+        for state in fixtures::sampled_positions::<T>(record) {
+            let (hop1zuo1_candidates, candidates) = state.get_candidates(config);
+            for cand in hop1zuo1_candidates.into_iter().chain(candidates) {
+                match cand {
+                    message::PureMove__::NormalMove(msg) => {
+                        let p = apply_normal_move(&state, msg, config).unwrap();
+                        for (hnr, water_entry_succeeds) in water_outcomes(p) {
+                            let action = Action::NormalMove {
+                                msg,
+                                water_entry_succeeds,
+                            };
+                            check(&state, action, &hnr, config);
+                        }
+                    }
+                    message::PureMove__::InfAfterStep(msg) => {
+                        let p = apply_inf_after_step(&state, msg, config).unwrap();
+                        for (excited, _) in p.into_outcomes() {
+                            for after in excited.get_candidates(config) {
+                                let p =
+                                    apply_after_half_acceptance(&excited, after, config).unwrap();
+                                for (hnr, water_entry_succeeds) in water_outcomes(p) {
+                                    let action = Action::InfAfterStep {
+                                        msg,
+                                        dest: after.dest,
+                                        water_entry_succeeds,
+                                    };
+                                    check(&state, action, &hnr, config);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_unmake_agrees_with_resolve() {
+        let penalizing = Config::cerke_online_alpha()
+            .with_repetition(RepetitionRule {
+                nth: 2,
+                consequence: RepetitionConsequence::Penalized { penalty: -3 },
+            })
+            .with_move_limit(Some(MoveLimit {
+                moves: 40,
+                outcome: MoveLimitOutcome::SeasonEndsWithoutScoreChange,
+            }));
+        for config in [
+            Config::cerke_online_alpha(),
+            Config::strict_y1_huap1(),
+            penalizing,
+        ] {
+            let record = fixtures::random_game(config, 5);
+            check_sampled_positions::<CetkaikNaive>(&record);
+            check_sampled_positions::<CetkaikCompact>(&record.map(Square::from));
+        }
+    }
+
+    #[test]
+    fn test_make_unmake_on_a_failed_water_entry_that_steps_on_tam2() {
+        use cetkaik_fundamental::{AbsoluteSide::IASide, Color, Profession};
+        use cetkaik_naive_representation::absolute::{Board, Column, Coord, Field, Piece, Row};
+
+        // IA side's Kauk2 can step over Tam2 in the water and enter the water beyond it
+        // IA側の兵は水中の皇を踏み越えて、その先の水に入ることができる
+        let f = Field {
+            board: Board(std::collections::HashMap::from([
+                (Coord(Row::AI, Column::Z), Piece::Tam2),
+                (
+                    Coord(Row::AU, Column::Z),
+                    Piece::NonTam2Piece {
+                        color: Color::Kok1,
+                        prof: Profession::Kauk2,
+                        side: IASide,
+                    },
+                ),
+            ])),
+            a_side_hop1zuo1: vec![],
+            ia_side_hop1zuo1: vec![],
+        };
+        let state = fixtures::ground_state(&f, IASide, Scores::new(), Rate::X1);
+        let msg = NormalMove_::NonTamMoveSrcStepDstFinite {
+            src: Coord(Row::AU, Column::Z),
+            step: Coord(Row::AI, Column::Z),
+            dest: Coord(Row::Y, Column::Z),
+        };
+
+        let exempt = Config {
+            failure_to_complete_the_move_means_exempt_from_kut2_tam2: true,
+            ..Config::strict_y1_huap1()
+        };
+        let not_a_hand = Config {
+            kut2tam2: Consequence::Penalized {
+                penalty: -5,
+                is_a_hand: false,
+            },
+            ..Config::cerke_online_alpha()
+        };
+        for config in [
+            Config::cerke_online_alpha(),
+            Config::strict_y1_huap1(),
+            exempt,
+            not_a_hand,
+        ] {
+            assert!(state
+                .get_candidates(config)
+                .1
+                .contains(&message::PureMove__::NormalMove(msg)));
+            let Probabilistic::Water { success, failure } =
+                apply_normal_move(&state, msg, config).unwrap()
+            else {
+                panic!("the move should need a water-entry cast")
+            };
+            assert!(success.kut2tam2_happened);
+            assert_eq!(
+                failure.kut2tam2_happened,
+                !config.failure_to_complete_the_move_means_exempt_from_kut2_tam2
+            );
+            assert_eq!(failure.f, state.f);
+            for (hnr, water_entry_succeeds) in [(success, true), (failure, false)] {
+                let action = Action::NormalMove {
+                    msg,
+                    water_entry_succeeds,
+                };
+                check(&state, action, &hnr, config);
+            }
+        }
+    }
+}
@@ -0,0 +1,591 @@
+use super::message::{AfterHalfAcceptance_, PureMove__, TymokOrTaxot};
+use super::probabilistic::Probabilistic;
+use super::search::{value_of_victor, Decision, Evaluator, LOWER, UPPER};
+use super::{setup, state, Config, IfTaxot_};
+use cetkaik_fundamental::AbsoluteSide;
+use cetkaik_traits::{CetkaikRepresentation, IsBoard, IsPieceWithSide};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+/// How long the search runs. With several threads, `Iterations` is the total over all the threads.
+/// ／探索をどれだけ続けるか。複数スレッドの場合、`Iterations` は全スレッドの合計である。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum Budget {
+    Iterations(u64),
+    Time(Duration),
+}
+
+/// How to choose the final destination (`AfterHalfAcceptance_`) once the stepping-over cast is known.
+/// ／踏越え判定の結果を見た後で、最終的な移動先（`AfterHalfAcceptance_`）をどう選ぶか。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum HalfAcceptancePolicy {
+    /// Uniformly at random among the candidates, including giving up the move.
+    /// ／移動の断念も含めた候補から一様に無作為に。
+    Random,
+
+    /// A destination that captures an opponent's piece if any; otherwise uniformly at random among the destinations. Gives up the move only when there is no destination.
+    /// ／相手の駒を取れる移動先があればそれを、なければ移動先から一様に無作為に。移動先がないときに限り移動を断念する。
+    PreferCapture,
+}
+
+impl HalfAcceptancePolicy {
+    /// # Panics
+    /// Never panics, since giving up the move is always a candidate.
+    #[must_use]
+    pub fn choose<T: CetkaikRepresentation, R: Rng>(
+        self,
+        state: &state::ExcitedState_<T>,
+        config: Config,
+        rng: &mut R,
+    ) -> AfterHalfAcceptance_<T::AbsoluteCoord> {
+        let candidates = state.get_candidates(config);
+        let candidates = match self {
+            Self::Random => candidates,
+            Self::PreferCapture => {
+                let destinations: Vec<_> = candidates
+                    .into_iter()
+                    .filter(|cand| cand.dest.is_some())
+                    .collect();
+                let captures: Vec<_> = destinations
+                    .iter()
+                    .copied()
+                    .filter(|cand| {
+                        cand.dest.is_some_and(|dest| {
+                            T::as_board_absolute(&state.c.f)
+                                .peek(dest)
+                                .is_some_and(|piece| {
+                                    piece.match_on_piece_and_apply(&|| false, &|_, _, side| {
+                                        side != state.c.whose_turn
+                                    })
+                                })
+                        })
+                    })
+                    .collect();
+                if !captures.is_empty() {
+                    captures
+                } else if !destinations.is_empty() {
+                    destinations
+                } else {
+                    vec![AfterHalfAcceptance_ { dest: None }]
+                }
+            }
+        };
+        *candidates
+            .choose(rng)
+            .expect("giving up the move is always a candidate")
+    }
+}
+
+/// How the player who has formed a hand decides between tymok and taxot.
+/// ／役を作ったプレイヤーが再行と終季のどちらを選ぶか。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum TymokTaxotPolicy {
+    AlwaysTymok,
+    AlwaysTaxot,
+    Random,
+
+    /// Taxot if it wins the game or leaves the player ahead; otherwise tymok.
+    /// ／終季によってゲームに勝つか、点で上回るなら終季。そうでなければ再行。
+    TaxotIfAhead,
+}
+
+impl TymokTaxotPolicy {
+    #[must_use]
+    pub fn decide<T: CetkaikRepresentation, R: Rng>(
+        self,
+        player: AbsoluteSide,
+        if_taxot: &IfTaxot_<T>,
+        rng: &mut R,
+    ) -> TymokOrTaxot {
+        match self {
+            Self::AlwaysTymok => TymokOrTaxot::Tymok,
+            Self::AlwaysTaxot => TymokOrTaxot::Taxot,
+            Self::Random => {
+                if rng.gen() {
+                    TymokOrTaxot::Tymok
+                } else {
+                    TymokOrTaxot::Taxot
+                }
+            }
+            Self::TaxotIfAhead => {
+                let victor = match if_taxot {
+                    IfTaxot_::VictoriousSide(victor) => *victor,
+                    IfTaxot_::NextSeason(
+                        Probabilistic::Pure(next)
+                        | Probabilistic::Water { failure: next, .. }
+                        | Probabilistic::Sticks { s0: next, .. }
+                        | Probabilistic::WhoGoesFirst { ia_first: next, .. },
+                    ) => next.scores.which_side_is_winning(),
+                };
+                if victor.0 == Some(player) {
+                    TymokOrTaxot::Taxot
+                } else {
+                    TymokOrTaxot::Tymok
+                }
+            }
+        }
+    }
+}
+
+/// Visit statistics of a candidate at the root. `mean_value` is seen from the player to move at the root, within `LOWER..=UPPER`.
+/// ／ルートの候補手の訪問統計。`mean_value` はルートで手番を持つプレイヤーから見た値で、`LOWER..=UPPER` に収まる。
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct MoveStats<Coord> {
+    pub decision: Decision<Coord>,
+    pub visits: u64,
+    pub mean_value: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct MctsResult<Coord> {
+    /// The most visited candidate. `None` if the player has nothing to decide.
+    /// ／最も訪問された候補。プレイヤーが決めることが何もないなら `None`。
+    pub best_move: Option<Decision<Coord>>,
+
+    /// Sorted by the number of visits, the most visited first.
+    /// ／訪問回数の多い順。
+    pub stats: Vec<MoveStats<Coord>>,
+    pub iterations: u64,
+}
+
+/// Monte Carlo Tree Search with UCT. The tree is open-loop: a node stands for a sequence of decisions, and the chance outcomes are sampled anew in each iteration. Only the decisions at `GroundState_` form the tree; the destination after the stepping-over cast and the choice between tymok and taxot are left to the policies.
+/// With several threads, each thread grows its own tree and the root statistics are merged (root parallelization).
+/// ／UCT によるモンテカルロ木探索。木はオープンループであり、ノードは決断の列を表し、偶然手番の結果は反復ごとに新たに抽選される。木を成すのは `GroundState_` での決断だけで、踏越え判定後の移動先と再行・終季の選択は方策に任される。
+/// 複数スレッドの場合、各スレッドが自分の木を育て、ルートの統計を合算する（ルート並列化）。
+///
+/// ```
+/// use cetkaik_full_state_transition::mcts::{Budget, Mcts};
+/// use cetkaik_full_state_transition::search::score_difference;
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let state = initial_state::<CetkaikNaive>().choose().0;
+/// let result = Mcts::new(Config::cerke_online_alpha(), score_difference::<CetkaikNaive>)
+///     .with_budget(Budget::Iterations(40))
+///     .with_playout_turns(2)
+///     .with_threads(2)
+///     .search(&state);
+/// assert_eq!(result.iterations, 40);
+/// assert_eq!(result.stats.iter().map(|s| s.visits).sum::<u64>(), 40);
+/// assert_eq!(result.best_move, Some(result.stats[0].decision));
+/// ```
+pub struct Mcts<T: CetkaikRepresentation, E> {
+    config: Config,
+    evaluator: E,
+    setup: setup::Setup<T>,
+    exploration: f64,
+    budget: Budget,
+    threads: usize,
+    playout_turns: u32,
+    seed: u64,
+    half_acceptance: HalfAcceptancePolicy,
+    tymok_taxot: TymokTaxotPolicy,
+}
+
+impl<T: CetkaikRepresentation + Clone, E: Evaluator<T>> Mcts<T, E> {
+    /// By default: exploration constant √2, 1000 iterations on a single thread, playouts of 20 turns, `HalfAcceptancePolicy::PreferCapture` and `TymokTaxotPolicy::TaxotIfAhead`.
+    /// ／既定値：探索定数 √2、単一スレッドで1000回の反復、20手番のプレイアウト、`HalfAcceptancePolicy::PreferCapture`、`TymokTaxotPolicy::TaxotIfAhead`。
+    pub fn new(config: Config, evaluator: E) -> Self {
+        Self {
+            config,
+            evaluator,
+            setup: setup::Setup::yhuap_initial(),
+            exploration: std::f64::consts::SQRT_2,
+            budget: Budget::Iterations(1000),
+            threads: 1,
+            playout_turns: 20,
+            seed: 0,
+            half_acceptance: HalfAcceptancePolicy::PreferCapture,
+            tymok_taxot: TymokTaxotPolicy::TaxotIfAhead,
+        }
+    }
+
+    #[must_use]
+    pub fn with_exploration(self, exploration: f64) -> Self {
+        Self {
+            exploration,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_budget(self, budget: Budget) -> Self {
+        Self { budget, ..self }
+    }
+
+    #[must_use]
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            ..self
+        }
+    }
+
+    /// A playout stops after this many turns, and the position reached is then evaluated.
+    /// ／プレイアウトはこの手番数で打ち切られ、到達した局面が評価される。
+    #[must_use]
+    pub fn with_playout_turns(self, playout_turns: u32) -> Self {
+        Self {
+            playout_turns,
+            ..self
+        }
+    }
+
+    /// Thread `i` uses the seed `seed + i`, so that a search with an iteration budget is reproducible.
+    /// ／スレッド `i` はシード `seed + i` を用いるので、反復回数で予算を決めた探索は再現できる。
+    #[must_use]
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    #[must_use]
+    pub fn with_policies(
+        self,
+        half_acceptance: HalfAcceptancePolicy,
+        tymok_taxot: TymokTaxotPolicy,
+    ) -> Self {
+        Self {
+            half_acceptance,
+            tymok_taxot,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_setup(self, setup: setup::Setup<T>) -> Self {
+        Self { setup, ..self }
+    }
+
+    /// # Panics
+    /// Panics if a thread panics.
+    #[allow(clippy::cast_precision_loss)] /* visit counts never get anywhere near 2^52 */
+    pub fn search(&self, root: &state::GroundState_<T>) -> MctsResult<T::AbsoluteCoord>
+    where
+        T::AbsoluteField: Send + Sync,
+        T::AbsoluteCoord: Send + Sync,
+        E: Sync,
+    {
+        let trees: Vec<Tree<T::AbsoluteCoord>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.threads)
+                .map(|i| {
+                    let budget = match self.budget {
+                        Budget::Iterations(n) => {
+                            /* distribute the remainder to the first threads */
+                            let n = n / self.threads as u64
+                                + u64::from((i as u64) < n % self.threads as u64);
+                            Budget::Iterations(n)
+                        }
+                        Budget::Time(time) => Budget::Time(time),
+                    };
+                    scope.spawn(move || self.grow_tree(root, self.seed + i as u64, budget))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("a search thread panicked"))
+                .collect()
+        });
+
+        let mut stats: Vec<MoveStats<T::AbsoluteCoord>> = vec![];
+        for tree in &trees {
+            for &child in &tree.nodes[0].children {
+                let node = &tree.nodes[child];
+                let decision = node.decision.expect("a child always has a decision");
+                match stats.iter_mut().find(|s| s.decision == decision) {
+                    Some(s) => {
+                        s.visits += node.visits;
+                        s.mean_value += node.total_value;
+                    }
+                    None => stats.push(MoveStats {
+                        decision,
+                        visits: node.visits,
+                        mean_value: node.total_value,
+                    }),
+                }
+            }
+        }
+        for s in &mut stats {
+            /* `mean_value` has held the total so far */
+            s.mean_value /= s.visits.max(1) as f64;
+        }
+        stats.sort_by_key(|s| std::cmp::Reverse(s.visits));
+
+        MctsResult {
+            best_move: stats.first().map(|s| s.decision),
+            stats,
+            iterations: trees.iter().map(|tree| tree.nodes[0].visits).sum(),
+        }
+    }
+
+    fn grow_tree(
+        &self,
+        root: &state::GroundState_<T>,
+        seed: u64,
+        budget: Budget,
+    ) -> Tree<T::AbsoluteCoord> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut tree = Tree {
+            nodes: vec![Node {
+                decision: None,
+                children: vec![],
+                visits: 0,
+                total_value: 0.0,
+            }],
+        };
+        let start = Instant::now();
+        let mut iterations = 0;
+        while match budget {
+            Budget::Iterations(n) => iterations < n,
+            Budget::Time(time) => start.elapsed() < time,
+        } {
+            self.iterate(&mut tree, root, &mut rng);
+            iterations += 1;
+        }
+        tree
+    }
+
+    /// Selection, expansion, playout and backpropagation. Each node on the path is credited from the point of view of the player who made the decision leading to it.
+    /// ／選択・展開・プレイアウト・逆伝播。経路上の各ノードは、そこに至る決断を下したプレイヤーの視点で加点される。
+    fn iterate(
+        &self,
+        tree: &mut Tree<T::AbsoluteCoord>,
+        root: &state::GroundState_<T>,
+        rng: &mut StdRng,
+    ) {
+        let mut path: Vec<(usize, AbsoluteSide)> = vec![];
+        let mut node = 0;
+        let mut state = root.clone();
+        let value = loop {
+            let decisions = self.legal_decisions(&state);
+            if decisions.is_empty() {
+                break value_of_victor(state.scores.which_side_is_winning());
+            }
+            let player = state.whose_turn;
+
+            let untried: Vec<_> = decisions
+                .iter()
+                .filter(|d| {
+                    !tree.nodes[node]
+                        .children
+                        .iter()
+                        .any(|&c| tree.nodes[c].decision == Some(**d))
+                })
+                .copied()
+                .collect();
+            let expanding = !untried.is_empty();
+            let child = if let Some(decision) = untried.choose(rng) {
+                tree.nodes.push(Node {
+                    decision: Some(*decision),
+                    children: vec![],
+                    visits: 0,
+                    total_value: 0.0,
+                });
+                let child = tree.nodes.len() - 1;
+                tree.nodes[node].children.push(child);
+                child
+            } else {
+                self.select(tree, node, &decisions)
+            };
+            path.push((child, player));
+
+            let decision = tree.nodes[child]
+                .decision
+                .expect("a child always has a decision");
+            match self.play(&state, decision, rng) {
+                Some(After::Ground(next)) => state = next,
+                Some(After::End(value)) => break value,
+                None => break 0.0, /* an inconsistent `Config` */
+            }
+            if expanding {
+                break self.playout(state, rng);
+            }
+            node = child;
+        };
+
+        tree.nodes[0].visits += 1;
+        for (index, player) in path {
+            let node = &mut tree.nodes[index];
+            node.visits += 1;
+            node.total_value += match player {
+                AbsoluteSide::IASide => value,
+                AbsoluteSide::ASide => -value,
+            };
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)] /* visit counts never get anywhere near 2^52 */
+    fn select(
+        &self,
+        tree: &Tree<T::AbsoluteCoord>,
+        node: usize,
+        legal: &[Decision<T::AbsoluteCoord>],
+    ) -> usize {
+        let parent_visits = tree.nodes[node].visits.max(1) as f64;
+        tree.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .filter(|&c| {
+                tree.nodes[c]
+                    .decision
+                    .is_some_and(|decision| legal.contains(&decision))
+            })
+            .max_by(|&a, &b| {
+                let uct = |c: usize| {
+                    let child = &tree.nodes[c];
+                    let visits = child.visits.max(1) as f64;
+                    child.total_value / visits
+                        + self.exploration * (parent_visits.ln() / visits).sqrt()
+                };
+                uct(a).total_cmp(&uct(b))
+            })
+            .expect("every legal decision has already been expanded")
+    }
+
+    /// Plays uniformly random decisions for at most `playout_turns` turns, and then evaluates.
+    /// ／最大 `playout_turns` 手番まで一様無作為に決断し、それから評価する。
+    fn playout(&self, mut state: state::GroundState_<T>, rng: &mut StdRng) -> f64 {
+        for _ in 0..self.playout_turns {
+            let decisions = self.legal_decisions(&state);
+            let Some(decision) = decisions.choose(rng) else {
+                return value_of_victor(state.scores.which_side_is_winning());
+            };
+            match self.play(&state, *decision, rng) {
+                Some(After::Ground(next)) => state = next,
+                Some(After::End(value)) => return value,
+                None => return 0.0,
+            }
+        }
+        self.evaluator.evaluate(&state).clamp(LOWER, UPPER)
+    }
+
+    /// Empty when the player is stuck and the game ends because of that.
+    /// ／手詰まりでゲームが終わるなら空。
+    fn legal_decisions(&self, state: &state::GroundState_<T>) -> Vec<Decision<T::AbsoluteCoord>> {
+        let (hop1zuo1_candidates, candidates) = state.get_candidates(self.config);
+        let stuck = hop1zuo1_candidates.is_empty() && candidates.is_empty();
+        let mut decisions: Vec<Decision<T::AbsoluteCoord>> = vec![];
+        for cand in hop1zuo1_candidates.into_iter().chain(candidates) {
+            let decision = Decision::Move(cand);
+            if !decisions.contains(&decision) {
+                decisions.push(decision);
+            }
+        }
+        if self.config.voluntary_pass_is_allowed
+            || (stuck && self.config.player_without_any_legal_move_passes)
+        {
+            decisions.push(Decision::Pass);
+        }
+        decisions
+    }
+
+    /// Plays a decision at `GroundState_`, sampling the chance outcomes and leaving the rest of the turn to the policies. `decision` must be one of `legal_decisions`, as it is not checked again. `None` if the decision is rejected.
+    /// ／`GroundState_` で決断を下し、偶然手番の結果を抽選し、手番の残りを方策に任せる。`decision` は再検査されないので、`legal_decisions` の一つでなければならない。決断が拒否されたら `None`。
+    fn play(
+        &self,
+        state: &state::GroundState_<T>,
+        decision: Decision<T::AbsoluteCoord>,
+        rng: &mut StdRng,
+    ) -> Option<After<T>> {
+        let config = self.config;
+        let hand_not_resolved = match decision {
+            Decision::Move(PureMove__::NormalMove(msg)) => {
+                super::apply_normal_move_unchecked(state, msg, config)
+                    .ok()?
+                    .choose_by_uniform_random_variable(rng.gen())
+                    .0
+            }
+            Decision::Move(PureMove__::InfAfterStep(msg)) => {
+                let (excited, _) = super::apply_inf_after_step_unchecked(state, msg)
+                    .choose_by_uniform_random_variable(rng.gen());
+                let msg = self.half_acceptance.choose(&excited, config, rng);
+                super::apply_after_half_acceptance(&excited, msg, config)
+                    .ok()?
+                    .choose_by_uniform_random_variable(rng.gen())
+                    .0
+            }
+            Decision::Pass => {
+                let resolved =
+                    super::apply_pass_with_setup(state, super::message::Pass, config, &self.setup)
+                        .ok()?;
+                return Some(self.after_resolution(resolved, rng));
+            }
+            Decision::AfterHalfAcceptance(_) | Decision::TymokOrTaxot(_) => return None,
+        };
+        let resolved = super::resolve_with_setup(&hand_not_resolved, config, &self.setup);
+        Some(self.after_resolution(resolved, rng))
+    }
+
+    fn after_resolution(&self, resolved: state::HandResolved_<T>, rng: &mut StdRng) -> After<T> {
+        let if_taxot = match resolved {
+            state::HandResolved_::NeitherTymokNorTaxot(next) => return After::Ground(next),
+            state::HandResolved_::GameEndsWithoutTymokTaxot(victor) => {
+                return After::End(value_of_victor(victor))
+            }
+            state::HandResolved_::SeasonEndsWithoutTymokTaxot(if_taxot) => if_taxot,
+            state::HandResolved_::HandExists { if_tymok, if_taxot } => {
+                match self
+                    .tymok_taxot
+                    .decide(!if_tymok.whose_turn, &if_taxot, rng)
+                {
+                    TymokOrTaxot::Tymok => return After::Ground(if_tymok),
+                    TymokOrTaxot::Taxot => if_taxot,
+                }
+            }
+        };
+        match if_taxot {
+            IfTaxot_::VictoriousSide(victor) => After::End(value_of_victor(victor)),
+            IfTaxot_::NextSeason(p) => {
+                After::Ground(p.choose_by_uniform_random_variable(rng.gen()).0)
+            }
+        }
+    }
+}
+
+enum After<T: CetkaikRepresentation> {
+    Ground(state::GroundState_<T>),
+
+    /// The game has ended, with this value for IA side.
+    /// ／ゲームが終わり、IA 側にとっての値はこれ。
+    End(f64),
+}
+
+struct Tree<Coord> {
+    /// `nodes[0]` is the root.
+    /// ／`nodes[0]` が根。
+    nodes: Vec<Node<Coord>>,
+}
+
+struct Node<Coord> {
+    decision: Option<Decision<Coord>>,
+    children: Vec<usize>,
+    visits: u64,
+
+    /// Seen from the player who made `decision`.
+    /// ／`decision` を下したプレイヤーから見た値。
+    total_value: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{position_with_a_winning_hand, WINNING_CAPTURE};
+    use crate::search;
+
+    #[test]
+    fn test_mcts_finds_a_winning_hand() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        let result = Mcts::new(
+            Config::cerke_online_alpha(),
+            search::score_difference::<CetkaikNaive>,
+        )
+        .with_budget(Budget::Iterations(1000))
+        .with_playout_turns(0)
+        .with_threads(2)
+        .search(&position_with_a_winning_hand());
+        assert_eq!(result.best_move, Some(WINNING_CAPTURE));
+        assert!((result.stats[0].mean_value - 1.0).abs() < f64::EPSILON);
+    }
+}
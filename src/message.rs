@@ -73,6 +73,76 @@ impl<T> From<PureMove_<T>> for PureMove__<T> {
     }
 }
 
+impl<T> PureMove__<T> {
+    /// Applies `f` to every coordinate in the move, e.g. to convert between representations or to mirror the board.
+    /// ／手に含まれる全ての座標に `f` を適用する。表現の間の変換や盤面の反転などに。
+    #[must_use]
+    pub fn map<U>(self, f: impl Fn(T) -> U) -> PureMove__<U> {
+        match self {
+            Self::InfAfterStep(InfAfterStep_ {
+                src,
+                step,
+                planned_direction,
+            }) => PureMove__::InfAfterStep(InfAfterStep_ {
+                src: f(src),
+                step: f(step),
+                planned_direction: f(planned_direction),
+            }),
+            Self::NormalMove(NormalMove_::NonTamMoveSrcDst { src, dest }) => {
+                PureMove__::NormalMove(NormalMove_::NonTamMoveSrcDst {
+                    src: f(src),
+                    dest: f(dest),
+                })
+            }
+            Self::NormalMove(NormalMove_::NonTamMoveSrcStepDstFinite { src, step, dest }) => {
+                PureMove__::NormalMove(NormalMove_::NonTamMoveSrcStepDstFinite {
+                    src: f(src),
+                    step: f(step),
+                    dest: f(dest),
+                })
+            }
+            Self::NormalMove(NormalMove_::NonTamMoveFromHopZuo { color, prof, dest }) => {
+                PureMove__::NormalMove(NormalMove_::NonTamMoveFromHopZuo {
+                    color,
+                    prof,
+                    dest: f(dest),
+                })
+            }
+            Self::NormalMove(NormalMove_::TamMoveNoStep {
+                src,
+                first_dest,
+                second_dest,
+            }) => PureMove__::NormalMove(NormalMove_::TamMoveNoStep {
+                src: f(src),
+                first_dest: f(first_dest),
+                second_dest: f(second_dest),
+            }),
+            Self::NormalMove(NormalMove_::TamMoveStepsDuringFormer {
+                src,
+                step,
+                first_dest,
+                second_dest,
+            }) => PureMove__::NormalMove(NormalMove_::TamMoveStepsDuringFormer {
+                src: f(src),
+                step: f(step),
+                first_dest: f(first_dest),
+                second_dest: f(second_dest),
+            }),
+            Self::NormalMove(NormalMove_::TamMoveStepsDuringLatter {
+                src,
+                step,
+                first_dest,
+                second_dest,
+            }) => PureMove__::NormalMove(NormalMove_::TamMoveStepsDuringLatter {
+                src: f(src),
+                step: f(step),
+                first_dest: f(first_dest),
+                second_dest: f(second_dest),
+            }),
+        }
+    }
+}
+
 impl<Coord: std::fmt::Display> std::fmt::Display for PureMove__<Coord> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -91,7 +161,7 @@ impl<Coord: std::fmt::Display> std::fmt::Display for PureMove__<Coord> {
                 )
             }
             PureMove__::NormalMove(NormalMove_::NonTamMoveSrcDst { src, dest }) => {
-                write!(f, "{src}片{dest}",)
+                write!(f, "{src}片{dest}")
             }
             PureMove__::NormalMove(NormalMove_::NonTamMoveSrcStepDstFinite { src, dest, step }) => {
                 write!(f, "{src}片{step}{dest}")
@@ -136,6 +206,11 @@ pub struct AfterHalfAcceptance_<T> {
     pub dest: Option<T>,
 }
 
+/// Describes a pass, i.e. handing over the turn without moving any piece. Whether it is allowed depends on `Config`.
+/// ／駒を動かさずに手番を渡すパスを表現する型。許されるかどうかは `Config` による。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub struct Pass;
+
 /// Describes all the moves except those that require a stepping-over cast
 /// (that is, when after stepping over a piece you plan to make a movement with infinite range).
 /// ／踏越え判定が不要なタイプの移動を表現する型。
@@ -173,3 +248,11 @@ pub enum NormalMove_<T> {
         second_dest: T,
     },
 }
+
+/// Describes the decision that the player who has formed a hand makes: either to continue the season with a doubled stake (tymok) or to end the season (taxot).
+/// ／役を作ったプレイヤーが下す決断。賭け金を倍にして季節を続ける（再行）か、季節を終える（終季）か。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum TymokOrTaxot {
+    Tymok,
+    Taxot,
+}
@@ -0,0 +1,433 @@
+use super::message::{NormalMove_, PureMove__};
+use super::state::{ExcitedState_, GroundState_, HandNotResolved_, HandResolved_};
+use super::{setup, Config, IfTaxot_};
+use cetkaik_traits::CetkaikRepresentation;
+
+/// The result of `perft`. Every count other than `nodes` describes the last turn, i.e. the turns that lead into the leaves, in the same way as the breakdown of a chess perft.
+/// Every outcome of a `Probabilistic` is a separate branch, regardless of its probability.
+/// ／`perft` の結果。`nodes` 以外の数はすべて最後の手番、つまり葉に至る手番についてのもの。チェスの perft の内訳と同様。
+/// `Probabilistic` の各結果は、確率によらずそれぞれ別の枝として数える。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PerftCounts {
+    /// The number of leaves: the `GroundState_`s reached after exactly `depth` turns, plus the games that end in the last turn.
+    /// ／葉の数。ちょうど `depth` 手番後に至る `GroundState_` と、最後の手番で終わる対局の数の和。
+    pub nodes: u64,
+
+    /// Moves of a non-Tam2 piece on the board, whether or not it steps over a piece.
+    /// ／盤上の皇以外の駒の移動。踏越えの有無は問わない。
+    pub non_tam_moves: u64,
+
+    /// Moves of Tam2.
+    /// ／皇の移動。
+    pub tam_moves: u64,
+
+    /// Pieces placed from hop1zuo1.
+    /// ／手駒を打つ手。
+    pub drops: u64,
+
+    /// Moves that step over a piece and then cast the sticks to decide how far to go.
+    /// ／踏越えの後に投げ棒で行き先を決める手。
+    pub inf_after_steps: u64,
+
+    /// The options after the sticks of an `inf_after_steps` have been cast, summed over the six outcomes of the sticks.
+    /// ／`inf_after_steps` の投げ棒の後の選択肢。投げ棒の6通りの結果にわたって合計する。
+    pub after_half_acceptances: u64,
+
+    /// Passes, whether voluntary or because no move is possible.
+    /// ／パス。自発的かどうかは問わない。
+    pub passes: u64,
+
+    /// Moves that require a water cast; the success and the failure are both expanded.
+    /// ／入水判定を要する手。成功と失敗の両方を展開する。
+    pub water_casts: u64,
+
+    /// Turns in which a hand has been formed, before choosing between tymok and taxot.
+    /// ／役が成立した手番。再行と終季の選択の前に数える。
+    pub hands: u64,
+
+    /// Leaves in which the game has ended.
+    /// ／対局が終わった葉。
+    pub game_ends: u64,
+
+    /// Leaves in which a new season has started.
+    /// ／新しい季節が始まった葉。
+    pub season_ends: u64,
+}
+
+impl std::ops::AddAssign for PerftCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.nodes += rhs.nodes;
+        self.non_tam_moves += rhs.non_tam_moves;
+        self.tam_moves += rhs.tam_moves;
+        self.drops += rhs.drops;
+        self.inf_after_steps += rhs.inf_after_steps;
+        self.after_half_acceptances += rhs.after_half_acceptances;
+        self.passes += rhs.passes;
+        self.water_casts += rhs.water_casts;
+        self.hands += rhs.hands;
+        self.game_ends += rhs.game_ends;
+        self.season_ends += rhs.season_ends;
+    }
+}
+
+/// Counts the tree of legal moves from `state` to `depth` turns. The duplicates in `get_candidates` count only once, as they lead to the same states.
+/// ／`state` から `depth` 手番までの合法手の木を数える。`get_candidates` の重複は同じ状態に至るので一度だけ数える。
+///
+/// ```
+/// use cetkaik_full_state_transition::probabilistic::Probabilistic;
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let Probabilistic::WhoGoesFirst { ia_first, .. } = initial_state::<CetkaikNaive>() else {
+///     unreachable!()
+/// };
+/// let counts = perft::perft(&ia_first, Config::cerke_online_alpha(), 0);
+/// assert_eq!(counts.nodes, 1);
+/// ```
+#[must_use]
+pub fn perft<T: CetkaikRepresentation + Clone>(
+    state: &GroundState_<T>,
+    config: Config,
+    depth: u32,
+) -> PerftCounts {
+    perft_with_setup(state, config, &setup::Setup::yhuap_initial(), depth)
+}
+
+/// Same as `perft`, except that the next season starts from the position described by `setup`.
+/// ／`perft` と同様だが、次の季節は `setup` の局面から始まる。
+#[must_use]
+pub fn perft_with_setup<T: CetkaikRepresentation + Clone>(
+    state: &GroundState_<T>,
+    config: Config,
+    setup: &setup::Setup<T>,
+    depth: u32,
+) -> PerftCounts {
+    Perft { config, setup }.ground(state, depth)
+}
+
+struct Perft<'a, T: CetkaikRepresentation> {
+    config: Config,
+    setup: &'a setup::Setup<T>,
+}
+
+impl<T: CetkaikRepresentation + Clone> Perft<'_, T> {
+    fn ground(&self, state: &GroundState_<T>, depth: u32) -> PerftCounts {
+        if depth == 0 {
+            return PerftCounts {
+                nodes: 1,
+                ..PerftCounts::default()
+            };
+        }
+        let last_turn = depth == 1;
+        let mut counts = PerftCounts::default();
+
+        let (hop1zuo1_candidates, candidates) = state.get_candidates(self.config);
+        if hop1zuo1_candidates.is_empty() && candidates.is_empty() {
+            if let Ok(resolved) =
+                super::no_move_possible_at_all_with_setup(state, self.config, self.setup)
+            {
+                if last_turn {
+                    counts.passes += 1;
+                }
+                counts += self.hand_resolved(resolved, depth);
+            }
+            return counts;
+        }
+
+        let mut moves = vec![];
+        for cand in hop1zuo1_candidates.into_iter().chain(candidates) {
+            if !moves.contains(&cand) {
+                moves.push(cand);
+            }
+        }
+        for mv in moves {
+            match mv {
+                PureMove__::NormalMove(msg) => {
+                    let Ok(p) = super::apply_normal_move(state, msg, self.config) else {
+                        continue;
+                    };
+                    if last_turn {
+                        match msg {
+                            NormalMove_::NonTamMoveSrcDst { .. }
+                            | NormalMove_::NonTamMoveSrcStepDstFinite { .. } => {
+                                counts.non_tam_moves += 1;
+                            }
+                            NormalMove_::NonTamMoveFromHopZuo { .. } => counts.drops += 1,
+                            NormalMove_::TamMoveNoStep { .. }
+                            | NormalMove_::TamMoveStepsDuringFormer { .. }
+                            | NormalMove_::TamMoveStepsDuringLatter { .. } => {
+                                counts.tam_moves += 1;
+                            }
+                        }
+                    }
+                    counts += self.hand_not_resolved(p, depth);
+                }
+                PureMove__::InfAfterStep(msg) => {
+                    let Ok(p) = super::apply_inf_after_step(state, msg, self.config) else {
+                        continue;
+                    };
+                    if last_turn {
+                        counts.inf_after_steps += 1;
+                    }
+                    for (excited, _) in p.into_outcomes() {
+                        counts += self.excited(&excited, depth);
+                    }
+                }
+            }
+        }
+
+        if self.config.voluntary_pass_is_allowed {
+            if let Ok(resolved) =
+                super::apply_pass_with_setup(state, super::message::Pass, self.config, self.setup)
+            {
+                if last_turn {
+                    counts.passes += 1;
+                }
+                counts += self.hand_resolved(resolved, depth);
+            }
+        }
+        counts
+    }
+
+    fn excited(&self, state: &ExcitedState_<T>, depth: u32) -> PerftCounts {
+        let mut counts = PerftCounts::default();
+        for msg in state.get_candidates(self.config) {
+            let Ok(p) = super::apply_after_half_acceptance(state, msg, self.config) else {
+                continue;
+            };
+            if depth == 1 {
+                counts.after_half_acceptances += 1;
+            }
+            counts += self.hand_not_resolved(p, depth);
+        }
+        counts
+    }
+
+    fn hand_not_resolved(
+        &self,
+        p: super::Probabilistic<HandNotResolved_<T>>,
+        depth: u32,
+    ) -> PerftCounts {
+        let mut counts = PerftCounts::default();
+        if depth == 1 && matches!(p, super::Probabilistic::Water { .. }) {
+            counts.water_casts += 1;
+        }
+        for (hand_not_resolved, _) in p.into_outcomes() {
+            let resolved = super::resolve_with_setup(&hand_not_resolved, self.config, self.setup);
+            counts += self.hand_resolved(resolved, depth);
+        }
+        counts
+    }
+
+    fn hand_resolved(&self, resolved: HandResolved_<T>, depth: u32) -> PerftCounts {
+        match resolved {
+            HandResolved_::NeitherTymokNorTaxot(next) => self.ground(&next, depth - 1),
+            HandResolved_::GameEndsWithoutTymokTaxot(_) => Self::game_end(depth),
+            HandResolved_::SeasonEndsWithoutTymokTaxot(if_taxot) => self.if_taxot(if_taxot, depth),
+            HandResolved_::HandExists { if_tymok, if_taxot } => {
+                let mut counts = self.ground(&if_tymok, depth - 1);
+                counts += self.if_taxot(if_taxot, depth);
+                if depth == 1 {
+                    counts.hands += 1;
+                }
+                counts
+            }
+        }
+    }
+
+    fn if_taxot(&self, if_taxot: IfTaxot_<T>, depth: u32) -> PerftCounts {
+        match if_taxot {
+            IfTaxot_::VictoriousSide(_) => Self::game_end(depth),
+            IfTaxot_::NextSeason(p) => {
+                let mut counts = PerftCounts::default();
+                for (next, _) in p.into_outcomes() {
+                    counts += self.ground(&next, depth - 1);
+                    if depth == 1 {
+                        counts.season_ends += 1;
+                    }
+                }
+                counts
+            }
+        }
+    }
+
+    /// A game that has ended is a leaf only in the last turn, just as a checkmate in a chess perft.
+    /// ／終わった対局は、チェスの perft の詰みと同様、最後の手番でのみ葉となる。
+    fn game_end(depth: u32) -> PerftCounts {
+        if depth == 1 {
+            PerftCounts {
+                nodes: 1,
+                game_ends: 1,
+                ..PerftCounts::default()
+            }
+        } else {
+            PerftCounts::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initial_state;
+    use crate::probabilistic::Probabilistic;
+
+    fn check_perft_table(table: &[(&str, Config, u32, PerftCounts)]) {
+        fn check<T: CetkaikRepresentation + Clone>(table: &[(&str, Config, u32, PerftCounts)]) {
+            let Probabilistic::WhoGoesFirst { ia_first, a_first } = initial_state::<T>() else {
+                unreachable!()
+            };
+            for (name, config, depth, expected) in table {
+                // the two first movers mirror each other
+                // 二つの先手は互いに鏡像である
+                for state in [&ia_first, &a_first] {
+                    assert_eq!(
+                        perft(state, *config, *depth),
+                        *expected,
+                        "{name} at depth {depth}"
+                    );
+                }
+            }
+        }
+        // The representations must agree on every count
+        // どの表現でも数は一致しなければならない
+        check::<cetkaik_naive_representation::CetkaikNaive>(table);
+        #[cfg(feature = "naive")]
+        check::<crate::compact::CetkaikCompact>(table);
+    }
+
+    /// The counts are what `perft` reported when it was written, kept to catch regressions; they do not come from an independent implementation. Instead, `check_perft_table` requires both representations to agree on them, and the moves of the first turn are counted again directly from `cetkaik_yhuap_move_candidates`.
+    /// ／この数は `perft` を書いたときに `perft` 自身が出力したもので、退行を検出するために残している。独立した実装から得たものではない。その代わり、`check_perft_table` は両方の表現がこれに一致することを要求し、最初の手番の手は `cetkaik_yhuap_move_candidates` から直接数え直す。
+    #[test]
+    fn test_perft() {
+        use cetkaik_fundamental::PureMove_;
+        use cetkaik_naive_representation::CetkaikNaive;
+
+        let Probabilistic::WhoGoesFirst { ia_first, .. } = initial_state::<CetkaikNaive>() else {
+            unreachable!()
+        };
+        let moves = cetkaik_yhuap_move_candidates::not_from_hop1zuo1_candidates_vec::<CetkaikNaive>(
+            &cetkaik_yhuap_move_candidates::AllowKut2Tam2 {
+                allow_kut2tam2: true,
+            },
+            false,
+            ia_first.whose_turn,
+            &ia_first.f,
+        );
+        let count = |pred: fn(&PureMove_<_>) -> bool| moves.iter().filter(|mv| pred(mv)).count();
+        assert_eq!(
+            count(|mv| matches!(
+                mv,
+                PureMove_::NonTamMoveSrcDst { .. } | PureMove_::NonTamMoveSrcStepDstFinite { .. }
+            )),
+            82
+        );
+        assert_eq!(
+            count(|mv| matches!(
+                mv,
+                PureMove_::TamMoveNoStep { .. }
+                    | PureMove_::TamMoveStepsDuringFormer { .. }
+                    | PureMove_::TamMoveStepsDuringLatter { .. }
+            )),
+            126
+        );
+        assert_eq!(count(|mv| matches!(mv, PureMove_::InfAfterStep { .. })), 41);
+        assert_eq!(
+            count(|mv| matches!(
+                mv,
+                PureMove_::NonTamMoveSrcDst {
+                    is_water_entry_ciurl: true,
+                    ..
+                } | PureMove_::NonTamMoveSrcStepDstFinite {
+                    is_water_entry_ciurl: true,
+                    ..
+                }
+            )),
+            4
+        );
+
+        check_perft_table(&[
+            (
+                "cerke_online_alpha",
+                Config::cerke_online_alpha(),
+                1,
+                PerftCounts {
+                    nodes: 812,
+                    non_tam_moves: 82,
+                    tam_moves: 126,
+                    drops: 0,
+                    inf_after_steps: 41,
+                    after_half_acceptances: 600,
+                    passes: 0,
+                    water_casts: 4,
+                    hands: 0,
+                    game_ends: 0,
+                    season_ends: 0,
+                },
+            ),
+            (
+                "strict_y1_huap1",
+                Config::strict_y1_huap1(),
+                1,
+                PerftCounts {
+                    nodes: 767,
+                    non_tam_moves: 82,
+                    tam_moves: 126,
+                    drops: 0,
+                    inf_after_steps: 41,
+                    after_half_acceptances: 429,
+                    passes: 0,
+                    water_casts: 4,
+                    hands: 63,
+                    game_ends: 0,
+                    season_ends: 126,
+                },
+            ),
+        ]);
+    }
+
+    /// Run with `cargo test --release -- --ignored`.
+    /// ／`cargo test --release -- --ignored` で実行する。
+    #[test]
+    #[ignore = "takes a few minutes even in release mode"]
+    fn test_perft_depth_2() {
+        check_perft_table(&[
+            (
+                "cerke_online_alpha",
+                Config::cerke_online_alpha(),
+                2,
+                PerftCounts {
+                    nodes: 680_456,
+                    non_tam_moves: 67345,
+                    tam_moves: 87586,
+                    drops: 0,
+                    inf_after_steps: 34713,
+                    after_half_acceptances: 514_251,
+                    passes: 0,
+                    water_casts: 9882,
+                    hands: 696,
+                    game_ends: 0,
+                    season_ends: 1392,
+                },
+            ),
+            (
+                "strict_y1_huap1",
+                Config::strict_y1_huap1(),
+                2,
+                PerftCounts {
+                    nodes: 612_319,
+                    non_tam_moves: 63435,
+                    tam_moves: 93425,
+                    drops: 0,
+                    inf_after_steps: 32977,
+                    after_half_acceptances: 342_897,
+                    passes: 0,
+                    water_casts: 5168,
+                    hands: 53697,
+                    game_ends: 0,
+                    season_ends: 107_394,
+                },
+            ),
+        ]);
+    }
+}
@@ -0,0 +1,670 @@
+use super::mcts::{HalfAcceptancePolicy, TymokTaxotPolicy};
+use super::message::{AfterHalfAcceptance_, Pass, PureMove__, TymokOrTaxot};
+use super::search::Decision;
+use super::{state, Config, IfTaxot_, Season, Victor};
+use cetkaik_fundamental::AbsoluteSide;
+use cetkaik_traits::CetkaikRepresentation;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+/// A bot, or anything else that makes the decisions of a player. Each method is called only at its own decision point, and is given the valid candidates whenever there are any to choose from.
+/// ／ボットなど、プレイヤーの決断を下すもの。各メソッドは対応する決断の場面でのみ呼ばれ、選ぶべき候補があるときはそれが渡される。
+pub trait Player<T: CetkaikRepresentation> {
+    /// Chooses a move from a `GroundState_`. `candidates` is never empty.
+    /// ／`GroundState_` から指す手を選ぶ。`candidates` は空でない。
+    fn play_move(
+        &mut self,
+        state: &state::GroundState_<T>,
+        config: Config,
+        candidates: &[PureMove__<T::AbsoluteCoord>],
+        rng: &mut StdRng,
+    ) -> PureMove__<T::AbsoluteCoord>;
+
+    /// Chooses the final destination after seeing the stepping-over cast. `candidates` always contains `AfterHalfAcceptance_ { dest: None }`.
+    /// ／踏越え判定の結果を見て最終的な移動先を選ぶ。`candidates` は常に `AfterHalfAcceptance_ { dest: None }` を含む。
+    fn after_half_acceptance(
+        &mut self,
+        state: &state::ExcitedState_<T>,
+        config: Config,
+        candidates: &[AfterHalfAcceptance_<T::AbsoluteCoord>],
+        rng: &mut StdRng,
+    ) -> AfterHalfAcceptance_<T::AbsoluteCoord>;
+
+    /// Decides whether to continue the season after forming a hand.
+    /// ／役を作った後で季節を続けるかどうかを決める。
+    fn tymok_or_taxot(
+        &mut self,
+        if_tymok: &state::GroundState_<T>,
+        if_taxot: &IfTaxot_<T>,
+        config: Config,
+        rng: &mut StdRng,
+    ) -> TymokOrTaxot;
+
+    /// Called before `play_move` only when `config.voluntary_pass_is_allowed` is set. Never passes by default.
+    /// ／`config.voluntary_pass_is_allowed` が設定されているときに限り、`play_move` の前に呼ばれる。既定ではパスしない。
+    fn wants_to_pass(
+        &mut self,
+        _state: &state::GroundState_<T>,
+        _config: Config,
+        _rng: &mut StdRng,
+    ) -> bool {
+        false
+    }
+}
+
+/// Something that happened in a game, in the order of occurrence.
+/// ／ゲーム中に起こったこと。起こった順に並べる。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum Event<Coord> {
+    SeasonStarts {
+        season: Season,
+        first_mover: AbsoluteSide,
+    },
+    Decision {
+        player: AbsoluteSide,
+        decision: Decision<Coord>,
+    },
+
+    /// The number of sticks that came up, either in a stepping-over cast or in a water entry cast.
+    /// ／踏越え判定または入水判定で表が出た投げ棒の本数。
+    Ciurl(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct GameRecord<Coord> {
+    pub seed: u64,
+    pub config: Config,
+    pub events: Vec<Event<Coord>>,
+
+    /// IA side's score when the game ended: 40 or 0 if a player was knocked out.
+    /// ／ゲーム終了時の IA 側の得点。片方の点が尽きた場合は40か0。
+    pub final_ia_score: i32,
+}
+
+/// One event per line, so that a dumped game can be read and replayed later.
+/// ／一行に一つの出来事を書くので、書き出した対局を後で読んで再現できる。
+impl<Coord: std::fmt::Display> std::fmt::Display for GameRecord<Coord> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "seed {}", self.seed)?;
+        for event in &self.events {
+            match event {
+                Event::SeasonStarts {
+                    season,
+                    first_mover,
+                } => writeln!(f, "season {season:?} {first_mover:?}")?,
+                Event::Decision { player, decision } => writeln!(f, "{player:?} {decision}")?,
+                Event::Ciurl(ciurl) => writeln!(f, "ciurl {ciurl}")?,
+            }
+        }
+        writeln!(f, "final {}", self.final_ia_score)
+    }
+}
+
+impl<T> GameRecord<T> {
+    /// Applies `f` to every coordinate in the record, e.g. to replay it with another representation.
+    /// ／記録に含まれる全ての座標に `f` を適用する。別の表現で再現するためなどに。
+    #[must_use]
+    pub fn map<U>(self, f: impl Fn(T) -> U) -> GameRecord<U> {
+        GameRecord {
+            seed: self.seed,
+            config: self.config,
+            events: self
+                .events
+                .into_iter()
+                .map(|event| match event {
+                    Event::SeasonStarts {
+                        season,
+                        first_mover,
+                    } => Event::SeasonStarts {
+                        season,
+                        first_mover,
+                    },
+                    Event::Decision { player, decision } => Event::Decision {
+                        player,
+                        decision: decision.map(&f),
+                    },
+                    Event::Ciurl(ciurl) => Event::Ciurl(ciurl),
+                })
+                .collect(),
+            final_ia_score: self.final_ia_score,
+        }
+    }
+}
+
+/// Replays `record`, which must have started from `setup`, calling `visit` with every move and pass together with the state in which it was made.
+/// ／`setup` から始まった `record` を再現し、全ての手とパスについて、それが指された状態とともに `visit` を呼ぶ。
+///
+/// ```
+/// use cetkaik_full_state_transition::player::{play_game, replay, RandomPlayer};
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let (record, _) = play_game::<CetkaikNaive>(&mut RandomPlayer, &mut RandomPlayer, Config::cerke_online_alpha(), 7);
+/// let mut moves = 0;
+/// replay(&record, &setup::Setup::yhuap_initial(), |_: &state::GroundState_<CetkaikNaive>, _| moves += 1).unwrap();
+/// assert!(moves > 0);
+/// ```
+///
+/// # Errors
+/// Fails if `record` is inconsistent with `setup` or with `record.config`.
+pub fn replay<T: CetkaikRepresentation + Clone>(
+    record: &GameRecord<T::AbsoluteCoord>,
+    setup: &super::setup::Setup<T>,
+    mut visit: impl FnMut(&state::GroundState_<T>, Decision<T::AbsoluteCoord>),
+) -> Result<(), &'static str> {
+    let config = record.config;
+    let mut events = record.events.iter().copied();
+    let mut next_season = Some(super::initial_state_from_setup(setup, config));
+    let mut state = None;
+    while let Some(event) = events.next() {
+        let (player, decision) = match event {
+            Event::SeasonStarts { first_mover, .. } => {
+                state = next_season
+                    .take()
+                    .ok_or("a season started before the previous one ended")?
+                    .into_outcomes()
+                    .into_iter()
+                    .map(|(s, _)| s)
+                    .find(|s: &state::GroundState_<T>| s.whose_turn == first_mover);
+                if state.is_none() {
+                    return Err("the recorded first mover is impossible");
+                }
+                continue;
+            }
+            Event::Decision { player, decision } => (player, decision),
+            Event::Ciurl(_) => return Err("a cast of sticks was recorded where none is needed"),
+        };
+        let current = state
+            .take()
+            .ok_or("a decision was recorded outside a season")?;
+        if player != current.whose_turn {
+            return Err("a decision was recorded out of turn");
+        }
+        visit(&current, decision);
+
+        let resolved = match decision {
+            Decision::Move(PureMove__::NormalMove(msg)) => {
+                let p = super::apply_normal_move(&current, msg, config)?;
+                super::resolve_with_setup(&cast(p, &mut events)?, config, setup)
+            }
+            Decision::Move(PureMove__::InfAfterStep(msg)) => {
+                let excited = cast(
+                    super::apply_inf_after_step(&current, msg, config)?,
+                    &mut events,
+                )?;
+                let Some(Event::Decision {
+                    decision: Decision::AfterHalfAcceptance(msg),
+                    ..
+                }) = events.next()
+                else {
+                    return Err("expected the final destination after a stepping-over cast");
+                };
+                let p = super::apply_after_half_acceptance(&excited, msg, config)?;
+                super::resolve_with_setup(&cast(p, &mut events)?, config, setup)
+            }
+            Decision::Pass => {
+                if current.has_any_legal_move(config) {
+                    super::apply_pass_with_setup(&current, Pass, config, setup)?
+                } else {
+                    super::no_move_possible_at_all_with_setup(&current, config, setup)?
+                }
+            }
+            Decision::AfterHalfAcceptance(_) | Decision::TymokOrTaxot(_) => {
+                return Err("a decision was recorded where none is needed")
+            }
+        };
+
+        let if_taxot = match resolved {
+            state::HandResolved_::NeitherTymokNorTaxot(next) => {
+                state = Some(next);
+                continue;
+            }
+            state::HandResolved_::GameEndsWithoutTymokTaxot(_) => return Ok(()),
+            state::HandResolved_::SeasonEndsWithoutTymokTaxot(if_taxot) => if_taxot,
+            state::HandResolved_::HandExists { if_tymok, if_taxot } => match events.next() {
+                Some(Event::Decision {
+                    decision: Decision::TymokOrTaxot(TymokOrTaxot::Tymok),
+                    ..
+                }) => {
+                    state = Some(if_tymok);
+                    continue;
+                }
+                Some(Event::Decision {
+                    decision: Decision::TymokOrTaxot(TymokOrTaxot::Taxot),
+                    ..
+                }) => if_taxot,
+                _ => return Err("expected tymok or taxot after a hand"),
+            },
+        };
+        match if_taxot {
+            IfTaxot_::VictoriousSide(_) => return Ok(()),
+            IfTaxot_::NextSeason(p) => next_season = Some(p),
+        }
+    }
+    Ok(())
+}
+
+/// Picks the outcome that the next recorded cast of sticks (if one is needed) leads to.
+/// ／（必要ならば）次に記録された投げ棒の結果が導く結果を選ぶ。
+fn cast<S, Coord>(
+    p: super::probabilistic::Probabilistic<S>,
+    events: &mut impl Iterator<Item = Event<Coord>>,
+) -> Result<S, &'static str> {
+    use super::probabilistic::Probabilistic;
+    let mut ciurl = || match events.next() {
+        Some(Event::Ciurl(ciurl)) => Ok(ciurl),
+        _ => Err("expected a cast of sticks"),
+    };
+    match p {
+        Probabilistic::Pure(s) => Ok(s),
+        Probabilistic::Water { failure, success } => {
+            Ok(if ciurl()? >= 3 { success } else { failure })
+        }
+        Probabilistic::Sticks {
+            s0,
+            s1,
+            s2,
+            s3,
+            s4,
+            s5,
+        } => match ciurl()? {
+            0 => Ok(s0),
+            1 => Ok(s1),
+            2 => Ok(s2),
+            3 => Ok(s3),
+            4 => Ok(s4),
+            5 => Ok(s5),
+            _ => Err("a cast of sticks must be between 0 and 5"),
+        },
+        Probabilistic::WhoGoesFirst { .. } => Err("who goes first is recorded by `SeasonStarts`"),
+    }
+}
+
+/// IA side's score after a knockout, i.e. a game that ended because a player's score ran out. A draw leaves `scores` as they are.
+/// ／片方の点が尽きて終わったゲームでの IA 側の得点。引き分けならば `scores` のまま。
+fn score_after_knockout(victor: Victor, scores: super::Scores) -> i32 {
+    match victor.0 {
+        Some(AbsoluteSide::IASide) => 40,
+        Some(AbsoluteSide::ASide) => 0,
+        None => scores.ia(),
+    }
+}
+
+/// Plays a whole game between `ia_player` and `a_player`. Every random choice (the casts of sticks, who goes first, and the randomness given to the players) is drawn from a generator seeded with `seed`, so the same players produce the same game for the same seed.
+/// ／`ia_player` と `a_player` の間で一局を通して指す。全ての無作為な選択（投げ棒・先手の決定・プレイヤーに渡す乱数）は `seed` で初期化した生成器から引くので、同じプレイヤーと同じシードからは同じ対局が得られる。
+///
+/// ```
+/// use cetkaik_full_state_transition::player::{play_game, HandGreedyPlayer, RandomPlayer};
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let config = Config::cerke_online_alpha();
+/// let (record, victor) = play_game::<CetkaikNaive>(&mut RandomPlayer, &mut HandGreedyPlayer, config, 1);
+/// let (record2, victor2) = play_game::<CetkaikNaive>(&mut RandomPlayer, &mut HandGreedyPlayer, config, 1);
+/// assert_eq!(record.events, record2.events);
+/// assert_eq!(victor, victor2);
+/// ```
+pub fn play_game<T: CetkaikRepresentation + Clone>(
+    ia_player: &mut dyn Player<T>,
+    a_player: &mut dyn Player<T>,
+    config: Config,
+    seed: u64,
+) -> (GameRecord<T::AbsoluteCoord>, Victor) {
+    play_game_from_setup(
+        ia_player,
+        a_player,
+        config,
+        &super::setup::Setup::yhuap_initial(),
+        seed,
+    )
+}
+
+/// Same as `play_game`, except that the game starts from `setup`.
+/// ／`play_game` と同様だが、`setup` から始める。
+///
+/// # Panics
+/// Panics if a player returns a decision that is not among the candidates.
+pub fn play_game_from_setup<T: CetkaikRepresentation + Clone>(
+    ia_player: &mut dyn Player<T>,
+    a_player: &mut dyn Player<T>,
+    config: Config,
+    setup: &super::setup::Setup<T>,
+    seed: u64,
+) -> (GameRecord<T::AbsoluteCoord>, Victor) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let state = super::initial_state_from_setup(setup, config)
+        .choose_by_uniform_random_variable(rng.gen())
+        .0;
+    let events = vec![Event::SeasonStarts {
+        season: state.season,
+        first_mover: state.whose_turn,
+    }];
+    play_out(ia_player, a_player, config, setup, state, rng, events, seed)
+}
+
+/// Plays the rest of a game from `state`, which may be in the middle of a season. Later seasons start from `setup`. The record begins at `state`.
+/// ／`state` から対局の残りを指す。`state` は季節の途中でもよい。以降の季節は `setup` から始まる。棋譜は `state` から始まる。
+///
+/// # Panics
+/// Panics if a player returns a decision that is not among the candidates.
+pub fn play_game_from_state<T: CetkaikRepresentation + Clone>(
+    ia_player: &mut dyn Player<T>,
+    a_player: &mut dyn Player<T>,
+    config: Config,
+    setup: &super::setup::Setup<T>,
+    state: state::GroundState_<T>,
+    seed: u64,
+) -> (GameRecord<T::AbsoluteCoord>, Victor) {
+    let rng = StdRng::seed_from_u64(seed);
+    play_out(ia_player, a_player, config, setup, state, rng, vec![], seed)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play_out<T: CetkaikRepresentation + Clone>(
+    ia_player: &mut dyn Player<T>,
+    a_player: &mut dyn Player<T>,
+    config: Config,
+    setup: &super::setup::Setup<T>,
+    mut state: state::GroundState_<T>,
+    mut rng: StdRng,
+    mut events: Vec<Event<T::AbsoluteCoord>>,
+    seed: u64,
+) -> (GameRecord<T::AbsoluteCoord>, Victor) {
+    let (victor, final_ia_score) = loop {
+        let mover = state.whose_turn;
+        let mut hand_not_resolved = None;
+        let player: &mut dyn Player<T> = match mover {
+            AbsoluteSide::IASide => &mut *ia_player,
+            AbsoluteSide::ASide => &mut *a_player,
+        };
+        let decide = |events: &mut Vec<_>, decision| {
+            events.push(Event::Decision {
+                player: mover,
+                decision,
+            });
+        };
+
+        let (hop1zuo1_candidates, candidates) = state.get_candidates(config);
+        let candidates: Vec<_> = hop1zuo1_candidates.into_iter().chain(candidates).collect();
+        let stuck = candidates.is_empty();
+        let resolved = if stuck {
+            if config.player_without_any_legal_move_passes {
+                decide(&mut events, Decision::Pass);
+            }
+            super::no_move_possible_at_all_with_setup(&state, config, setup)
+                .expect("no move is possible")
+        } else if config.voluntary_pass_is_allowed && player.wants_to_pass(&state, config, &mut rng)
+        {
+            decide(&mut events, Decision::Pass);
+            super::apply_pass_with_setup(&state, Pass, config, setup)
+                .expect("a voluntary pass is allowed")
+        } else {
+            let mv = player.play_move(&state, config, &candidates, &mut rng);
+            assert!(
+                candidates.contains(&mv),
+                "`play_move` returned an invalid move"
+            );
+            decide(&mut events, Decision::Move(mv));
+            let p = match mv {
+                PureMove__::NormalMove(msg) => super::apply_normal_move(&state, msg, config)
+                    .expect("a candidate move is valid"),
+                PureMove__::InfAfterStep(msg) => {
+                    let (excited, ciurl) = super::apply_inf_after_step(&state, msg, config)
+                        .expect("a candidate move is valid")
+                        .choose_by_uniform_random_variable(rng.gen());
+                    events.extend(ciurl.map(Event::Ciurl));
+                    let candidates = excited.get_candidates(config);
+                    let msg = player.after_half_acceptance(&excited, config, &candidates, &mut rng);
+                    assert!(
+                        candidates.contains(&msg),
+                        "`after_half_acceptance` returned an invalid destination"
+                    );
+                    decide(&mut events, Decision::AfterHalfAcceptance(msg));
+                    super::apply_after_half_acceptance(&excited, msg, config)
+                        .expect("a candidate destination is valid")
+                }
+            };
+            let (next, ciurl) = p.choose_by_uniform_random_variable(rng.gen());
+            events.extend(ciurl.map(Event::Ciurl));
+            super::resolve_with_setup(hand_not_resolved.insert(next), config, setup)
+        };
+
+        let if_taxot = match resolved {
+            state::HandResolved_::NeitherTymokNorTaxot(next) => {
+                state = next;
+                continue;
+            }
+            state::HandResolved_::GameEndsWithoutTymokTaxot(victor) => {
+                if stuck && !config.player_without_any_legal_move_passes {
+                    break (victor, state.scores.ia());
+                }
+                break (victor, score_after_knockout(victor, state.scores));
+            }
+            state::HandResolved_::SeasonEndsWithoutTymokTaxot(if_taxot) => if_taxot,
+            state::HandResolved_::HandExists { if_tymok, if_taxot } => {
+                let decision = player.tymok_or_taxot(&if_tymok, &if_taxot, config, &mut rng);
+                decide(&mut events, Decision::TymokOrTaxot(decision));
+                if decision == TymokOrTaxot::Tymok {
+                    state = if_tymok;
+                    continue;
+                }
+                if_taxot
+            }
+        };
+        match if_taxot {
+            IfTaxot_::VictoriousSide(victor) => {
+                let final_ia_score = match hand_not_resolved.and_then(|hand_not_resolved| {
+                    super::scores_if_taxot(&hand_not_resolved, config)
+                }) {
+                    Some(Ok(new_scores)) => new_scores.ia(),
+                    Some(Err(victor)) => score_after_knockout(victor, state.scores),
+                    None => state.scores.ia(),
+                };
+                break (victor, final_ia_score);
+            }
+            IfTaxot_::NextSeason(p) => {
+                state = p.choose_by_uniform_random_variable(rng.gen()).0;
+                events.push(Event::SeasonStarts {
+                    season: state.season,
+                    first_mover: state.whose_turn,
+                });
+            }
+        }
+    };
+
+    (
+        GameRecord {
+            seed,
+            config,
+            events,
+            final_ia_score,
+        },
+        victor,
+    )
+}
+
+/// Chooses uniformly at random at every decision point.
+/// ／全ての決断を一様に無作為に行う。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RandomPlayer;
+
+impl<T: CetkaikRepresentation> Player<T> for RandomPlayer {
+    fn play_move(
+        &mut self,
+        _state: &state::GroundState_<T>,
+        _config: Config,
+        candidates: &[PureMove__<T::AbsoluteCoord>],
+        rng: &mut StdRng,
+    ) -> PureMove__<T::AbsoluteCoord> {
+        *candidates.choose(rng).expect("`candidates` is never empty")
+    }
+
+    fn after_half_acceptance(
+        &mut self,
+        _state: &state::ExcitedState_<T>,
+        _config: Config,
+        candidates: &[AfterHalfAcceptance_<T::AbsoluteCoord>],
+        rng: &mut StdRng,
+    ) -> AfterHalfAcceptance_<T::AbsoluteCoord> {
+        *candidates
+            .choose(rng)
+            .expect("giving up the move is always a candidate")
+    }
+
+    fn tymok_or_taxot(
+        &mut self,
+        if_tymok: &state::GroundState_<T>,
+        if_taxot: &IfTaxot_<T>,
+        _config: Config,
+        rng: &mut StdRng,
+    ) -> TymokOrTaxot {
+        TymokTaxotPolicy::Random.decide(!if_tymok.whose_turn, if_taxot, rng)
+    }
+}
+
+/// Captures an opponent's piece whenever possible, and otherwise moves at random. Takes taxot when it leaves the player ahead.
+/// ／相手の駒を取れるときは必ず取り、そうでなければ無作為に指す。終季で点が上回るなら終季を選ぶ。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash, Default)]
+pub struct GreedyCapturePlayer;
+
+impl<T: CetkaikRepresentation> Player<T> for GreedyCapturePlayer {
+    fn play_move(
+        &mut self,
+        state: &state::GroundState_<T>,
+        _config: Config,
+        candidates: &[PureMove__<T::AbsoluteCoord>],
+        rng: &mut StdRng,
+    ) -> PureMove__<T::AbsoluteCoord> {
+        let captures: Vec<_> = candidates
+            .iter()
+            .copied()
+            .filter(|mv| super::captured_by(state, *mv).is_some())
+            .collect();
+        *captures
+            .choose(rng)
+            .or_else(|| candidates.choose(rng))
+            .expect("`candidates` is never empty")
+    }
+
+    fn after_half_acceptance(
+        &mut self,
+        state: &state::ExcitedState_<T>,
+        config: Config,
+        _candidates: &[AfterHalfAcceptance_<T::AbsoluteCoord>],
+        rng: &mut StdRng,
+    ) -> AfterHalfAcceptance_<T::AbsoluteCoord> {
+        HalfAcceptancePolicy::PreferCapture.choose(state, config, rng)
+    }
+
+    fn tymok_or_taxot(
+        &mut self,
+        if_tymok: &state::GroundState_<T>,
+        if_taxot: &IfTaxot_<T>,
+        _config: Config,
+        rng: &mut StdRng,
+    ) -> TymokOrTaxot {
+        TymokTaxotPolicy::TaxotIfAhead.decide(!if_tymok.whose_turn, if_taxot, rng)
+    }
+}
+
+/// Makes the capture that adds the most points to the hands in its hop1zuo1, preferring any capture to no capture. Takes taxot when it leaves the player ahead.
+/// ／手駒の役の点が最も増える駒取りを行う。点が増えなくても、駒を取らないよりは取るほうを好む。終季で点が上回るなら終季を選ぶ。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash, Default)]
+pub struct HandGreedyPlayer;
+
+impl<T: CetkaikRepresentation> Player<T> for HandGreedyPlayer {
+    fn play_move(
+        &mut self,
+        state: &state::GroundState_<T>,
+        _config: Config,
+        candidates: &[PureMove__<T::AbsoluteCoord>],
+        rng: &mut StdRng,
+    ) -> PureMove__<T::AbsoluteCoord> {
+        let gains: Vec<_> = candidates
+            .iter()
+            .filter_map(|mv| {
+                super::captured_by(state, *mv).map(|piece| {
+                    (
+                        *mv,
+                        super::hand_gain::<T>(&state.f, state.whose_turn, piece),
+                    )
+                })
+            })
+            .collect();
+        let best_gain = gains.iter().map(|(_, gain)| *gain).max();
+        let best: Vec<_> = gains
+            .iter()
+            .filter(|(_, gain)| Some(*gain) == best_gain)
+            .map(|(mv, _)| *mv)
+            .collect();
+        *best
+            .choose(rng)
+            .or_else(|| candidates.choose(rng))
+            .expect("`candidates` is never empty")
+    }
+
+    fn after_half_acceptance(
+        &mut self,
+        state: &state::ExcitedState_<T>,
+        _config: Config,
+        candidates: &[AfterHalfAcceptance_<T::AbsoluteCoord>],
+        rng: &mut StdRng,
+    ) -> AfterHalfAcceptance_<T::AbsoluteCoord> {
+        let gain_of = |cand: &AfterHalfAcceptance_<T::AbsoluteCoord>| {
+            cand.dest
+                .and_then(|dest| super::captured_at::<T>(&state.c.f, state.c.whose_turn, dest))
+                .map(|piece| super::hand_gain::<T>(&state.c.f, state.c.whose_turn, piece))
+        };
+        let best_gain = candidates.iter().filter_map(gain_of).max();
+        let best: Vec<_> = candidates
+            .iter()
+            .copied()
+            .filter(|cand| best_gain.is_some() && gain_of(cand) == best_gain)
+            .collect();
+        let destinations: Vec<_> = candidates
+            .iter()
+            .copied()
+            .filter(|cand| cand.dest.is_some())
+            .collect();
+        *best
+            .choose(rng)
+            .or_else(|| destinations.choose(rng))
+            .or_else(|| candidates.choose(rng))
+            .expect("`candidates` always contains giving up the move")
+    }
+
+    fn tymok_or_taxot(
+        &mut self,
+        if_tymok: &state::GroundState_<T>,
+        if_taxot: &IfTaxot_<T>,
+        _config: Config,
+        rng: &mut StdRng,
+    ) -> TymokOrTaxot {
+        TymokTaxotPolicy::TaxotIfAhead.decide(!if_tymok.whose_turn, if_taxot, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{position_with_a_winning_hand, WINNING_CAPTURE};
+
+    #[test]
+    fn test_greedy_players_capture() {
+        let state = position_with_a_winning_hand();
+        let config = Config::cerke_online_alpha();
+        let (hop1zuo1_candidates, candidates) = state.get_candidates(config);
+        let candidates: Vec<_> = hop1zuo1_candidates.into_iter().chain(candidates).collect();
+        let mut rng = StdRng::seed_from_u64(0);
+        for player in [
+            &mut GreedyCapturePlayer as &mut dyn Player<_>,
+            &mut HandGreedyPlayer,
+        ] {
+            assert_eq!(
+                Decision::Move(player.play_move(&state, config, &candidates, &mut rng)),
+                WINNING_CAPTURE
+            );
+        }
+    }
+}
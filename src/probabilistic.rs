@@ -54,6 +54,36 @@ impl<T: Clone> Probabilistic<T> {
         prob.choose_by_uniform_random_variable(rand)
     }
 
+    /// Lists the possible outcomes along with their probabilities, without distinguishing the ciurls that lead to the same outcome. Useful when searching the game tree.
+    /// ／起こりうる結果をその確率とともに列挙する。同じ結果に至る投げ棒の出目は区別しない。ゲーム木の探索に便利。
+    #[must_use]
+    pub fn into_outcomes(self) -> Vec<(T, f64)> {
+        match self {
+            Probabilistic::Pure(t) => vec![(t, 1.0)],
+            Probabilistic::Water { failure, success } => {
+                vec![(failure, 16.0 / 32.0), (success, 16.0 / 32.0)]
+            }
+            Probabilistic::Sticks {
+                s0,
+                s1,
+                s2,
+                s3,
+                s4,
+                s5,
+            } => vec![
+                (s0, 1.0 / 32.0),
+                (s1, 5.0 / 32.0),
+                (s2, 10.0 / 32.0),
+                (s3, 10.0 / 32.0),
+                (s4, 5.0 / 32.0),
+                (s5, 1.0 / 32.0),
+            ],
+            Probabilistic::WhoGoesFirst { ia_first, a_first } => {
+                vec![(ia_first, 1.0 / 2.0), (a_first, 1.0 / 2.0)]
+            }
+        }
+    }
+
     /// # Panics
     /// Panics when called while ciurl exists.
     #[must_use]
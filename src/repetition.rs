@@ -0,0 +1,268 @@
+use cetkaik_fundamental::AbsoluteSide;
+use cetkaik_traits::CetkaikRepresentation;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Computes a key that identifies a position for the purpose of repetition detection. Two positions get the same key if they agree on the board, on both hop1zuo1 (as multisets), on the side to move and on `tam_has_moved_previously`.
+/// ／千日手の検出のために局面を識別する鍵を計算する。盤面・両者の手駒（多重集合として）・手番・`tam_has_moved_previously` が一致する局面には同じ鍵が与えられる。
+///
+/// # Panics
+/// Panics if a hop1zuo1 contains more than 16 pieces of the same kind.
+#[must_use]
+pub fn position_key<T: CetkaikRepresentation>(
+    f: &T::AbsoluteField,
+    whose_turn: AbsoluteSide,
+    tam_has_moved_previously: bool,
+) -> u64 {
+    super::zobrist::hash_of_field::<T>(f)
+        ^ super::zobrist::side_to_move(whose_turn)
+        ^ super::zobrist::tam_has_moved_previously(tam_has_moved_previously)
+}
+
+/// Same as `position_key`, but obtained from `state.zobrist_hash` without looking at the board.
+/// ／`position_key` と同じだが、盤面を見ずに `state.zobrist_hash` から得る。
+#[must_use]
+pub const fn position_key_of_state<T: CetkaikRepresentation>(
+    state: &super::state::GroundState_<T>,
+) -> u64 {
+    state.zobrist_hash
+        ^ super::zobrist::season(state.season)
+        ^ super::zobrist::scores(state.scores)
+        ^ super::zobrist::rate(state.rate)
+}
+
+/// The keys of the positions that have appeared in a season, kept as a persistent list: recording a position shares the keys recorded so far instead of copying them, so that cloning a state takes O(1) however long the season has lasted.
+/// How many times each key has been recorded is kept beside it in a persistent hash trie, so that `count` does not go through the list.
+/// Serialized as the sequence of the keys, oldest first.
+/// ／季節内に現れた局面の鍵を永続リストとして保持したもの。局面を記録するときはそれまでの鍵を複製せずに共有するので、季節がどれだけ長引いても状態の複製は O(1) で済む。
+/// 各鍵が記録された回数はその傍らに永続ハッシュトライとして保持するので、`count` はリストをたどらない。
+/// 鍵の列として、古いものから順にシリアライズされる。
+///
+/// ```
+/// use cetkaik_full_state_transition::repetition::PositionHistory;
+///
+/// let mut history: PositionHistory = [1, 2].into_iter().collect();
+/// let before = history.clone();
+/// history.push(1);
+/// assert_eq!(history.count(1), 2);
+/// assert_eq!(history.last(), Some(1));
+/// assert_eq!(before.count(1), 1);
+/// assert_eq!(history.pop(), Some(1));
+/// assert_eq!(history.count(1), 1);
+/// assert_eq!(history.count(3), 0);
+/// assert_eq!(history, before);
+/// assert_eq!(history.digest(), [2, 1].into_iter().collect::<PositionHistory>().digest());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PositionHistory {
+    last: Option<Arc<Node>>,
+    len: usize,
+    digest: u64,
+    counts: Option<Arc<Counts>>,
+}
+
+#[derive(Debug)]
+struct Node {
+    key: u64,
+    earlier: Option<Arc<Node>>,
+}
+
+/// A hash trie from keys to how many times they have been recorded, branching on four bits of the key at each level. Updating it copies only the path to the key.
+/// ／鍵から記録された回数への、各段で鍵の4ビットによって分岐するハッシュトライ。更新するときは鍵への経路のみを複製する。
+#[derive(Debug)]
+enum Counts {
+    Leaf { key: u64, count: u32 },
+    Branch([Option<Arc<Counts>>; 16]),
+}
+
+impl Counts {
+    const fn nibble(key: u64, depth: u32) -> usize {
+        ((key >> (4 * depth)) & 0xf) as usize
+    }
+
+    fn get(mut node: Option<&Arc<Self>>, key: u64) -> u32 {
+        let mut depth = 0;
+        while let Some(n) = node {
+            match &**n {
+                Self::Leaf { key: k, count } => return if *k == key { *count } else { 0 },
+                Self::Branch(children) => node = children[Self::nibble(key, depth)].as_ref(),
+            }
+            depth += 1;
+        }
+        0
+    }
+
+    /// Adds one to the count of `key`.
+    /// ／`key` の回数に一を足す。
+    fn increment(node: Option<&Arc<Self>>, key: u64, depth: u32) -> Arc<Self> {
+        Arc::new(match node.map(|n| &**n) {
+            None => Self::Leaf { key, count: 1 },
+            Some(Self::Leaf { key: k, count }) if *k == key => Self::Leaf {
+                key,
+                count: count + 1,
+            },
+            Some(Self::Leaf { key: k, .. }) => {
+                let mut children: [Option<Arc<Self>>; 16] = Default::default();
+                children[Self::nibble(*k, depth)] = node.cloned();
+                let i = Self::nibble(key, depth);
+                children[i] = Some(Self::increment(children[i].as_ref(), key, depth + 1));
+                Self::Branch(children)
+            }
+            Some(Self::Branch(children)) => {
+                let mut children = children.clone();
+                let i = Self::nibble(key, depth);
+                children[i] = Some(Self::increment(children[i].as_ref(), key, depth + 1));
+                Self::Branch(children)
+            }
+        })
+    }
+
+    /// Subtracts one from the count of `key`, which must have been recorded.
+    /// ／`key` の回数から一を引く。`key` は記録されていなければならない。
+    fn decrement(node: &Arc<Self>, key: u64, depth: u32) -> Option<Arc<Self>> {
+        match &**node {
+            Self::Leaf { key: k, count } => {
+                debug_assert_eq!(*k, key);
+                (*count > 1).then(|| {
+                    Arc::new(Self::Leaf {
+                        key,
+                        count: count - 1,
+                    })
+                })
+            }
+            Self::Branch(children) => {
+                let mut children = children.clone();
+                let i = Self::nibble(key, depth);
+                children[i] = children[i]
+                    .as_ref()
+                    .and_then(|child| Self::decrement(child, key, depth + 1));
+                children
+                    .iter()
+                    .any(Option::is_some)
+                    .then(|| Arc::new(Self::Branch(children)))
+            }
+        }
+    }
+}
+
+impl PositionHistory {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            len: 0,
+            digest: 0,
+            counts: None,
+        }
+    }
+
+    pub fn push(&mut self, key: u64) {
+        self.last = Some(Arc::new(Node {
+            key,
+            earlier: self.last.take(),
+        }));
+        self.len += 1;
+        self.digest = self.digest.wrapping_add(super::zobrist::mix(key));
+        self.counts = Some(Counts::increment(self.counts.as_ref(), key, 0));
+    }
+
+    /// Forgets the key recorded last and returns it.
+    /// ／最後に記録された鍵を忘れ、それを返す。
+    pub fn pop(&mut self) -> Option<u64> {
+        let last = self.last.take()?;
+        self.last.clone_from(&last.earlier);
+        self.len -= 1;
+        self.digest = self.digest.wrapping_sub(super::zobrist::mix(last.key));
+        self.counts = self
+            .counts
+            .as_ref()
+            .and_then(|counts| Counts::decrement(counts, last.key, 0));
+        Some(last.key)
+    }
+
+    #[must_use]
+    pub fn last(&self) -> Option<u64> {
+        self.last.as_ref().map(|node| node.key)
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The keys, newest first.
+    /// ／鍵を新しいものから順に。
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        std::iter::successors(self.last.as_deref(), |node| node.earlier.as_deref())
+            .map(|node| node.key)
+    }
+
+    /// A hash of the keys as a multiset: two histories that record the same keys the same number of times have the same digest, whatever the order. Kept up to date by `push` and `pop`.
+    /// ／鍵の多重集合としてのハッシュ値。同じ鍵を同じ回数記録した二つの履歴は、順番によらず同じ値を持つ。`push` と `pop` が更新し続ける。
+    #[must_use]
+    pub const fn digest(&self) -> u64 {
+        self.digest
+    }
+
+    /// How many times `key` has been recorded. Takes time proportional to the depth of the trie, which is about the logarithm of the number of distinct keys, not to `len()`.
+    /// ／`key` が記録された回数。`len()` ではなく、異なる鍵の数のおおよそ対数であるトライの深さに比例する時間がかかる。
+    #[must_use]
+    pub fn count(&self, key: u64) -> usize {
+        Counts::get(self.counts.as_ref(), key) as usize
+    }
+}
+
+/// Oldest first.
+/// ／古いものから順に。
+impl FromIterator<u64> for PositionHistory {
+    fn from_iter<I: IntoIterator<Item = u64>>(keys: I) -> Self {
+        let mut history = Self::new();
+        for key in keys {
+            history.push(key);
+        }
+        history
+    }
+}
+
+impl PartialEq for PositionHistory {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for PositionHistory {}
+
+/// Drops the nodes one by one, since the default recursive drop could overflow the stack on a long season.
+/// ／既定の再帰的な解放は長い季節でスタックを溢れさせうるので、ノードを一つずつ解放する。
+impl Drop for PositionHistory {
+    fn drop(&mut self) {
+        let mut next = self.last.take();
+        while let Some(node) = next {
+            match Arc::try_unwrap(node) {
+                Ok(mut node) => next = node.earlier.take(),
+                // the rest is still shared with another history
+                // 残りは別の履歴とまだ共有されている
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl Serialize for PositionHistory {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut keys: Vec<u64> = self.iter().collect();
+        keys.reverse();
+        keys.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PositionHistory {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::<u64>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
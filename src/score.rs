@@ -25,6 +25,16 @@ impl Scores {
         Self { ia: 20, a: 20 }
     }
 
+    /// Scores where the IA-side player holds `ia` points. Fails unless `0 < ia < 40`, since otherwise the game is already over.
+    /// ／IA側が `ia` 点を持っている得点。`0 < ia < 40` でなければゲームは既に終わっているので失敗する。
+    pub const fn from_ia_score(ia: i32) -> Result<Self, &'static str> {
+        if 0 < ia && ia < 40 {
+            Ok(Self { ia, a: 40 - ia })
+        } else {
+            Err("the score must be between 1 and 39; otherwise the game is already over")
+        }
+    }
+
     #[must_use]
     pub const fn ia(self) -> i32 {
         self.ia
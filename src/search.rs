@@ -0,0 +1,604 @@
+use super::message::{AfterHalfAcceptance_, NormalMove_, PureMove__, TymokOrTaxot};
+use super::transposition::{Bound, Entry, TranspositionTable};
+use super::{setup, state, Config, IfTaxot_, Victor};
+use cetkaik_fundamental::AbsoluteSide;
+use cetkaik_traits::CetkaikRepresentation;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Every value handled by the search lies within `LOWER..=UPPER`. `UPPER` means that IA side certainly wins the game, and `LOWER` means that A side certainly does.
+/// ／探索で扱う値は全て `LOWER..=UPPER` に収まる。`UPPER` は IA 側の確実な勝利を、`LOWER` は A 側の確実な勝利を意味する。
+pub const LOWER: f64 = -1.0;
+pub const UPPER: f64 = 1.0;
+
+/// Describes a decision made by a player. A principal variation is a sequence of these.
+/// ／プレイヤーが下す決断。読み筋はこれを並べたものである。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum Decision<Coord> {
+    Move(PureMove__<Coord>),
+    Pass,
+    AfterHalfAcceptance(AfterHalfAcceptance_<Coord>),
+    TymokOrTaxot(TymokOrTaxot),
+}
+
+impl<Coord: std::fmt::Display> std::fmt::Display for Decision<Coord> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Decision::Move(mv) => write!(f, "{mv}"),
+            Decision::Pass => write!(f, "pass"),
+            Decision::AfterHalfAcceptance(AfterHalfAcceptance_ { dest: Some(dest) }) => {
+                write!(f, "{dest}")
+            }
+            Decision::AfterHalfAcceptance(AfterHalfAcceptance_ { dest: None }) => {
+                write!(f, "give up")
+            }
+            Decision::TymokOrTaxot(TymokOrTaxot::Tymok) => write!(f, "tymok"),
+            Decision::TymokOrTaxot(TymokOrTaxot::Taxot) => write!(f, "taxot"),
+        }
+    }
+}
+
+impl<T> Decision<T> {
+    /// Applies `f` to every coordinate in the decision. See `PureMove__::map`.
+    /// ／決断に含まれる全ての座標に `f` を適用する。`PureMove__::map` を参照。
+    #[must_use]
+    pub fn map<U>(self, f: impl Fn(T) -> U) -> Decision<U> {
+        match self {
+            Self::Move(mv) => Decision::Move(mv.map(f)),
+            Self::Pass => Decision::Pass,
+            Self::AfterHalfAcceptance(AfterHalfAcceptance_ { dest }) => {
+                Decision::AfterHalfAcceptance(AfterHalfAcceptance_ { dest: dest.map(f) })
+            }
+            Self::TymokOrTaxot(choice) => Decision::TymokOrTaxot(choice),
+        }
+    }
+}
+
+/// Evaluates a position from IA side's point of view. The value must lie within `LOWER..=UPPER`; a value outside is clamped.
+/// ／局面を IA 側から見て評価する。値は `LOWER..=UPPER` に収まらなければならず、はみ出た値は切り詰められる。
+pub trait Evaluator<T: CetkaikRepresentation> {
+    fn evaluate(&self, state: &state::GroundState_<T>) -> f64;
+}
+
+impl<T: CetkaikRepresentation, F: Fn(&state::GroundState_<T>) -> f64> Evaluator<T> for F {
+    fn evaluate(&self, state: &state::GroundState_<T>) -> f64 {
+        self(state)
+    }
+}
+
+/// The simplest evaluation: only looks at the scores.
+/// ／最も単純な評価関数。得点しか見ない。
+#[must_use]
+pub fn score_difference<T: CetkaikRepresentation>(state: &state::GroundState_<T>) -> f64 {
+    f64::from(state.scores.ia() - state.scores.a()) / 40.0
+}
+
+#[must_use]
+pub const fn value_of_victor(victor: Victor) -> f64 {
+    match victor.0 {
+        Some(AbsoluteSide::IASide) => UPPER,
+        Some(AbsoluteSide::ASide) => LOWER,
+        None => 0.0,
+    }
+}
+
+/// The result of a search. `expected_value` is seen from the player to move in the searched state, so that `UPPER` means a certain win for that player.
+/// ／探索の結果。`expected_value` は探索した局面で手番を持つプレイヤーから見た値であり、`UPPER` はそのプレイヤーの確実な勝利を意味する。
+#[derive(Clone, Debug)]
+pub struct SearchResult<Coord> {
+    /// `None` if the player has nothing to decide, i.e. the game ends because the player is stuck.
+    /// ／プレイヤーが決めることが何もない、すなわち手詰まりでゲームが終わるなら `None`。
+    pub best_move: Option<Decision<Coord>>,
+    pub expected_value: f64,
+
+    /// Starts with `best_move`. At each chance node, the most probable outcome is followed.
+    /// ／`best_move` から始まる。偶然手番では最も確率の高い結果をたどる。
+    pub principal_variation: Vec<Decision<Coord>>,
+
+    /// The depth of the deepest iteration that was completed.
+    /// ／完了した最も深い反復の深さ。
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+/// Expectiminimax search with *-minimax (Star1) pruning at the chance nodes and iterative deepening.
+/// The depth is counted in turns; the stepping-over cast, the water entry cast and the choice between tymok and taxot all belong to the turn in which they occur.
+/// ／偶然手番で *-minimax (Star1) の枝刈りを行う、反復深化つきの期待値ミニマックス探索。
+/// 深さは手番の数で数える。踏越え判定・入水判定・再行か終季かの選択は、それが起こった手番に属する。
+///
+/// ```
+/// use cetkaik_full_state_transition::search::{score_difference, Searcher};
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let state = initial_state::<CetkaikNaive>().choose().0;
+/// let result = Searcher::new(Config::cerke_online_alpha(), score_difference::<CetkaikNaive>)
+///     .with_max_depth(1)
+///     .search(&state);
+/// assert!(result.best_move.is_some());
+/// assert_eq!(result.principal_variation[0], result.best_move.unwrap());
+/// ```
+pub struct Searcher<T: CetkaikRepresentation, E> {
+    config: Config,
+    evaluator: E,
+    setup: setup::Setup<T>,
+    max_depth: u32,
+    time_limit: Option<Duration>,
+    table: Option<Arc<TranspositionTable<T::AbsoluteCoord>>>,
+}
+
+impl<T: CetkaikRepresentation + Clone, E: Evaluator<T>> Searcher<T, E> {
+    /// Searches to depth 3 without any time limit by default.
+    /// ／既定では時間制限なしで深さ3まで探索する。
+    pub fn new(config: Config, evaluator: E) -> Self {
+        Self {
+            config,
+            evaluator,
+            setup: setup::Setup::yhuap_initial(),
+            max_depth: 3,
+            time_limit: None,
+            table: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_max_depth(self, max_depth: u32) -> Self {
+        Self { max_depth, ..self }
+    }
+
+    /// The search stops deepening once the time is up. The first iteration is always completed, however long it takes.
+    /// ／時間切れになると深化をやめる。ただし最初の反復はどれだけ時間がかかっても完了させる。
+    #[must_use]
+    pub fn with_time_limit(self, time_limit: Duration) -> Self {
+        Self {
+            time_limit: Some(time_limit),
+            ..self
+        }
+    }
+
+    /// The position that the following seasons start from.
+    /// ／以降の季節の開始局面。
+    #[must_use]
+    pub fn with_setup(self, setup: setup::Setup<T>) -> Self {
+        Self { setup, ..self }
+    }
+
+    /// Caches the results of the `GroundState_` nodes in `table`, which may be shared with other searchers (with the same `Config`, setup and evaluator) running on other threads. At the root, the table is used only to order the moves.
+    /// ／`GroundState_` のノードの結果を `table` に保存する。`table` は（同じ `Config`・開始局面・評価関数を持つ）他のスレッドで走る探索器と共有してもよい。ルートでは手の並べ替えにのみ用いる。
+    #[must_use]
+    pub fn with_transposition_table(
+        self,
+        table: Arc<TranspositionTable<T::AbsoluteCoord>>,
+    ) -> Self {
+        Self {
+            table: Some(table),
+            ..self
+        }
+    }
+
+    pub fn search(&self, state: &state::GroundState_<T>) -> SearchResult<T::AbsoluteCoord> {
+        let deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        let mut run = Run {
+            searcher: self,
+            deadline: None, /* the first iteration is never aborted */
+            nodes: 0,
+            aborted: false,
+            root_first: None,
+            at_root: true,
+        };
+        let mut result = SearchResult {
+            best_move: None,
+            expected_value: 0.0,
+            principal_variation: vec![],
+            depth: 0,
+            nodes: 0,
+        };
+        for depth in 1..=self.max_depth.max(1) {
+            let (value, pv) = run.ground(state, depth, LOWER, UPPER);
+            if run.aborted && depth > 1 {
+                break;
+            }
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                expected_value: match state.whose_turn {
+                    AbsoluteSide::IASide => value,
+                    AbsoluteSide::ASide => -value,
+                },
+                principal_variation: pv,
+                depth,
+                nodes: run.nodes,
+            };
+            run.root_first = result.best_move;
+            run.at_root = true;
+            run.deadline = deadline;
+            run.deadline_passed();
+        }
+        result.nodes = run.nodes;
+        result
+    }
+}
+
+type Line<Coord> = (f64, Vec<Decision<Coord>>);
+
+struct Run<'a, T: CetkaikRepresentation, E> {
+    searcher: &'a Searcher<T, E>,
+    deadline: Option<Instant>,
+    nodes: u64,
+
+    /// Once set, every node returns immediately and the current iteration is discarded.
+    /// ／一度立つと、全てのノードが即座に返り、現在の反復は捨てられる。
+    aborted: bool,
+
+    /// The best move of the previous iteration, tried first at the root.
+    /// ／前の反復での最善手。ルートで最初に試される。
+    root_first: Option<Decision<T::AbsoluteCoord>>,
+
+    /// Set until the root node is entered, so that the root never returns early from the transposition table.
+    /// ／ルートのノードに入るまで立っている。ルートが置換表によって早期に返ることのないようにするため。
+    at_root: bool,
+}
+
+impl<T: CetkaikRepresentation + Clone, E: Evaluator<T>> Run<'_, T, E> {
+    fn deadline_passed(&mut self) -> bool {
+        if !self.aborted
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.aborted = true;
+        }
+        self.aborted
+    }
+
+    fn ground(
+        &mut self,
+        state: &state::GroundState_<T>,
+        depth: u32,
+        alpha: f64,
+        beta: f64,
+    ) -> Line<T::AbsoluteCoord> {
+        self.nodes += 1;
+        if self.aborted || (self.nodes % 1024 == 0 && self.deadline_passed()) {
+            return (0.0, vec![]);
+        }
+        if depth == 0 {
+            return (
+                self.searcher.evaluator.evaluate(state).clamp(LOWER, UPPER),
+                vec![],
+            );
+        }
+
+        let at_root = std::mem::take(&mut self.at_root);
+        let table = self.searcher.table.as_deref();
+        let key = super::transposition::key_of(state, self.searcher.config);
+        let entry = table.and_then(|table| table.probe(key));
+        if let Some(entry) = entry.filter(|entry| !at_root && entry.depth >= depth) {
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.expected_value >= beta,
+                Bound::Upper => entry.expected_value <= alpha,
+            };
+            if usable {
+                return (
+                    entry.expected_value,
+                    entry.best_move.map(Decision::Move).into_iter().collect(),
+                );
+            }
+        }
+
+        let config = self.searcher.config;
+        let (hop1zuo1_candidates, candidates) = state.get_candidates(config);
+        if hop1zuo1_candidates.is_empty() && candidates.is_empty() {
+            return super::no_move_possible_at_all_with_setup(state, config, &self.searcher.setup)
+                .map_or((0.0, vec![]), |resolved| {
+                    self.hand_resolved(resolved, depth, alpha, beta)
+                });
+        }
+
+        let mut decisions: Vec<Decision<T::AbsoluteCoord>> = vec![];
+        for cand in hop1zuo1_candidates.into_iter().chain(candidates) {
+            let decision = Decision::Move(cand);
+            if !decisions.contains(&decision) {
+                decisions.push(decision);
+            }
+        }
+        if config.voluntary_pass_is_allowed {
+            decisions.push(Decision::Pass);
+        }
+        let first = self
+            .root_first
+            .take()
+            .or_else(|| entry.and_then(|entry| entry.best_move).map(Decision::Move));
+        if let Some(first) = first {
+            if let Some(index) = decisions.iter().position(|d| *d == first) {
+                decisions[..=index].rotate_right(1);
+            }
+        }
+
+        let (value, pv) = self.decide(
+            state.whose_turn,
+            decisions,
+            alpha,
+            beta,
+            // Every move comes from `get_candidates` above, so it need not be checked again
+            // 手は全て上の `get_candidates` から来たものなので、再検査する必要はない
+            |run, decision, alpha, beta| match decision {
+                Decision::Move(PureMove__::NormalMove(msg)) => {
+                    run.after_normal_move(state, msg, depth, alpha, beta)
+                }
+                Decision::Move(PureMove__::InfAfterStep(msg)) => Some(run.chance(
+                    super::apply_inf_after_step_unchecked(state, msg).into_outcomes(),
+                    alpha,
+                    beta,
+                    |run, excited, alpha, beta| run.excited(&excited, depth, alpha, beta),
+                )),
+                Decision::Pass => super::apply_pass_with_setup(
+                    state,
+                    super::message::Pass,
+                    config,
+                    &run.searcher.setup,
+                )
+                .ok()
+                .map(|resolved| run.hand_resolved(resolved, depth, alpha, beta)),
+                Decision::AfterHalfAcceptance(_) | Decision::TymokOrTaxot(_) => None,
+            },
+        );
+        if let Some(table) = table.filter(|_| !self.aborted) {
+            table.store(Entry {
+                key,
+                depth,
+                // A value at `LOWER` or `UPPER` cannot be improved on, so it is exact even if it falls outside the window
+                // `LOWER` や `UPPER` の値はそれ以上動きようがないので、窓の外に出ても正確である
+                bound: if value <= alpha && value > LOWER {
+                    Bound::Upper
+                } else if value >= beta && value < UPPER {
+                    Bound::Lower
+                } else {
+                    Bound::Exact
+                },
+                expected_value: value,
+                best_move: match pv.first() {
+                    Some(Decision::Move(mv)) => Some(*mv),
+                    _ => None,
+                },
+                generation: 0,
+            });
+        }
+        (value, pv)
+    }
+
+    /// `msg` must be one of `state.get_candidates`.
+    /// ／`msg` は `state.get_candidates` の一つでなければならない。
+    fn after_normal_move(
+        &mut self,
+        state: &state::GroundState_<T>,
+        msg: NormalMove_<T::AbsoluteCoord>,
+        depth: u32,
+        alpha: f64,
+        beta: f64,
+    ) -> Option<Line<T::AbsoluteCoord>> {
+        let p = super::apply_normal_move_unchecked(state, msg, self.searcher.config).ok()?;
+        Some(self.chance(
+            p.into_outcomes(),
+            alpha,
+            beta,
+            |run, hand_not_resolved, alpha, beta| {
+                let resolved = super::resolve_with_setup(
+                    &hand_not_resolved,
+                    run.searcher.config,
+                    &run.searcher.setup,
+                );
+                run.hand_resolved(resolved, depth, alpha, beta)
+            },
+        ))
+    }
+
+    fn excited(
+        &mut self,
+        state: &state::ExcitedState_<T>,
+        depth: u32,
+        alpha: f64,
+        beta: f64,
+    ) -> Line<T::AbsoluteCoord> {
+        let config = self.searcher.config;
+        let decisions = state
+            .get_candidates(config)
+            .into_iter()
+            .map(Decision::AfterHalfAcceptance)
+            .collect();
+        self.decide(
+            state.c.whose_turn,
+            decisions,
+            alpha,
+            beta,
+            |run, decision, alpha, beta| {
+                let Decision::AfterHalfAcceptance(msg) = decision else {
+                    return None;
+                };
+                let p = super::apply_after_half_acceptance(state, msg, config).ok()?;
+                Some(run.chance(
+                    p.into_outcomes(),
+                    alpha,
+                    beta,
+                    |run, hand_not_resolved, alpha, beta| {
+                        let resolved = super::resolve_with_setup(
+                            &hand_not_resolved,
+                            config,
+                            &run.searcher.setup,
+                        );
+                        run.hand_resolved(resolved, depth, alpha, beta)
+                    },
+                ))
+            },
+        )
+    }
+
+    fn hand_resolved(
+        &mut self,
+        resolved: state::HandResolved_<T>,
+        depth: u32,
+        alpha: f64,
+        beta: f64,
+    ) -> Line<T::AbsoluteCoord> {
+        match resolved {
+            state::HandResolved_::NeitherTymokNorTaxot(next) => {
+                self.ground(&next, depth - 1, alpha, beta)
+            }
+            state::HandResolved_::GameEndsWithoutTymokTaxot(victor) => {
+                (value_of_victor(victor), vec![])
+            }
+            state::HandResolved_::SeasonEndsWithoutTymokTaxot(if_taxot) => {
+                self.if_taxot(if_taxot, depth, alpha, beta)
+            }
+            state::HandResolved_::HandExists { if_tymok, if_taxot } => {
+                let mut if_taxot = Some(if_taxot);
+                self.decide(
+                    !if_tymok.whose_turn, /* the player who has formed the hand */
+                    vec![
+                        Decision::TymokOrTaxot(TymokOrTaxot::Taxot),
+                        Decision::TymokOrTaxot(TymokOrTaxot::Tymok),
+                    ],
+                    alpha,
+                    beta,
+                    |run, decision, alpha, beta| match decision {
+                        Decision::TymokOrTaxot(TymokOrTaxot::Tymok) => {
+                            Some(run.ground(&if_tymok, depth - 1, alpha, beta))
+                        }
+                        Decision::TymokOrTaxot(TymokOrTaxot::Taxot) => {
+                            let if_taxot = if_taxot.take()?;
+                            Some(run.if_taxot(if_taxot, depth, alpha, beta))
+                        }
+                        _ => None,
+                    },
+                )
+            }
+        }
+    }
+
+    fn if_taxot(
+        &mut self,
+        if_taxot: IfTaxot_<T>,
+        depth: u32,
+        alpha: f64,
+        beta: f64,
+    ) -> Line<T::AbsoluteCoord> {
+        match if_taxot {
+            IfTaxot_::VictoriousSide(victor) => (value_of_victor(victor), vec![]),
+            IfTaxot_::NextSeason(p) => {
+                self.chance(p.into_outcomes(), alpha, beta, |run, next, alpha, beta| {
+                    run.ground(&next, depth - 1, alpha, beta)
+                })
+            }
+        }
+    }
+
+    /// A decision node with alpha-beta pruning. IA side maximizes and A side minimizes. `f` returns `None` for a decision that turns out to be illegal.
+    /// ／アルファベータ枝刈りつきの決断ノード。IA 側が最大化し、A 側が最小化する。`f` は不正と判明した決断に対しては `None` を返す。
+    fn decide(
+        &mut self,
+        player: AbsoluteSide,
+        decisions: Vec<Decision<T::AbsoluteCoord>>,
+        mut alpha: f64,
+        mut beta: f64,
+        mut f: impl FnMut(
+            &mut Self,
+            Decision<T::AbsoluteCoord>,
+            f64,
+            f64,
+        ) -> Option<Line<T::AbsoluteCoord>>,
+    ) -> Line<T::AbsoluteCoord> {
+        let maximizing = player == AbsoluteSide::IASide;
+        let mut best: Option<Line<T::AbsoluteCoord>> = None;
+        for decision in decisions {
+            let Some((value, pv)) = f(self, decision, alpha, beta) else {
+                continue;
+            };
+            if self.aborted {
+                return (0.0, vec![]);
+            }
+            let improves = best.as_ref().map_or(true, |(best_value, _)| {
+                if maximizing {
+                    value > *best_value
+                } else {
+                    value < *best_value
+                }
+            });
+            if improves {
+                let mut line = vec![decision];
+                line.extend(pv);
+                best = Some((value, line));
+            }
+            if maximizing {
+                alpha = alpha.max(value);
+            } else {
+                beta = beta.min(value);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        // Every decision turned out to be illegal; this does not happen with a consistent `Config`
+        // 全ての決断が不正と判明した。矛盾のない `Config` ではこれは起こらない
+        best.unwrap_or((0.0, vec![]))
+    }
+
+    /// A chance node with Star1 pruning: the outcomes are searched with narrowed windows, and the search stops once the bounds on the expected value fall outside `alpha..beta`.
+    /// ／Star1 枝刈りつきの偶然手番。結果を狭めた窓で探索し、期待値の上下界が `alpha..beta` の外に出たら探索を打ち切る。
+    fn chance<S>(
+        &mut self,
+        mut outcomes: Vec<(S, f64)>,
+        alpha: f64,
+        beta: f64,
+        mut f: impl FnMut(&mut Self, S, f64, f64) -> Line<T::AbsoluteCoord>,
+    ) -> Line<T::AbsoluteCoord> {
+        // The likely outcomes first, since they narrow the bounds the most
+        // 上下界を最も狭めるので、確率の高い結果を先に
+        outcomes.sort_by(|(_, p), (_, q)| q.total_cmp(p));
+
+        let mut sum = 0.0;
+        let mut remaining = 1.0;
+        let mut pv = None;
+        for (outcome, probability) in outcomes {
+            remaining -= probability;
+            let child_alpha = ((alpha - sum - remaining * UPPER) / probability).max(LOWER);
+            let child_beta = ((beta - sum - remaining * LOWER) / probability).min(UPPER);
+            let (value, child_pv) = f(self, outcome, child_alpha, child_beta);
+            if self.aborted {
+                return (0.0, vec![]);
+            }
+            sum += probability * value;
+            let pv = pv.get_or_insert(child_pv);
+            if sum + remaining * UPPER <= alpha {
+                return (sum + remaining * UPPER, std::mem::take(pv));
+            }
+            if sum + remaining * LOWER >= beta {
+                return (sum + remaining * LOWER, std::mem::take(pv));
+            }
+        }
+        (sum, pv.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{position_with_a_winning_hand, WINNING_CAPTURE};
+
+    #[test]
+    fn test_search_finds_a_winning_hand() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        let result = Searcher::new(
+            Config::cerke_online_alpha(),
+            score_difference::<CetkaikNaive>,
+        )
+        .with_max_depth(2)
+        .search(&position_with_a_winning_hand());
+        assert_eq!(result.best_move, Some(WINNING_CAPTURE));
+        assert!((result.expected_value - 1.0).abs() < f64::EPSILON);
+        assert_eq!(
+            result.principal_variation,
+            vec![WINNING_CAPTURE, Decision::TymokOrTaxot(TymokOrTaxot::Taxot)]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use super::{Rate, Scores, Season};
+use cetkaik_fundamental::{AbsoluteSide, ColorAndProf, Profession};
+use cetkaik_traits::{CetkaikRepresentation, IsAbsoluteField, IsBoard, IsPieceWithSide};
+
+/// A representation whose hop1zuo1 can be directly edited. `cetkaik_traits` only allows a piece to enter a hop1zuo1 by being captured, which is not enough to lay out an arbitrary position, and only changes a hop1zuo1 by building a new field, which `GroundState_::make` avoids.
+/// ／手駒を直接編集できる表現。`cetkaik_traits` では駒を取ることでしか手駒に駒を加えられず、任意の局面を並べるには足りない。また新しい盤を作ることでしか手駒を変えられないが、`GroundState_::make` はそれを避ける。
+pub trait Hop1Zuo1Editable: CetkaikRepresentation {
+    fn put_into_hop1zuo1(f: &mut Self::AbsoluteField, side: AbsoluteSide, piece: ColorAndProf);
+
+    /// Takes one `piece` out of `side`'s hop1zuo1. Returns whether there was one.
+    /// ／`side` の手駒から `piece` を一つ取り出す。あったかどうかを返す。
+    fn take_from_hop1zuo1(
+        f: &mut Self::AbsoluteField,
+        side: AbsoluteSide,
+        piece: ColorAndProf,
+    ) -> bool;
+
+    /// `piece` on the board as `side`'s.
+    /// ／盤上の `side` の駒としての `piece`。
+    fn piece_of(piece: ColorAndProf, side: AbsoluteSide) -> Self::AbsolutePiece;
+}
+
+#[cfg(feature = "naive")]
+impl Hop1Zuo1Editable for cetkaik_naive_representation::CetkaikNaive {
+    fn put_into_hop1zuo1(f: &mut Self::AbsoluteField, side: AbsoluteSide, piece: ColorAndProf) {
+        f.insert_nontam_piece_into_hop1zuo1(piece.color, piece.prof, side);
+    }
+
+    fn take_from_hop1zuo1(
+        f: &mut Self::AbsoluteField,
+        side: AbsoluteSide,
+        piece: ColorAndProf,
+    ) -> bool {
+        let hop1zuo1 = match side {
+            AbsoluteSide::ASide => &mut f.a_side_hop1zuo1,
+            AbsoluteSide::IASide => &mut f.ia_side_hop1zuo1,
+        };
+
+        // The last one, so that taking back a capture restores the order
+        // 駒取りを取り消すときに順序が元に戻るよう、最後のもの
+        let Some(index) = hop1zuo1.iter().rposition(|p| *p == piece) else {
+            return false;
+        };
+        hop1zuo1.remove(index);
+        true
+    }
+
+    fn piece_of(piece: ColorAndProf, side: AbsoluteSide) -> Self::AbsolutePiece {
+        cetkaik_naive_representation::absolute::Piece::NonTam2Piece {
+            color: piece.color,
+            prof: piece.prof,
+            side,
+        }
+    }
+}
+
+/// Describes the position that each season starts from, along with the season, the scores and the rate that the game starts with. Used for textbook positions and for handicaps.
+/// Only a legal setup can be constructed: no two pieces share a square, there is exactly one Tam2, and no more pieces of each kind exist than the game has.
+/// ／各季節の開始局面と、ゲーム開始時の季節・得点・レートを表す。教本の局面や駒落ちに用いる。
+/// 合法なものしか構築できない。すなわち、同じマスに駒が二つ置かれておらず、皇がちょうど一つで、各種類の駒がゲームに存在する個数を超えない。
+///
+#[cfg_attr(feature = "naive", doc = "```")]
+#[cfg_attr(not(feature = "naive"), doc = "```ignore")]
+/// use cetkaik_fundamental::{AbsoluteSide, Color, ColorAndProf, Profession};
+/// use cetkaik_full_state_transition::setup::Setup;
+/// use cetkaik_full_state_transition::probabilistic::Probabilistic;
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::absolute::{Coord, Piece};
+/// use cetkaik_naive_representation::absolute::Row::*;
+/// use cetkaik_naive_representation::absolute::Column::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let setup = Setup::<CetkaikNaive>::new(
+///     &[
+///         (Coord(O, Z), Piece::Tam2),
+///         (Coord(IA, Z), Piece::NonTam2Piece { color: Color::Kok1, prof: Profession::Io, side: AbsoluteSide::IASide }),
+///         (Coord(A, Z), Piece::NonTam2Piece { color: Color::Huok2, prof: Profession::Io, side: AbsoluteSide::ASide }),
+///     ],
+///     &[],
+///     &[ColorAndProf { color: Color::Kok1, prof: Profession::Uai1 }],
+/// )
+/// .unwrap()
+/// .with_whose_turn(Some(AbsoluteSide::ASide));
+///
+/// let Probabilistic::Pure(state) = initial_state_from_setup(&setup, Config::cerke_online_alpha()) else { panic!() };
+/// assert_eq!(state.whose_turn(), AbsoluteSide::ASide);
+/// assert_eq!(state.f().ia_side_hop1zuo1.len(), 1);
+///
+/// // two pieces on the same square
+/// assert!(Setup::<CetkaikNaive>::new(&[(Coord(O, Z), Piece::Tam2), (Coord(O, Z), Piece::Tam2)], &[], &[]).is_err());
+/// ```
+#[readonly::make]
+#[derive(Clone, Debug)]
+pub struct Setup<T: CetkaikRepresentation> {
+    pub f: T::AbsoluteField,
+
+    /// `None` means that the first mover is decided at random.
+    /// ／`None` ならば先手は無作為に決まる。
+    pub whose_turn: Option<AbsoluteSide>,
+
+    pub season: Season,
+    pub scores: Scores,
+
+    /// Only affects the first season; the following seasons start from `Rate::X1` as usual.
+    /// ／最初の季節にのみ影響する。以降の季節は通常どおり `Rate::X1` から始まる。
+    pub rate: Rate,
+}
+
+const fn upper_limit(prof: Profession) -> usize {
+    match prof {
+        Profession::Kauk2 => 8,
+        Profession::Io | Profession::Nuak1 => 1,
+        Profession::Gua2
+        | Profession::Kaun1
+        | Profession::Dau2
+        | Profession::Maun1
+        | Profession::Kua2
+        | Profession::Tuk2
+        | Profession::Uai1 => 2,
+    }
+}
+
+impl<T: CetkaikRepresentation> Setup<T> {
+    /// The official initial arrangement, with the first mover decided at random.
+    /// ／官定の初期配置。先手は無作為に決まる。
+    #[must_use]
+    pub fn yhuap_initial() -> Self {
+        Self {
+            f: <T::AbsoluteField as IsAbsoluteField>::yhuap_initial(),
+            whose_turn: None,
+            season: Season::Iei2,
+            scores: Scores::new(),
+            rate: Rate::X1,
+        }
+    }
+
+    /// Validates and lays out the pieces. The game starts in spring with each player holding 20 points, and the first mover is decided at random; use the `with_*` methods to change them.
+    /// ／駒の配置を検証して並べる。ゲームは春に所持点20で始まり、先手は無作為に決まる。変更するには `with_*` 系のメソッドを用いる。
+    pub fn new(
+        board: &[(T::AbsoluteCoord, T::AbsolutePiece)],
+        a_side_hop1zuo1: &[ColorAndProf],
+        ia_side_hop1zuo1: &[ColorAndProf],
+    ) -> Result<Self, &'static str>
+    where
+        T: Hop1Zuo1Editable,
+    {
+        for (i, (coord, _)) in board.iter().enumerate() {
+            if board[i + 1..].iter().any(|(c, _)| c == coord) {
+                return Err("two pieces are placed on the same square");
+            }
+        }
+
+        match board
+            .iter()
+            .filter(|(_, piece)| *piece == T::absolute_tam2())
+            .count()
+        {
+            0 => return Err("there is no Tam2 on the board"),
+            1 => {}
+            _ => return Err("there is more than one Tam2 on the board"),
+        }
+
+        let mut pieces: Vec<ColorAndProf> = board
+            .iter()
+            .filter_map(|(_, piece)| {
+                piece.match_on_piece_and_apply(&|| None, &|color, prof, _| {
+                    Some(ColorAndProf { color, prof })
+                })
+            })
+            .collect();
+        pieces.extend_from_slice(a_side_hop1zuo1);
+        pieces.extend_from_slice(ia_side_hop1zuo1);
+        for p in &pieces {
+            if pieces.iter().filter(|q| *q == p).count() > upper_limit(p.prof) {
+                return Err(
+                    "there are more pieces of the same color and profession than the game has",
+                );
+            }
+        }
+
+        let mut f = <T::AbsoluteField as IsAbsoluteField>::yhuap_initial();
+        for coord in super::occupied_squares::<T>(&f) {
+            T::as_board_mut_absolute(&mut f).put(coord, None);
+        }
+        for (coord, piece) in board {
+            T::as_board_mut_absolute(&mut f).put(*coord, Some(*piece));
+        }
+        for piece in a_side_hop1zuo1 {
+            T::put_into_hop1zuo1(&mut f, AbsoluteSide::ASide, *piece);
+        }
+        for piece in ia_side_hop1zuo1 {
+            T::put_into_hop1zuo1(&mut f, AbsoluteSide::IASide, *piece);
+        }
+
+        Ok(Self {
+            f,
+            ..Self::yhuap_initial()
+        })
+    }
+
+    #[must_use]
+    pub fn with_whose_turn(self, whose_turn: Option<AbsoluteSide>) -> Self {
+        Self { whose_turn, ..self }
+    }
+
+    #[must_use]
+    pub fn with_season(self, season: Season) -> Self {
+        Self { season, ..self }
+    }
+
+    #[must_use]
+    pub fn with_scores(self, scores: Scores) -> Self {
+        Self { scores, ..self }
+    }
+
+    #[must_use]
+    pub fn with_rate(self, rate: Rate) -> Self {
+        Self { rate, ..self }
+    }
+}
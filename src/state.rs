@@ -2,23 +2,196 @@ use crate::IfTaxot_;
 
 use super::{state, Rate, Scores, Season};
 use cetkaik_fundamental::AbsoluteSide;
-use cetkaik_traits::CetkaikRepresentation;
+use cetkaik_traits::{CetkaikRepresentation, IsAbsoluteField, IsBoard};
 use serde::{Deserialize, Serialize};
 
 type PM<T> = super::message::PureMove__<<T as CetkaikRepresentation>::AbsoluteCoord>;
 
 /// Normal state. ／一番普通の状態。
-#[derive(Clone, Debug, Serialize, Deserialize)]
+///
+/// Only obtained from `GroundState_::new`, from the transition functions or by deserializing, all of which compute `zobrist_hash()` and `hands()` from the other fields. The fields are read through methods of the same names, so that they can never disagree with those two.
+/// ／`GroundState_::new`・状態遷移関数・デシリアライズからのみ得られ、いずれも `zobrist_hash()` と `hands()` を他のフィールドから計算する。フィールドはそれらと食い違わないよう、同名のメソッドを通じて読む。
+#[derive(Clone, Debug, Serialize)]
 pub struct GroundState_<T: CetkaikRepresentation> {
-    pub f: T::AbsoluteField,
-    pub whose_turn: AbsoluteSide,
-    pub season: Season,
-    pub scores: Scores,
-    pub rate: Rate,
-    pub tam_has_moved_previously: bool,
+    pub(crate) f: T::AbsoluteField,
+
+    /// Maintained by the transition functions, and not serialized. See `hands::HandTracker`.
+    /// ／状態遷移関数によって更新され、シリアライズされない。`hands::HandTracker` を参照。
+    #[serde(skip)]
+    pub(crate) hands: super::hands::HandTracker,
+    pub(crate) whose_turn: AbsoluteSide,
+    pub(crate) season: Season,
+    pub(crate) season_first_mover: AbsoluteSide,
+    pub(crate) moves_in_this_season: u32,
+    pub(crate) positions_in_this_season: super::repetition::PositionHistory,
+    pub(crate) scores: Scores,
+    pub(crate) rate: Rate,
+    pub(crate) tam_has_moved_previously: bool,
+
+    /// Maintained by the transition functions, and not serialized. See `zobrist::hash_of_ground_state`.
+    /// ／状態遷移関数によって更新され、シリアライズされない。`zobrist::hash_of_ground_state` を参照。
+    #[serde(skip)]
+    pub(crate) zobrist_hash: u64,
+
+    /// Built by the first call to `legal_move_set`, and not serialized. See `LegalMoveCache`.
+    /// ／`legal_move_set` の最初の呼び出しによって作られ、シリアライズされない。`LegalMoveCache` を参照。
+    #[serde(skip)]
+    pub(crate) legal_move_cache: LegalMoveCache,
+}
+
+/// Also accepts the format of version 1.3.1, which lacks `season_first_mover`, `moves_in_this_season` and `positions_in_this_season`. They are then assumed to be `whose_turn`, 0 and only the current position.
+/// ／`season_first_mover`・`moves_in_this_season`・`positions_in_this_season` を欠くバージョン 1.3.1 の形式も受け付ける。その場合はそれぞれ `whose_turn`・0・現在の局面のみであるとみなす。
+impl<'de, T: CetkaikRepresentation> Deserialize<'de> for GroundState_<T>
+where
+    T::AbsoluteField: Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(bound(deserialize = "T::AbsoluteField: Deserialize<'de>"))]
+        struct Stored<T: CetkaikRepresentation> {
+            f: T::AbsoluteField,
+            whose_turn: AbsoluteSide,
+            season: Season,
+            #[serde(default)]
+            season_first_mover: Option<AbsoluteSide>,
+            #[serde(default)]
+            moves_in_this_season: u32,
+            #[serde(default)]
+            positions_in_this_season: super::repetition::PositionHistory,
+            scores: Scores,
+            rate: Rate,
+            tam_has_moved_previously: bool,
+        }
+
+        let stored = Stored::<T>::deserialize(deserializer)?;
+        let mut state = Self {
+            hands: super::hands::HandTracker::of_field::<T>(&stored.f),
+            f: stored.f,
+            whose_turn: stored.whose_turn,
+            season: stored.season,
+            season_first_mover: stored.season_first_mover.unwrap_or(stored.whose_turn),
+            moves_in_this_season: stored.moves_in_this_season,
+            positions_in_this_season: stored.positions_in_this_season,
+            scores: stored.scores,
+            rate: stored.rate,
+            tam_has_moved_previously: stored.tam_has_moved_previously,
+            zobrist_hash: 0,
+            legal_move_cache: LegalMoveCache::default(),
+        };
+        state.zobrist_hash = super::zobrist::hash_of_ground_state(&state);
+        if state.positions_in_this_season.is_empty() {
+            state
+                .positions_in_this_season
+                .push(super::repetition::position_key_of_state(&state));
+        }
+        Ok(state)
+    }
 }
 
 impl<T: CetkaikRepresentation> state::GroundState_<T> {
+    /// A state at the beginning of a season, as laid out by hand: no move has been made in the season yet. The hands and `zobrist_hash()` are calculated from `f`.
+    /// ／手で並べた、季節の始まりの状態。季節内ではまだ一手も指されていない。役と `zobrist_hash()` は `f` から計算される。
+    ///
+    /// # Panics
+    /// Panics if a hop1zuo1 contains more pieces than the game has.
+    #[must_use]
+    pub fn new(
+        f: T::AbsoluteField,
+        whose_turn: AbsoluteSide,
+        season: Season,
+        season_first_mover: AbsoluteSide,
+        scores: Scores,
+        rate: Rate,
+        tam_has_moved_previously: bool,
+    ) -> Self {
+        let mut state = Self {
+            hands: super::hands::HandTracker::of_field::<T>(&f),
+            f,
+            whose_turn,
+            season,
+            season_first_mover,
+            moves_in_this_season: 0,
+            positions_in_this_season: super::repetition::PositionHistory::new(),
+            scores,
+            rate,
+            tam_has_moved_previously,
+            zobrist_hash: 0,
+            legal_move_cache: LegalMoveCache::default(),
+        };
+        state.zobrist_hash = super::zobrist::hash_of_ground_state(&state);
+        state
+            .positions_in_this_season
+            .push(super::repetition::position_key_of_state(&state));
+        state
+    }
+
+    /// The board and both hop1zuo1.
+    /// ／盤面と両者の手駒。
+    #[must_use]
+    pub const fn f(&self) -> &T::AbsoluteField {
+        &self.f
+    }
+
+    #[must_use]
+    pub const fn whose_turn(&self) -> AbsoluteSide {
+        self.whose_turn
+    }
+
+    #[must_use]
+    pub const fn season(&self) -> Season {
+        self.season
+    }
+
+    /// The side that made the first move of the current season. Needed by `FirstMover::Alternate`.
+    /// ／この季節で先手だった側。`FirstMover::Alternate` で必要になる。
+    #[must_use]
+    pub const fn season_first_mover(&self) -> AbsoluteSide {
+        self.season_first_mover
+    }
+
+    /// The number of moves (including passes) made so far in the current season. Needed by `Config::move_limit`.
+    /// ／この季節でこれまでに指された（パスを含む）手数。`Config::move_limit` で必要になる。
+    #[must_use]
+    pub const fn moves_in_this_season(&self) -> u32 {
+        self.moves_in_this_season
+    }
+
+    /// The keys (see `repetition::position_key`) of the positions that have appeared in the current season, including the current one. Needed by `Config::repetition`. Shared between the states of a season, so that cloning it is cheap.
+    /// ／この季節に現れた局面の鍵（`repetition::position_key` を参照）。現在の局面を含む。`Config::repetition` で必要になる。季節内の状態の間で共有されるので、複製は安価である。
+    #[must_use]
+    pub const fn positions_in_this_season(&self) -> &super::repetition::PositionHistory {
+        &self.positions_in_this_season
+    }
+
+    #[must_use]
+    pub const fn scores(&self) -> Scores {
+        self.scores
+    }
+
+    #[must_use]
+    pub const fn rate(&self) -> Rate {
+        self.rate
+    }
+
+    #[must_use]
+    pub const fn tam_has_moved_previously(&self) -> bool {
+        self.tam_has_moved_previously
+    }
+
+    /// Covers the board, both hop1zuo1 (as multisets), whose turn it is, the season, the scores, the rate and `tam_has_moved_previously`. See `zobrist::hash_of_ground_state`.
+    /// ／盤面・両者の手駒（多重集合として）・手番・季節・得点・レート・`tam_has_moved_previously` を含む。`zobrist::hash_of_ground_state` を参照。
+    #[must_use]
+    pub const fn zobrist_hash(&self) -> u64 {
+        self.zobrist_hash
+    }
+
+    /// The hands that both sides' hop1zuo1 form. See `hands::HandTracker`.
+    /// ／両者の手駒がなす役。`hands::HandTracker` を参照。
+    #[must_use]
+    pub const fn hands(&self) -> super::hands::HandTracker {
+        self.hands
+    }
+
     /// ```
     /// use cetkaik_full_state_transition::message::InfAfterStep_;
     /// use cetkaik_full_state_transition::*;
@@ -38,18 +211,19 @@ impl<T: CetkaikRepresentation> state::GroundState_<T> {
     ///
     ///     assert_eq!(a, b)
     /// }
-    /// let ia_first = state::GroundState_::<CetkaikNaive> {
-    ///     whose_turn: AbsoluteSide::IASide,
-    ///     scores: Scores::new(),
-    ///     rate: Rate::X1,
-    ///     season: Season::Iei2,
-    ///     tam_has_moved_previously: false,
-    ///     f: absolute::Field {
+    /// let ia_first = state::GroundState_::<CetkaikNaive>::new(
+    ///     absolute::Field {
     ///         a_side_hop1zuo1: vec![],
     ///         ia_side_hop1zuo1: vec![],
     ///         board: cetkaik_naive_representation::absolute::yhuap_initial_board(),
     ///     },
-    /// };
+    ///     AbsoluteSide::IASide,
+    ///     Season::Iei2,
+    ///     AbsoluteSide::IASide,
+    ///     Scores::new(),
+    ///     Rate::X1,
+    ///     false,
+    /// );
     /// let (hop1zuo1_candidates, candidates) = ia_first.get_candidates(Config::cerke_online_alpha());
     /// assert_eq!(hop1zuo1_candidates, vec![]);
     /// let inf_after_step: Vec<_> = candidates.into_iter()
@@ -105,132 +279,323 @@ impl<T: CetkaikRepresentation> state::GroundState_<T> {
     /// ```
     #[must_use]
     pub fn get_candidates(&self, config: super::Config) -> (Vec<PM<T>>, Vec<PM<T>>) {
-        use cetkaik_yhuap_move_candidates::{
-            from_hop1zuo1_candidates_vec, not_from_hop1zuo1_candidates_vec,
-        };
+        (
+            self.candidates_from_hop1zuo1(config).collect(),
+            self.candidates_not_from_hop1zuo1(config).collect(),
+        )
+    }
 
-        let hop1zuo1_candidates = from_hop1zuo1_candidates_vec::<T>(self.whose_turn, &self.f)
+    /// The moves of both lists of `get_candidates`, in the same order. Each list is generated as a whole by `cetkaik_yhuap_move_candidates` when the iterator first reaches it, and the restrictions by `config` are applied as the moves are taken out, without another pass over the list.
+    /// ／`get_candidates` の二つのリストの手を同じ順に並べたもの。各リストはイテレータが初めてそこに達したときに `cetkaik_yhuap_move_candidates` によって丸ごと生成され、`config` による制限はリストを再走査することなく手を取り出すときに適用される。
+    pub fn candidates(&self, config: super::Config) -> impl Iterator<Item = PM<T>> + '_ {
+        self.candidates_from_hop1zuo1(config)
+            .chain(self.candidates_not_from_hop1zuo1(config))
+    }
+
+    /// The first list of `get_candidates` as an iterator. See `candidates`.
+    /// ／`get_candidates` の一つ目のリストをイテレータで。`candidates` を参照。
+    pub fn candidates_from_hop1zuo1(
+        &self,
+        config: super::Config,
+    ) -> impl Iterator<Item = PM<T>> + '_ {
+        std::iter::once_with(move || {
+            let drop_is_restricted = (config.drop_restriction != super::DropRestriction::NONE)
+                .then(|| super::drop_is_restricted::<T>(config, self.whose_turn, &self.f));
+            cetkaik_yhuap_move_candidates::from_hop1zuo1_candidates_vec::<T>(
+                self.whose_turn,
+                &self.f,
+            )
             .into_iter()
             .map(super::message::PureMove__::from)
-            .collect::<Vec<_>>();
+            .filter(move |mv| match mv {
+                super::message::PureMove__::NormalMove(
+                    super::message::NormalMove_::NonTamMoveFromHopZuo { dest, .. },
+                ) => drop_is_restricted
+                    .as_ref()
+                    .map_or(true, |drop_is_restricted| !drop_is_restricted(*dest)),
+                _ => true,
+            })
+        })
+        .flatten()
+    }
 
-        let mut candidates = not_from_hop1zuo1_candidates_vec::<T>(
-            &cetkaik_yhuap_move_candidates::AllowKut2Tam2 {
-                allow_kut2tam2: true,
-            },
-            config.tam_itself_is_tam_hue,
-            self.whose_turn,
-            &self.f,
-        )
-        .into_iter()
+    /// The second list of `get_candidates` as an iterator. See `candidates`.
+    /// ／`get_candidates` の二つ目のリストをイテレータで。`candidates` を参照。
+    pub fn candidates_not_from_hop1zuo1(
+        &self,
+        config: super::Config,
+    ) -> impl Iterator<Item = PM<T>> + '_ {
+        let tam_may_move = !(self.tam_has_moved_previously
+            && config.moving_tam_immediately_after_tam_has_moved == super::Consequence::Forbidden);
+        let tam_mun_mok_is_allowed = config.tam_mun_mok != super::Consequence::Forbidden;
+        std::iter::once_with(move || {
+            cetkaik_yhuap_move_candidates::not_from_hop1zuo1_candidates_vec::<T>(
+                &cetkaik_yhuap_move_candidates::AllowKut2Tam2 {
+                    allow_kut2tam2: config.kut2tam2 != super::Consequence::Forbidden,
+                },
+                config.tam_itself_is_tam_hue,
+                self.whose_turn,
+                &self.f,
+            )
+        })
+        .flatten()
         .map(super::message::PureMove__::from)
-        .collect::<Vec<_>>();
-
-        if self.tam_has_moved_previously
-            && config.moving_tam_immediately_after_tam_has_moved == super::Consequence::Forbidden
-        {
-            candidates.retain(|a| {
-                !matches!(
-                    a,
-                    super::message::PureMove__::NormalMove(
-                        super::message::NormalMove_::TamMoveNoStep { .. }
-                            | super::message::NormalMove_::TamMoveStepsDuringFormer { .. }
-                            | super::message::NormalMove_::TamMoveStepsDuringLatter { .. },
-                    )
-                )
-            });
-        }
-
-        if config.tam_mun_mok == super::Consequence::Forbidden {
-            candidates.retain(|a| {
-                match a {
-                    super::message::PureMove__::NormalMove(
-                        super::message::NormalMove_::TamMoveNoStep {
-                            src, second_dest, ..
-                        }
-                        | super::message::NormalMove_::TamMoveStepsDuringFormer {
-                            src,
-                            second_dest,
-                            ..
-                        }
-                        | super::message::NormalMove_::TamMoveStepsDuringLatter {
-                            src,
-                            second_dest,
-                            ..
-                        },
-                    ) => src != second_dest, /* false when mun1mok1 */
-                    _ => true, /* always allow */
+        .filter(move |mv| match mv {
+            super::message::PureMove__::NormalMove(
+                super::message::NormalMove_::TamMoveNoStep {
+                    src, second_dest, ..
+                }
+                | super::message::NormalMove_::TamMoveStepsDuringFormer {
+                    src,
+                    second_dest,
+                    ..
                 }
-            });
+                | super::message::NormalMove_::TamMoveStepsDuringLatter {
+                    src,
+                    second_dest,
+                    ..
+                },
+            ) => tam_may_move && (tam_mun_mok_is_allowed || src != second_dest), /* false when mun1mok1 */
+            _ => true, /* always allow */
+        })
+    }
+
+    /// Whether `candidates` is not empty. Usually found without generating any candidate: the list of `candidates_not_from_hop1zuo1` is generated only if no piece can be dropped and no piece other than Tam2 reaches an empty square.
+    /// ／`candidates` が空でないかどうか。通常は候補手を一つも生成することなく求める。`candidates_not_from_hop1zuo1` のリストが生成されるのは、打てる駒がなく、皇以外のどの駒も空きマスに到達しないときに限る。
+    #[must_use]
+    pub fn has_any_legal_move(&self, config: super::Config) -> bool {
+        // A piece in hop1zuo1 can be placed on any empty square where dropping is not restricted
+        // 手駒は、打つことが制限されていない任意の空きマスに打てる
+        let can_drop = || {
+            let drop_is_restricted =
+                super::drop_is_restricted::<T>(config, self.whose_turn, &self.f);
+            T::as_board_absolute(&self.f)
+                .empty_squares()
+                .any(|dest| !drop_is_restricted(dest))
+        };
+
+        // A piece other than Tam2 can always move to an empty square that it reaches, whatever `config` says
+        // 皇以外の駒は、到達する空きマスには `config` によらず常に動ける
+        let can_move_to_an_empty_square = || {
+            let perspective = T::get_one_perspective();
+            let field = T::to_relative_field(self.f.clone(), perspective);
+            let board = *T::as_board_relative(&field);
+            let mut found = false;
+            T::loop_over_one_side_and_tam(
+                &board,
+                T::to_relative_side(self.whose_turn, perspective),
+                &mut |src, prof| {
+                    if found || prof.is_none() {
+                        return;
+                    }
+                    let Some(piece) = board.peek(src) else {
+                        return;
+                    };
+                    let cetkaik_yhuap_move_candidates::MovablePositions { finite, infinite } =
+                        cetkaik_yhuap_move_candidates::calculate_movable_positions_for_either_side::<
+                            T,
+                        >(src, piece, board, config.tam_itself_is_tam_hue);
+                    found = finite
+                        .into_iter()
+                        .chain(infinite)
+                        .any(|dest| board.peek(dest).is_none());
+                },
+            );
+            found
+        };
+        (self.f.hop1zuo1_of(self.whose_turn).next().is_some() && can_drop())
+            || can_move_to_an_empty_square()
+            || self.candidates_not_from_hop1zuo1(config).next().is_some()
+    }
+
+    /// The moves of `candidates` in a hash set, for answering many legality queries about this state in O(1) each. The set is built once and kept by the state, so that later calls with the same `config` (including those that `apply_normal_move` and `apply_inf_after_step` make) do not build it again.
+    /// ／`candidates` の手をハッシュ集合にしたもの。この状態についての多数の合法性の問い合わせにそれぞれ O(1) で答えるため。集合は一度だけ作られて状態に保持されるので、同じ `config` による以降の呼び出し（`apply_normal_move` や `apply_inf_after_step` によるものを含む）はこれを作り直さない。
+    #[must_use]
+    pub fn legal_move_set(&self, config: super::Config) -> LegalMoveSet<'_, T> {
+        let build = || self.candidates(config).map(move_key::<T>).collect();
+        let moves = match self.legal_move_cache.0.get_or_init(|| (config, build())) {
+            (cached_for, moves) if *cached_for == config => std::borrow::Cow::Borrowed(moves),
+            _ => std::borrow::Cow::Owned(build()),
+        };
+        LegalMoveSet {
+            state: self,
+            config,
+            moves,
         }
+    }
 
-        (hop1zuo1_candidates, candidates)
+    /// Same as `get_candidates` (both lists chained), but each move comes as a token that `apply_legal_normal_move` and `apply_legal_inf_after_step` accept without checking it again.
+    /// ／`get_candidates`（二つのリストを連結したもの）と同じだが、各手は `apply_legal_normal_move` や `apply_legal_inf_after_step` が再検査せずに受け付けるトークンとして得られる。
+    ///
+    /// ```
+    /// use cetkaik_full_state_transition::state::LegalMove;
+    /// use cetkaik_full_state_transition::*;
+    /// use cetkaik_naive_representation::CetkaikNaive;
+    ///
+    /// let config = Config::cerke_online_alpha();
+    /// let state = initial_state::<CetkaikNaive>().choose().0;
+    /// let legal_moves = state.legal_moves(config);
+    /// let (hop1zuo1_candidates, candidates) = state.get_candidates(config);
+    /// assert_eq!(legal_moves.len(), hop1zuo1_candidates.len() + candidates.len());
+    /// for legal in legal_moves {
+    ///     match legal {
+    ///         LegalMove::NormalMove(legal) => {
+    ///             let _ = apply_legal_normal_move(legal);
+    ///         }
+    ///         LegalMove::InfAfterStep(legal) => {
+    ///             let _ = apply_legal_inf_after_step(legal);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    #[must_use]
+    pub fn legal_moves(&self, config: super::Config) -> Vec<LegalMove<'_, T>> {
+        self.candidates(config)
+            .map(|mv| match mv {
+                super::message::PureMove__::NormalMove(msg) => LegalMove::NormalMove(Legal {
+                    state: self,
+                    config,
+                    msg,
+                }),
+                super::message::PureMove__::InfAfterStep(msg) => LegalMove::InfAfterStep(Legal {
+                    state: self,
+                    config,
+                    msg,
+                }),
+            })
+            .collect()
     }
 }
 
-#[test]
-fn test_get_candidates() {
-    use crate::message::AfterHalfAcceptance_;
-    use cetkaik_naive_representation::absolute;
+/// A message found legal in `state` under `config` by `GroundState_::legal_moves`, which is the only way to obtain one. Since it borrows the state it was found in, it cannot be applied to any other state.
+/// ／`GroundState_::legal_moves` によって、`config` の下で `state` において合法であると分かったメッセージ。これを得る方法は他にない。見つかった状態を借用しているので、他の状態に適用することはできない。
+pub struct Legal<'state, T: CetkaikRepresentation, Msg> {
+    state: &'state GroundState_<T>,
+    config: super::Config,
+    msg: Msg,
+}
 
-    use absolute::{
-        Column::Z,
-        Coord,
-        Row::{AI, E, I, O, U},
-    };
-    use cetkaik_fundamental::Profession;
-    use cetkaik_naive_representation::CetkaikNaive;
-    assert_eq!(
-        ExcitedState_::<CetkaikNaive> {
-            c: ExcitedStateWithoutCiurl_ {
-                f: absolute::Field {
-                    a_side_hop1zuo1: vec![],
-                    ia_side_hop1zuo1: vec![],
-                    board: cetkaik_naive_representation::absolute::Board(
-                        std::collections::HashMap::from([
-                            (
-                                absolute::Coord(AI, Z),
-                                absolute::Piece::NonTam2Piece {
-                                    color: cetkaik_fundamental::Color::Huok2,
-                                    prof: Profession::Nuak1,
-                                    side: AbsoluteSide::IASide
-                                }
-                            ),
-                            (
-                                absolute::Coord(O, Z),
-                                absolute::Piece::NonTam2Piece {
-                                    color: cetkaik_fundamental::Color::Huok2,
-                                    prof: Profession::Kauk2,
-                                    side: AbsoluteSide::IASide
-                                }
-                            )
-                        ])
-                    )
-                },
-                whose_turn: AbsoluteSide::IASide,
-                flying_piece_src: absolute::Coord(AI, Z),
-                flying_piece_step: absolute::Coord(O, Z),
-                flying_piece_planned_direction: absolute::Coord(I, Z),
-                season: Season::Iei2,
-                scores: crate::Scores::default(),
-                rate: Rate::X1,
-            },
-            ciurl: 3
+impl<T: CetkaikRepresentation, Msg: Copy> Clone for Legal<'_, T, Msg> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: CetkaikRepresentation, Msg: Copy> Copy for Legal<'_, T, Msg> {}
+
+impl<'state, T: CetkaikRepresentation, Msg: Copy> Legal<'state, T, Msg> {
+    #[must_use]
+    pub const fn state(&self) -> &'state GroundState_<T> {
+        self.state
+    }
+
+    #[must_use]
+    pub const fn config(&self) -> super::Config {
+        self.config
+    }
+
+    #[must_use]
+    pub const fn msg(&self) -> Msg {
+        self.msg
+    }
+}
+
+/// See `GroundState_::legal_move_set`.
+/// ／`GroundState_::legal_move_set` を参照。
+pub struct LegalMoveSet<'state, T: CetkaikRepresentation> {
+    state: &'state GroundState_<T>,
+    config: super::Config,
+    moves: std::borrow::Cow<'state, std::collections::HashSet<super::message::PureMove__<u8>>>,
+}
+
+/// The set of `GroundState_::legal_move_set` together with the `Config` it was built for. Only the first `Config` asked for is kept; a state is almost always asked about under a single `Config`. `make` moves it into `Undo`, since it does not hold for the state after the action, and `unmake` gives it back.
+/// ／`GroundState_::legal_move_set` の集合と、それを作ったときの `Config`。最初に問われた `Config` についてのみ保持する。一つの状態がほぼ常に一つの `Config` の下で問われるからである。手を指した後の状態については成り立たないので `make` がこれを `Undo` に移し、`unmake` が戻す。
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LegalMoveCache(
+    pub(crate)  std::sync::OnceLock<(
+        super::Config,
+        std::collections::HashSet<super::message::PureMove__<u8>>,
+    )>,
+);
+
+impl<'state, T: CetkaikRepresentation> LegalMoveSet<'state, T> {
+    #[must_use]
+    pub fn contains(&self, mv: PM<T>) -> bool {
+        self.moves.contains(&move_key::<T>(mv))
+    }
+
+    /// Turns `mv`, e.g. one sent by a client, into a token for `apply_legal_normal_move` or `apply_legal_inf_after_step`. `None` if `mv` is illegal.
+    /// ／`mv`（クライアントから送られたものなど）を `apply_legal_normal_move` や `apply_legal_inf_after_step` のためのトークンにする。`mv` が違法ならば `None`。
+    #[must_use]
+    pub fn legalize(&self, mv: PM<T>) -> Option<LegalMove<'state, T>> {
+        if !self.contains(mv) {
+            return None;
         }
-        .get_candidates(crate::Config::cerke_online_alpha()),
-        vec![
-            AfterHalfAcceptance_ { dest: None },
-            AfterHalfAcceptance_ {
-                dest: Some(Coord(U, Z))
-            },
-            AfterHalfAcceptance_ {
-                dest: Some(Coord(I, Z))
-            },
-            AfterHalfAcceptance_ {
-                dest: Some(Coord(E, Z))
+        let (state, config) = (self.state, self.config);
+        Some(match mv {
+            super::message::PureMove__::NormalMove(msg) => {
+                LegalMove::NormalMove(Legal { state, config, msg })
+            }
+            super::message::PureMove__::InfAfterStep(msg) => {
+                LegalMove::InfAfterStep(Legal { state, config, msg })
             }
-        ]
-    );
+        })
+    }
+
+    /// The number of distinct legal moves, where `InfAfterStep`s that differ only in `planned_direction` count as one.
+    /// ／異なる合法手の数。`planned_direction` のみが異なる `InfAfterStep` は一つと数える。
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+/// `T::AbsoluteCoord` is not necessarily `Hash`, so the squares are numbered by `square_index`. The `planned_direction` of an `InfAfterStep` is left out, as `apply_inf_after_step` ignores it.
+/// ／`T::AbsoluteCoord` は `Hash` とは限らないので、マスは `square_index` で番号付けする。`apply_inf_after_step` が無視するので、`InfAfterStep` の `planned_direction` は含めない。
+// `square_index` is less than 81
+// `square_index` は81未満
+#[allow(clippy::cast_possible_truncation)]
+fn move_key<T: CetkaikRepresentation>(mv: PM<T>) -> super::message::PureMove__<u8> {
+    let mv = match mv {
+        super::message::PureMove__::InfAfterStep(msg) => {
+            super::message::PureMove__::InfAfterStep(super::message::InfAfterStep_ {
+                planned_direction: msg.src,
+                ..msg
+            })
+        }
+        super::message::PureMove__::NormalMove(_) => mv,
+    };
+    mv.map(|coord| super::square_index::<T>(coord) as u8)
+}
+
+/// A move returned by `GroundState_::legal_moves`.
+/// ／`GroundState_::legal_moves` が返す手。
+pub enum LegalMove<'state, T: CetkaikRepresentation> {
+    NormalMove(Legal<'state, T, super::message::NormalMove_<T::AbsoluteCoord>>),
+    InfAfterStep(Legal<'state, T, super::message::InfAfterStep_<T::AbsoluteCoord>>),
+}
+
+impl<T: CetkaikRepresentation> Clone for LegalMove<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: CetkaikRepresentation> Copy for LegalMove<'_, T> {}
+
+impl<T: CetkaikRepresentation> LegalMove<'_, T> {
+    #[must_use]
+    pub const fn pure_move(&self) -> PM<T> {
+        match self {
+            Self::NormalMove(legal) => super::message::PureMove__::NormalMove(legal.msg),
+            Self::InfAfterStep(legal) => super::message::PureMove__::InfAfterStep(legal.msg),
+        }
+    }
 }
 
 /// This is the state after the user has stepped over a piece and has cast the sticks so that the user can play to make an infinite movement from there. Seeing the sticks, the user is supposed to decide the final location and send it (`AfterHalfAcceptance`) to the server.
@@ -249,7 +614,7 @@ impl<T: CetkaikRepresentation> state::ExcitedState_<T> {
     ) -> Vec<super::message::AfterHalfAcceptance_<T::AbsoluteCoord>> {
         let candidates = cetkaik_yhuap_move_candidates::not_from_hop1zuo1_candidates_vec::<T>(
             &cetkaik_yhuap_move_candidates::AllowKut2Tam2 {
-                allow_kut2tam2: true,
+                allow_kut2tam2: config.kut2tam2 != super::Consequence::Forbidden,
             },
             config.tam_itself_is_tam_hue,
             self.c.whose_turn,
@@ -296,40 +661,244 @@ impl<T: CetkaikRepresentation> state::ExcitedState_<T> {
 
 /// Same as `ExcitedState`, except that the ciurl is not mentioned.
 /// ／`ExcitedState` から投げ棒の値を除いたやつ。
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct ExcitedStateWithoutCiurl_<T: CetkaikRepresentation> {
     pub f: T::AbsoluteField,
+
+    /// Maintained by the transition functions, and not serialized. See `hands::HandTracker`.
+    /// ／状態遷移関数によって更新され、シリアライズされない。`hands::HandTracker` を参照。
+    #[serde(skip)]
+    pub(crate) hands: super::hands::HandTracker,
     pub whose_turn: AbsoluteSide,
     pub flying_piece_src: T::AbsoluteCoord,
     pub flying_piece_step: T::AbsoluteCoord,
     pub flying_piece_planned_direction: T::AbsoluteCoord,
     pub season: Season,
+    pub season_first_mover: AbsoluteSide,
+    pub moves_in_this_season: u32,
+    pub positions_in_this_season: super::repetition::PositionHistory,
     pub scores: Scores,
     pub rate: Rate,
+
+    /// Maintained by the transition functions, and not serialized. See `zobrist::hash_of_excited_state`.
+    /// ／状態遷移関数によって更新され、シリアライズされない。`zobrist::hash_of_excited_state` を参照。
+    #[serde(skip)]
+    pub(crate) zobrist_hash: u64,
+}
+
+/// Also accepts the format of version 1.3.1, which lacks `season_first_mover`, `moves_in_this_season` and `positions_in_this_season`. They are then assumed to be `whose_turn`, 0 and empty.
+/// ／`season_first_mover`・`moves_in_this_season`・`positions_in_this_season` を欠くバージョン 1.3.1 の形式も受け付ける。その場合はそれぞれ `whose_turn`・0・空であるとみなす。
+impl<'de, T: CetkaikRepresentation> Deserialize<'de> for ExcitedStateWithoutCiurl_<T>
+where
+    T::AbsoluteField: Deserialize<'de>,
+    T::AbsoluteCoord: Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(bound(
+            deserialize = "T::AbsoluteField: Deserialize<'de>, T::AbsoluteCoord: Deserialize<'de>"
+        ))]
+        struct Stored<T: CetkaikRepresentation> {
+            f: T::AbsoluteField,
+            whose_turn: AbsoluteSide,
+            flying_piece_src: T::AbsoluteCoord,
+            flying_piece_step: T::AbsoluteCoord,
+            flying_piece_planned_direction: T::AbsoluteCoord,
+            season: Season,
+            #[serde(default)]
+            season_first_mover: Option<AbsoluteSide>,
+            #[serde(default)]
+            moves_in_this_season: u32,
+            #[serde(default)]
+            positions_in_this_season: super::repetition::PositionHistory,
+            scores: Scores,
+            rate: Rate,
+        }
+
+        let stored = Stored::<T>::deserialize(deserializer)?;
+        let mut state = Self {
+            hands: super::hands::HandTracker::of_field::<T>(&stored.f),
+            f: stored.f,
+            whose_turn: stored.whose_turn,
+            flying_piece_src: stored.flying_piece_src,
+            flying_piece_step: stored.flying_piece_step,
+            flying_piece_planned_direction: stored.flying_piece_planned_direction,
+            season: stored.season,
+            season_first_mover: stored.season_first_mover.unwrap_or(stored.whose_turn),
+            moves_in_this_season: stored.moves_in_this_season,
+            positions_in_this_season: stored.positions_in_this_season,
+            scores: stored.scores,
+            rate: stored.rate,
+            zobrist_hash: 0,
+        };
+        state.zobrist_hash = super::zobrist::hash_of_excited_state(&state);
+        Ok(state)
+    }
+}
+
+impl<T: CetkaikRepresentation> ExcitedStateWithoutCiurl_<T> {
+    /// See `zobrist::hash_of_excited_state`.
+    /// ／`zobrist::hash_of_excited_state` を参照。
+    #[must_use]
+    pub const fn zobrist_hash(&self) -> u64 {
+        self.zobrist_hash
+    }
+
+    /// The hands that both sides' hop1zuo1 form. See `hands::HandTracker`.
+    /// ／両者の手駒がなす役。`hands::HandTracker` を参照。
+    #[must_use]
+    pub const fn hands(&self) -> super::hands::HandTracker {
+        self.hands
+    }
 }
 
 /// The water entry cast (if any) is now over, and thus the piece movement is now fully completed. However, I still haven't resolved whether a hand exists. If so, I must ask the user to choose whether to end the season or not.
 /// ／入水判定も終わり、駒を完全に動かし終わった。しかしながら、「役が存在していて再行・終季をユーザーに訊く」を発生させるか否かをまだ解決していない。そんな状態。
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct HandNotResolved_<T: CetkaikRepresentation> {
     pub f: T::AbsoluteField,
+
+    /// Maintained by the transition functions, and not serialized. The hands before the move: does not count `captured_piece` yet, and still counts `dropped_piece`; `resolve` applies both to tell whether a new hand has been formed. See `hands::HandTracker`.
+    /// ／状態遷移関数によって更新され、シリアライズされない。手を指す前の役であり、`captured_piece` はまだ数えず、`dropped_piece` はまだ数える。`resolve` が両者を反映して新しい役ができたかを判定する。`hands::HandTracker` を参照。
+    #[serde(skip)]
+    pub(crate) hands: super::hands::HandTracker,
     pub whose_turn: AbsoluteSide,
     pub season: Season,
+    pub season_first_mover: AbsoluteSide,
+    pub moves_in_this_season: u32,
+    pub positions_in_this_season: super::repetition::PositionHistory,
     pub scores: Scores,
     pub rate: Rate,
     pub i_have_moved_tam_in_this_turn: bool,
-    pub previous_a_side_hop1zuo1: Vec<cetkaik_fundamental::ColorAndProf>,
-    pub previous_ia_side_hop1zuo1: Vec<cetkaik_fundamental::ColorAndProf>,
+
+    /// The piece that `whose_turn` has captured in this turn, if any. `resolve` looks at it to tell whether a new hand has been formed.
+    /// ／この手番で `whose_turn` が取った駒（あれば）。`resolve` はこれを見て新しい役ができたかを判定する。
+    pub captured_piece: Option<cetkaik_fundamental::ColorAndProf>,
+
+    /// The piece that `whose_turn` has dropped from hop1zuo1 in this turn, if any. Dropping can also form a hand, by breaking a 同色 hand into the plain one, so `resolve` looks at it as well.
+    /// ／この手番で `whose_turn` が手駒から打った駒（あれば）。打つことで同色の役が崩れて同色でない役ができることもあるので、`resolve` はこれも見る。
+    pub dropped_piece: Option<cetkaik_fundamental::ColorAndProf>,
     pub kut2tam2_happened: bool,
     pub tam2tysak2_raw_penalty: i32,
 
     /// Even when this field is set, the penalty is already subtracted from `ia_owner_s_score`
     /// ／このフィールドが `true` であるときも、罰則点はすでに `ia_owner_s_score` に計上してあるので、調整しなくてよい。
     pub tam2tysak2_will_trigger_taxottymok: bool,
+
+    /// Maintained by the transition functions, and not serialized. See `zobrist::hash_of_hand_not_resolved`.
+    /// ／状態遷移関数によって更新され、シリアライズされない。`zobrist::hash_of_hand_not_resolved` を参照。
+    #[serde(skip)]
+    pub(crate) zobrist_hash: u64,
+}
+
+impl<T: CetkaikRepresentation> HandNotResolved_<T> {
+    /// See `zobrist::hash_of_hand_not_resolved`.
+    /// ／`zobrist::hash_of_hand_not_resolved` を参照。
+    #[must_use]
+    pub const fn zobrist_hash(&self) -> u64 {
+        self.zobrist_hash
+    }
+
+    /// The hands that both sides' hop1zuo1 form. See `hands::HandTracker`.
+    /// ／両者の手駒がなす役。`hands::HandTracker` を参照。
+    #[must_use]
+    pub const fn hands(&self) -> super::hands::HandTracker {
+        self.hands
+    }
+}
+
+/// Also accepts the older format, which had `previous_a_side_hop1zuo1` and `previous_ia_side_hop1zuo1` (both hop1zuo1 before the move) instead of `captured_piece` and `dropped_piece`. The captured piece is then recovered as the piece that `whose_turn`'s hop1zuo1 has gained, and the dropped piece as the piece that it has lost. A missing `season_first_mover`, `moves_in_this_season` or `positions_in_this_season` is assumed to be `whose_turn`, 0 or empty. The hands and `zobrist_hash()`, which are not serialized, are calculated from the other fields.
+/// ／`captured_piece` と `dropped_piece` の代わりに `previous_a_side_hop1zuo1` と `previous_ia_side_hop1zuo1`（手を指す前の両者の手駒）を持っていた古い形式も受け付ける。その場合、取った駒は `whose_turn` の手駒が増えた分として、打った駒は減った分として復元する。`season_first_mover`・`moves_in_this_season`・`positions_in_this_season` がなければそれぞれ `whose_turn`・0・空であるとみなす。シリアライズされない役と `zobrist_hash()` は他のフィールドから計算する。
+impl<'de, T: CetkaikRepresentation> Deserialize<'de> for HandNotResolved_<T>
+where
+    T::AbsoluteField: Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(bound(deserialize = "T::AbsoluteField: Deserialize<'de>"))]
+        struct Stored<T: CetkaikRepresentation> {
+            f: T::AbsoluteField,
+            whose_turn: AbsoluteSide,
+            season: Season,
+            #[serde(default)]
+            season_first_mover: Option<AbsoluteSide>,
+            #[serde(default)]
+            moves_in_this_season: u32,
+            #[serde(default)]
+            positions_in_this_season: super::repetition::PositionHistory,
+            scores: Scores,
+            rate: Rate,
+            i_have_moved_tam_in_this_turn: bool,
+            #[serde(default)]
+            captured_piece: Option<cetkaik_fundamental::ColorAndProf>,
+            #[serde(default)]
+            dropped_piece: Option<cetkaik_fundamental::ColorAndProf>,
+            #[serde(default)]
+            previous_a_side_hop1zuo1: Option<Vec<cetkaik_fundamental::ColorAndProf>>,
+            #[serde(default)]
+            previous_ia_side_hop1zuo1: Option<Vec<cetkaik_fundamental::ColorAndProf>>,
+            kut2tam2_happened: bool,
+            tam2tysak2_raw_penalty: i32,
+            tam2tysak2_will_trigger_taxottymok: bool,
+        }
+
+        let stored = Stored::<T>::deserialize(deserializer)?;
+        let previous = match stored.whose_turn {
+            AbsoluteSide::ASide => stored.previous_a_side_hop1zuo1,
+            AbsoluteSide::IASide => stored.previous_ia_side_hop1zuo1,
+        };
+        let (captured_piece, dropped_piece) =
+            match (previous, stored.captured_piece, stored.dropped_piece) {
+                (Some(previous), None, None) => {
+                    let mut gained: Vec<_> = stored.f.hop1zuo1_of(stored.whose_turn).collect();
+                    let mut lost = vec![];
+                    for piece in previous {
+                        match gained.iter().position(|p| *p == piece) {
+                            Some(index) => {
+                                gained.swap_remove(index);
+                            }
+                            None => lost.push(piece),
+                        }
+                    }
+                    match (&gained[..], &lost[..]) {
+                        ([piece], []) => (Some(*piece), None),
+                        ([], [piece]) => (None, Some(*piece)),
+                        _ => (None, None),
+                    }
+                }
+                (_, captured_piece, dropped_piece) => (captured_piece, dropped_piece),
+            };
+        let hands = super::hands::HandTracker::of_field_before_move::<T>(
+            &stored.f,
+            stored.whose_turn,
+            captured_piece,
+            dropped_piece,
+        );
+        let mut state = Self {
+            hands,
+            f: stored.f,
+            whose_turn: stored.whose_turn,
+            season: stored.season,
+            season_first_mover: stored.season_first_mover.unwrap_or(stored.whose_turn),
+            moves_in_this_season: stored.moves_in_this_season,
+            positions_in_this_season: stored.positions_in_this_season,
+            scores: stored.scores,
+            rate: stored.rate,
+            i_have_moved_tam_in_this_turn: stored.i_have_moved_tam_in_this_turn,
+            captured_piece,
+            dropped_piece,
+            kut2tam2_happened: stored.kut2tam2_happened,
+            tam2tysak2_raw_penalty: stored.tam2tysak2_raw_penalty,
+            tam2tysak2_will_trigger_taxottymok: stored.tam2tysak2_will_trigger_taxottymok,
+            zobrist_hash: 0,
+        };
+        state.zobrist_hash = super::zobrist::hash_of_hand_not_resolved(&state);
+        Ok(state)
+    }
 }
 
 /// Converting `HandNotResolved` into `HandResolved` with `resolve` tells you whether a new hand was created. If so, the `HandExists` variant is taken; if not, the `NeitherTymokNorTaxot` is taken.
-/// ／`HandNotResolved` を `resolve` でこの型に変換することによって、『役は発生しなかったぞ』であるのか、それとも『役は発生しており、したがって【再行ならこの `GroundState` に至る】【終季ならこの `Probabilistic<state::GroundState>` に至る（どちらが先手になるかは鯖のみぞ知るので `Probabilistic`）】』のどちらであるかを知ることができる。撃皇が役を構成するかどうかによってここの処理は変わってくるので、
+/// ／`HandNotResolved` を `resolve` でこの型に変換することによって、『役は発生しなかったぞ』であるのか、それとも『役は発生しており、したがって【再行ならこの `GroundState` に至る】【終季ならこの `Probabilistic<state::GroundState>` に至る（`Config::who_goes_first` が無作為ならばどちらが先手になるかは鯖のみぞ知るので `Probabilistic`）】』のどちらであるかを知ることができる。撃皇が役を構成するかどうかによってここの処理は変わってくるので、
 /// `resolve` は `Config` を要求する。
 #[derive(Clone, Debug)]
 pub enum HandResolved_<T: CetkaikRepresentation> {
@@ -341,4 +910,441 @@ pub enum HandResolved_<T: CetkaikRepresentation> {
 
     /// 減点行為が役でないルールでは、役が成立して終季・再行の選択が発生せずに点が尽きることがありうる
     GameEndsWithoutTymokTaxot(super::score::Victor),
+
+    /// The season ended without any hand, e.g. because of `Config::move_limit`.
+    /// 手数制限などにより、役が成立せずに季節が終わった
+    SeasonEndsWithoutTymokTaxot(IfTaxot_<T>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{position_with_a_winning_hand, WINNING_CAPTURE};
+    use crate::probabilistic::Probabilistic;
+    use crate::{
+        apply_inf_after_step, apply_legal_inf_after_step, apply_legal_normal_move,
+        apply_normal_move, apply_pass, fixtures, initial_state, message, no_move_possible_at_all,
+        repetition, resolve, search, Config, DropRestriction,
+    };
+    use cetkaik_fundamental::AbsoluteSide::{ASide, IASide};
+
+    #[test]
+    fn test_get_candidates() {
+        use crate::message::AfterHalfAcceptance_;
+        use cetkaik_naive_representation::absolute;
+
+        use absolute::{
+            Column::Z,
+            Coord,
+            Row::{AI, E, I, O, U},
+        };
+        use cetkaik_fundamental::Profession;
+        use cetkaik_naive_representation::CetkaikNaive;
+        assert_eq!(
+            ExcitedState_::<CetkaikNaive> {
+                c: ExcitedStateWithoutCiurl_ {
+                    f: absolute::Field {
+                        a_side_hop1zuo1: vec![],
+                        ia_side_hop1zuo1: vec![],
+                        board: cetkaik_naive_representation::absolute::Board(
+                            std::collections::HashMap::from([
+                                (
+                                    absolute::Coord(AI, Z),
+                                    absolute::Piece::NonTam2Piece {
+                                        color: cetkaik_fundamental::Color::Huok2,
+                                        prof: Profession::Nuak1,
+                                        side: AbsoluteSide::IASide
+                                    }
+                                ),
+                                (
+                                    absolute::Coord(O, Z),
+                                    absolute::Piece::NonTam2Piece {
+                                        color: cetkaik_fundamental::Color::Huok2,
+                                        prof: Profession::Kauk2,
+                                        side: AbsoluteSide::IASide
+                                    }
+                                )
+                            ])
+                        )
+                    },
+                    whose_turn: AbsoluteSide::IASide,
+                    flying_piece_src: absolute::Coord(AI, Z),
+                    flying_piece_step: absolute::Coord(O, Z),
+                    flying_piece_planned_direction: absolute::Coord(I, Z),
+                    season: Season::Iei2,
+                    season_first_mover: AbsoluteSide::IASide,
+                    moves_in_this_season: 0,
+                    positions_in_this_season: crate::repetition::PositionHistory::new(),
+                    scores: crate::Scores::default(),
+                    rate: Rate::X1,
+                    hands: crate::hands::HandTracker::default(),
+                    zobrist_hash: 0,
+                },
+                ciurl: 3
+            }
+            .get_candidates(crate::Config::cerke_online_alpha()),
+            vec![
+                AfterHalfAcceptance_ { dest: None },
+                AfterHalfAcceptance_ {
+                    dest: Some(Coord(U, Z))
+                },
+                AfterHalfAcceptance_ {
+                    dest: Some(Coord(I, Z))
+                },
+                AfterHalfAcceptance_ {
+                    dest: Some(Coord(E, Z))
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_legal_moves_apply_like_validated_moves() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        use state::LegalMove;
+        fn hashes<S: Clone>(p: Probabilistic<S>, hash: impl Fn(&S) -> u64) -> Vec<(u64, f64)> {
+            p.into_outcomes()
+                .into_iter()
+                .map(|(s, probability)| (hash(&s), probability))
+                .collect()
+        }
+        for config in [Config::cerke_online_alpha(), Config::strict_y1_huap1()] {
+            let record = fixtures::random_game(config, 3);
+            for state in fixtures::sampled_positions::<CetkaikNaive>(&record) {
+                for legal in state.legal_moves(config) {
+                    match legal {
+                        LegalMove::NormalMove(legal) => assert_eq!(
+                            hashes(
+                                apply_normal_move(&state, legal.msg(), config).unwrap(),
+                                |s| s.zobrist_hash
+                            ),
+                            hashes(apply_legal_normal_move(legal), |s| s.zobrist_hash)
+                        ),
+                        LegalMove::InfAfterStep(legal) => assert_eq!(
+                            hashes(
+                                apply_inf_after_step(&state, legal.msg(), config).unwrap(),
+                                |s| s.c.zobrist_hash
+                            ),
+                            hashes(apply_legal_inf_after_step(legal), |s| s.c.zobrist_hash)
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_candidate_iterators_and_legal_move_set() {
+        use cetkaik_fundamental::{Color, ColorAndProf, Profession};
+        use cetkaik_naive_representation::absolute::{Board, Column, Coord, Field, Piece, Row};
+        use cetkaik_naive_representation::CetkaikNaive;
+        let restricted = Config::cerke_online_alpha().with_drop_restriction(DropRestriction {
+            no_drop_into_tam_hue: true,
+            no_drop_into_water: true,
+            no_drop_onto_opponent_home_rows: true,
+        });
+        for config in [Config::strict_y1_huap1(), restricted] {
+            let record = fixtures::random_game(config, 5);
+            for state in fixtures::positions::<CetkaikNaive>(&record) {
+                let (hop1zuo1_candidates, candidates) = state.get_candidates(config);
+                assert_eq!(
+                    state.has_any_legal_move(config),
+                    !hop1zuo1_candidates.is_empty() || !candidates.is_empty()
+                );
+                let set = state.legal_move_set(config);
+                for cand in hop1zuo1_candidates.into_iter().chain(candidates) {
+                    assert!(set.contains(cand));
+                    assert_eq!(set.legalize(cand).unwrap().pure_move(), cand);
+
+                    // Any `planned_direction` is accepted, as `apply_inf_after_step` accepts it
+                    // `apply_inf_after_step` が受け付けるので、どの `planned_direction` も受け付ける
+                    if let message::PureMove__::InfAfterStep(msg) = cand {
+                        let msg = message::InfAfterStep_ {
+                            planned_direction: msg.step,
+                            ..msg
+                        };
+                        assert!(apply_inf_after_step(&state, msg, config).is_ok());
+                        let cand = message::PureMove__::InfAfterStep(msg);
+                        assert_eq!(set.legalize(cand).unwrap().pure_move(), cand);
+                    }
+                }
+            }
+        }
+
+        // A player with a single piece in hop1zuo1 and nothing on the board
+        // 手駒が一つだけで盤上に何もないプレイヤー
+        let state = fixtures::ground_state(
+            &Field {
+                board: Board(std::collections::HashMap::from([(
+                    Coord(Row::E, Column::K),
+                    Piece::NonTam2Piece {
+                        color: Color::Huok2,
+                        prof: Profession::Kauk2,
+                        side: ASide,
+                    },
+                )])),
+                ia_side_hop1zuo1: vec![ColorAndProf {
+                    color: Color::Kok1,
+                    prof: Profession::Kauk2,
+                }],
+                a_side_hop1zuo1: vec![],
+            },
+            IASide,
+            Scores::new(),
+            Rate::X1,
+        );
+        let config = Config::cerke_online_alpha();
+        assert!(state.has_any_legal_move(config));
+        let set = state.legal_move_set(config);
+        assert_eq!(set.len(), 80);
+
+        // The set kept for `config` is not used for another `Config`
+        // `config` について保持された集合は別の `Config` には使われない
+        assert_eq!(
+            state.legal_move_set(restricted).len(),
+            state.candidates(restricted).count()
+        );
+        assert!(state.legal_move_set(restricted).len() < 80);
+        let illegal = message::PureMove__::NormalMove(message::NormalMove_::NonTamMoveSrcDst {
+            src: Coord(Row::E, Column::K),
+            dest: Coord(Row::I, Column::K),
+        });
+        assert!(!set.contains(illegal));
+        assert!(set.legalize(illegal).is_none());
+
+        // The same player without the piece in hop1zuo1
+        // 同じプレイヤーから手駒を除いたもの
+        let mut f = state.f.clone();
+        f.ia_side_hop1zuo1.clear();
+        let state = fixtures::ground_state(&f, IASide, Scores::new(), Rate::X1);
+        assert!(!state.has_any_legal_move(config));
+        assert!(state.legal_move_set(config).is_empty());
+        assert!(no_move_possible_at_all(&state, config).is_ok());
+    }
+
+    #[test]
+    fn test_hand_not_resolved_records_the_captured_piece() {
+        use cetkaik_fundamental::{Color, ColorAndProf, Profession};
+        use cetkaik_naive_representation::CetkaikNaive;
+        let config = Config::cerke_online_alpha();
+        let search::Decision::Move(message::PureMove__::NormalMove(msg)) = WINNING_CAPTURE else {
+            unreachable!()
+        };
+        let before = position_with_a_winning_hand();
+        let hand_not_resolved = apply_normal_move(&before, msg, config)
+            .unwrap()
+            .choose_when_no_ciurl();
+        assert_eq!(
+            hand_not_resolved.captured_piece,
+            Some(ColorAndProf {
+                color: Color::Kok1,
+                prof: Profession::Io
+            })
+        );
+
+        // the current format round-trips
+        // 現行の形式はそのまま読み書きできる
+        let mut json = serde_json::to_value(&hand_not_resolved).unwrap();
+        let round_tripped: state::HandNotResolved_<CetkaikNaive> =
+            serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            round_tripped.captured_piece,
+            hand_not_resolved.captured_piece
+        );
+
+        // the legacy format, with hop1zuo1 snapshots instead of the captured piece, is migrated
+        // 取った駒の代わりに手駒の写しを持つ古い形式は移行される
+        let object = json.as_object_mut().unwrap();
+        object.remove("captured_piece");
+        for (key, side) in [
+            ("previous_a_side_hop1zuo1", ASide),
+            ("previous_ia_side_hop1zuo1", IASide),
+        ] {
+            let previous: Vec<_> = before.f.hop1zuo1_of(side).collect();
+            object.insert(key.to_owned(), serde_json::to_value(previous).unwrap());
+        }
+        let migrated: state::HandNotResolved_<CetkaikNaive> = serde_json::from_value(json).unwrap();
+        assert_eq!(migrated.captured_piece, hand_not_resolved.captured_piece);
+        assert!(matches!(
+            resolve(&migrated, config),
+            state::HandResolved_::HandExists { .. }
+        ));
+    }
+
+    #[test]
+    fn test_states_deserialize_without_the_fields_added_later() {
+        use cetkaik_naive_representation::absolute::{Column, Coord, Row};
+        use cetkaik_naive_representation::CetkaikNaive;
+        use serde::de::DeserializeOwned;
+
+        /// Serializes `state` and deserializes it again without `fields`
+        fn without<S: serde::Serialize + DeserializeOwned>(state: &S, fields: &[&str]) -> S {
+            let mut json = serde_json::to_value(state).unwrap();
+            for field in fields {
+                assert!(json.as_object_mut().unwrap().remove(*field).is_some());
+            }
+            serde_json::from_value(json).unwrap()
+        }
+
+        let config = Config::cerke_online_alpha().with_pass(true, false);
+        let Probabilistic::WhoGoesFirst { ia_first, .. } = initial_state::<CetkaikNaive>() else {
+            unreachable!()
+        };
+        let state::HandResolved_::NeitherTymokNorTaxot(after_pass) =
+            apply_pass(&ia_first, message::Pass, config).unwrap()
+        else {
+            unreachable!()
+        };
+        let ground = without(
+            &after_pass,
+            &[
+                "season_first_mover",
+                "moves_in_this_season",
+                "positions_in_this_season",
+            ],
+        );
+        assert_eq!(ground.season_first_mover, ASide);
+        assert_eq!(ground.moves_in_this_season, 0);
+        assert_eq!(
+            ground.positions_in_this_season.iter().collect::<Vec<_>>(),
+            [repetition::position_key_of_state(&after_pass)]
+        );
+        assert_eq!(ground, after_pass);
+
+        let excited = apply_inf_after_step(
+            &ia_first,
+            message::InfAfterStep_ {
+                src: Coord(Row::AU, Column::L),
+                step: Coord(Row::AU, Column::K),
+                planned_direction: Coord(Row::AU, Column::L),
+            },
+            config,
+        )
+        .unwrap()
+        .choose()
+        .0
+        .c;
+        let excited = without(
+            &excited,
+            &[
+                "season_first_mover",
+                "moves_in_this_season",
+                "positions_in_this_season",
+            ],
+        );
+        assert_eq!(excited.season_first_mover, IASide);
+
+        let search::Decision::Move(message::PureMove__::NormalMove(msg)) = WINNING_CAPTURE else {
+            unreachable!()
+        };
+        let hand_not_resolved = apply_normal_move(&position_with_a_winning_hand(), msg, config)
+            .unwrap()
+            .choose_when_no_ciurl();
+        let hand_not_resolved = without(
+            &hand_not_resolved,
+            &[
+                "season_first_mover",
+                "moves_in_this_season",
+                "positions_in_this_season",
+            ],
+        );
+        assert_eq!(hand_not_resolved.season_first_mover, IASide);
+    }
+
+    #[test]
+    #[cfg(feature = "naive")]
+    fn test_states_deserialize_from_1_3_1() {
+        use crate::hands::HandTracker;
+        use crate::{apply_after_half_acceptance, zobrist};
+        use cetkaik_calculate_hand::PositiveHand;
+        use cetkaik_fundamental::{Color, ColorAndProf, Profession};
+        use cetkaik_naive_representation::CetkaikNaive;
+
+        // Serialized by version 1.3.1 in the middle of random games under `Config::cerke_online_alpha()`
+        // バージョン 1.3.1 で `Config::cerke_online_alpha()` のもとでのランダムな対局の途中にシリアライズしたもの
+        let config = Config::cerke_online_alpha();
+
+        let ground: state::GroundState_<CetkaikNaive> =
+            serde_json::from_str(include_str!("../testdata/1.3.1/ground_state.json")).unwrap();
+        assert_eq!(ground.season_first_mover, ground.whose_turn);
+        assert_eq!(ground.moves_in_this_season, 0);
+        assert_eq!(
+            ground.positions_in_this_season.iter().collect::<Vec<_>>(),
+            [repetition::position_key_of_state(&ground)]
+        );
+        assert_eq!(
+            ground.hands(),
+            HandTracker::of_field::<CetkaikNaive>(&ground.f)
+        );
+        assert_eq!(
+            ground.zobrist_hash(),
+            zobrist::hash_of_ground_state(&ground)
+        );
+        let (hop1zuo1_candidates, candidates) = ground.get_candidates(config);
+        assert!(!hop1zuo1_candidates.is_empty());
+        for cand in hop1zuo1_candidates.into_iter().chain(candidates) {
+            if let message::PureMove__::NormalMove(msg) = cand {
+                apply_normal_move(&ground, msg, config).unwrap();
+            }
+        }
+
+        let excited: state::ExcitedStateWithoutCiurl_<CetkaikNaive> = serde_json::from_str(
+            include_str!("../testdata/1.3.1/excited_state_without_ciurl.json"),
+        )
+        .unwrap();
+        assert_eq!(excited.season_first_mover, excited.whose_turn);
+        assert_eq!(excited.moves_in_this_season, 0);
+        assert!(excited.positions_in_this_season.is_empty());
+        assert_eq!(
+            excited.hands(),
+            HandTracker::of_field::<CetkaikNaive>(&excited.f)
+        );
+        assert_eq!(
+            excited.zobrist_hash(),
+            zobrist::hash_of_excited_state(&excited)
+        );
+        let excited = state::ExcitedState_ {
+            c: excited,
+            ciurl: 0,
+        };
+        apply_after_half_acceptance(
+            &excited,
+            message::AfterHalfAcceptance_ { dest: None },
+            config,
+        )
+        .unwrap();
+
+        // Version 1.3.1 found that capturing the red Maun1 forms Saup1 (Dau2 and Maun1)
+        // バージョン 1.3.1 は赤馬を取ると獣（虎と馬）ができると判定した
+        let hand_not_resolved: state::HandNotResolved_<CetkaikNaive> =
+            serde_json::from_str(include_str!("../testdata/1.3.1/hand_not_resolved.json")).unwrap();
+        assert_eq!(
+            hand_not_resolved.captured_piece,
+            Some(ColorAndProf {
+                color: Color::Kok1,
+                prof: Profession::Maun1,
+            })
+        );
+        assert_eq!(
+            hand_not_resolved.hands(),
+            HandTracker::of_field_before_move::<CetkaikNaive>(
+                &hand_not_resolved.f,
+                hand_not_resolved.whose_turn,
+                hand_not_resolved.captured_piece,
+                None,
+            )
+        );
+        assert_eq!(
+            hand_not_resolved.zobrist_hash(),
+            zobrist::hash_of_hand_not_resolved(&hand_not_resolved)
+        );
+        let state::HandResolved_::HandExists { if_tymok, .. } = resolve(&hand_not_resolved, config)
+        else {
+            panic!("a hand should have been formed")
+        };
+        assert!(if_tymok
+            .hands()
+            .of(hand_not_resolved.whose_turn)
+            .contains(PositiveHand::Saup1));
+    }
 }
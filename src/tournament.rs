@@ -0,0 +1,457 @@
+use super::player::{play_game_from_setup, Player};
+use super::{setup, Config, Victor};
+use cetkaik_fundamental::AbsoluteSide;
+use cetkaik_traits::CetkaikRepresentation;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+type NewPlayer<T> = dyn Fn() -> Box<dyn Player<T>> + Send + Sync;
+
+/// A participant of a tournament. Since games are played in parallel, a fresh player is created for every game.
+/// ／大会の参加者。対局は並列に行われるので、対局ごとに新しいプレイヤーを作る。
+pub struct Entrant<T: CetkaikRepresentation> {
+    pub name: String,
+    new_player: Box<NewPlayer<T>>,
+}
+
+impl<T: CetkaikRepresentation> Entrant<T> {
+    pub fn new(
+        name: impl Into<String>,
+        new_player: impl Fn() -> Box<dyn Player<T>> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            new_player: Box::new(new_player),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// Every pair of entrants plays.
+    /// ／全ての参加者の組が対局する。
+    RoundRobin,
+
+    /// The entrant at this index plays against every other entrant, who do not play each other.
+    /// ／この番号の参加者が他の全員と対局する。他の参加者同士は対局しない。
+    Gauntlet(usize),
+}
+
+/// The result of a game. `ia` and `a` are indices into the entrants.
+/// ／一局の結果。`ia` と `a` は参加者の番号。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub struct GameResult {
+    pub ia: usize,
+    pub a: usize,
+    pub seed: u64,
+    pub victor: Victor,
+    pub final_ia_score: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+
+    /// The average of (own final score − opponent's final score).
+    /// ／（自分の最終得点 − 相手の最終得点）の平均。
+    pub mean_margin: f64,
+
+    /// Elo rating, normalized so that the average over the entrants is 0.
+    /// ／参加者の平均が0になるよう正規化した Elo レーティング。
+    pub elo: f64,
+
+    /// Half the width of the 95% confidence interval of `elo`.
+    /// ／`elo` の95%信頼区間の幅の半分。
+    pub elo_error: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct TournamentResult {
+    /// In the order of the schedule, regardless of the order in which the games finished.
+    /// ／対局が終わった順によらず、日程の順。
+    pub games: Vec<GameResult>,
+
+    /// In the same order as the entrants.
+    /// ／参加者と同じ順。
+    pub standings: Vec<Standing>,
+}
+
+/// Runs games between `Player`s in parallel and ranks them.
+/// Each pairing plays `games_per_pairing` games, alternating who takes IA side. Two consecutive games of a pairing share the seed, so that both entrants face the same casts of sticks from each side.
+/// ／`Player` 同士の対局を並列に行い、順位をつける。
+/// 各組は `games_per_pairing` 局を指し、どちらが IA 側を持つかを交互に入れ替える。ある組の連続する二局はシードを共有するので、両参加者はそれぞれの側で同じ投げ棒の結果に臨む。
+///
+/// ```
+/// use cetkaik_full_state_transition::player::{GreedyCapturePlayer, RandomPlayer};
+/// use cetkaik_full_state_transition::tournament::{Entrant, Tournament};
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let config = Config::cerke_online_alpha().with_move_limit(Some(MoveLimit {
+///     moves: 20,
+///     outcome: MoveLimitOutcome::GameEndsInADraw,
+/// }));
+/// let result = Tournament::<CetkaikNaive>::new(
+///     config,
+///     vec![
+///         Entrant::new("random", || Box::new(RandomPlayer)),
+///         Entrant::new("greedy", || Box::new(GreedyCapturePlayer)),
+///     ],
+/// )
+/// .with_games_per_pairing(2)
+/// .with_threads(2)
+/// .run()
+/// .unwrap();
+/// assert_eq!(result.games.len(), 2);
+/// assert_eq!(result.standings[0].wins, result.standings[1].losses);
+/// assert!((result.standings[0].elo + result.standings[1].elo).abs() < 1e-6);
+/// ```
+pub struct Tournament<T: CetkaikRepresentation> {
+    config: Config,
+    entrants: Vec<Entrant<T>>,
+    setup: setup::Setup<T>,
+    format: Format,
+    games_per_pairing: u32,
+    seed: u64,
+    threads: usize,
+    dump_dir: Option<PathBuf>,
+}
+
+impl<T: CetkaikRepresentation + Clone> Tournament<T> {
+    /// By default: a round robin with 2 games per pairing on a single thread, without dumping the games.
+    /// ／既定値：各組2局の総当たりを単一スレッドで行い、対局は書き出さない。
+    #[must_use]
+    pub fn new(config: Config, entrants: Vec<Entrant<T>>) -> Self {
+        Self {
+            config,
+            entrants,
+            setup: setup::Setup::yhuap_initial(),
+            format: Format::RoundRobin,
+            games_per_pairing: 2,
+            seed: 0,
+            threads: 1,
+            dump_dir: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_format(self, format: Format) -> Self {
+        Self { format, ..self }
+    }
+
+    #[must_use]
+    pub fn with_games_per_pairing(self, games_per_pairing: u32) -> Self {
+        Self {
+            games_per_pairing,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    #[must_use]
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_setup(self, setup: setup::Setup<T>) -> Self {
+        Self { setup, ..self }
+    }
+
+    /// Writes every game into this directory as `game-00000.txt`, `game-00001.txt` and so on, numbered in the order of the schedule. See `GameRecord`'s `Display` for the format.
+    /// ／全ての対局をこのディレクトリに `game-00000.txt`、`game-00001.txt` などとして書き出す。番号は日程の順。形式は `GameRecord` の `Display` を参照。
+    #[must_use]
+    pub fn with_dump_dir(self, dump_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dump_dir: Some(dump_dir.into()),
+            ..self
+        }
+    }
+
+    /// The games in the order of the schedule, as `(ia, a, seed)`.
+    /// ／日程順の対局を `(ia, a, seed)` として返す。
+    fn schedule(&self) -> Vec<(usize, usize, u64)> {
+        let n = self.entrants.len();
+        let pairings: Vec<(usize, usize)> = match self.format {
+            Format::RoundRobin => (0..n)
+                .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                .collect(),
+            Format::Gauntlet(challenger) => (0..n)
+                .filter(|&j| j != challenger)
+                .map(|j| (challenger, j))
+                .collect(),
+        };
+        let mut schedule = vec![];
+        for (p, (i, j)) in pairings.into_iter().enumerate() {
+            for k in 0..u64::from(self.games_per_pairing) {
+                let seed = self.seed + p as u64 * u64::from(self.games_per_pairing) + k / 2;
+                schedule.push(if k % 2 == 0 {
+                    (i, j, seed)
+                } else {
+                    (j, i, seed)
+                });
+            }
+        }
+        schedule
+    }
+
+    /// # Errors
+    /// Fails with `std::io::ErrorKind::InvalidInput` if the challenger of `Format::Gauntlet` is not an index into the entrants, and fails if a game cannot be dumped.
+    /// ／`Format::Gauntlet` の挑戦者が参加者の番号でなければ `std::io::ErrorKind::InvalidInput` で失敗し、対局を書き出せなければ失敗する。
+    /// # Panics
+    /// Panics if a game panics.
+    pub fn run(&self) -> std::io::Result<TournamentResult>
+    where
+        T::AbsoluteField: Send + Sync,
+        T::AbsoluteCoord: std::fmt::Display,
+    {
+        if let Format::Gauntlet(challenger) = self.format {
+            if challenger >= self.entrants.len() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "The challenger of `Format::Gauntlet` is not an index into the entrants",
+                ));
+            }
+        }
+        if let Some(dir) = &self.dump_dir {
+            std::fs::create_dir_all(dir)?;
+        }
+        let schedule = self.schedule();
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<GameResult>>> = Mutex::new(vec![None; schedule.len()]);
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.threads)
+                .map(|_| {
+                    scope.spawn(|| -> std::io::Result<()> {
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(&(ia, a, seed)) = schedule.get(index) else {
+                                return Ok(());
+                            };
+                            let (record, victor) = play_game_from_setup(
+                                &mut *(self.entrants[ia].new_player)(),
+                                &mut *(self.entrants[a].new_player)(),
+                                self.config,
+                                &self.setup,
+                                seed,
+                            );
+                            if let Some(dir) = &self.dump_dir {
+                                std::fs::write(
+                                    dir.join(format!("game-{index:05}.txt")),
+                                    format!(
+                                        "# {} (IA) vs {} (A)\n{record}",
+                                        self.entrants[ia].name, self.entrants[a].name
+                                    ),
+                                )?;
+                            }
+                            results.lock().expect("a thread panicked")[index] = Some(GameResult {
+                                ia,
+                                a,
+                                seed,
+                                victor,
+                                final_ia_score: record.final_ia_score,
+                            });
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().expect("a game panicked"))
+        })?;
+
+        let games: Vec<GameResult> = results
+            .into_inner()
+            .expect("a thread panicked")
+            .into_iter()
+            .map(|result| result.expect("every game has been played"))
+            .collect();
+        let standings = standings(
+            &self
+                .entrants
+                .iter()
+                .map(|e| e.name.clone())
+                .collect::<Vec<_>>(),
+            &games,
+        );
+        Ok(TournamentResult { games, standings })
+    }
+}
+
+/// Computes the Elo ratings by fitting the Bradley–Terry model with the MM algorithm, counting a draw as half a win. Every pairing that played gets one extra virtual draw, so that an entrant who won or lost every game still gets a finite rating.
+/// ／引き分けを半勝として、MM アルゴリズムで Bradley–Terry モデルを当てはめて Elo レーティングを計算する。対局した全ての組に仮想の引き分けを一つ加えるので、全勝や全敗の参加者にも有限のレーティングがつく。
+#[allow(clippy::cast_precision_loss)] /* the number of games never gets anywhere near 2^52 */
+fn standings(names: &[String], games: &[GameResult]) -> Vec<Standing> {
+    let n = names.len();
+    let mut standings: Vec<Standing> = names
+        .iter()
+        .map(|name| Standing {
+            name: name.clone(),
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            mean_margin: 0.0,
+            elo: 0.0,
+            elo_error: 0.0,
+        })
+        .collect();
+
+    // points[i][j]: how many points i has scored against j. played[i][j]: how many games i and j have played.
+    // points[i][j]: i が j から挙げた勝ち点。played[i][j]: i と j の対局数。
+    let mut points = vec![vec![0.0; n]; n];
+    let mut played = vec![vec![0.0; n]; n];
+    for game in games {
+        let margin = f64::from(2 * game.final_ia_score - 40);
+        standings[game.ia].mean_margin += margin;
+        standings[game.a].mean_margin -= margin;
+        played[game.ia][game.a] += 1.0;
+        played[game.a][game.ia] += 1.0;
+        match game.victor.0 {
+            Some(AbsoluteSide::IASide) => {
+                standings[game.ia].wins += 1;
+                standings[game.a].losses += 1;
+                points[game.ia][game.a] += 1.0;
+            }
+            Some(AbsoluteSide::ASide) => {
+                standings[game.a].wins += 1;
+                standings[game.ia].losses += 1;
+                points[game.a][game.ia] += 1.0;
+            }
+            None => {
+                standings[game.ia].draws += 1;
+                standings[game.a].draws += 1;
+                points[game.ia][game.a] += 0.5;
+                points[game.a][game.ia] += 0.5;
+            }
+        }
+    }
+    for i in 0..n {
+        for j in 0..n {
+            if played[i][j] > 0.0 {
+                played[i][j] += 1.0;
+                points[i][j] += 0.5;
+            }
+        }
+    }
+
+    let total_points: Vec<f64> = points.iter().map(|row| row.iter().sum()).collect();
+    let total_played: Vec<f64> = played.iter().map(|row| row.iter().sum()).collect();
+    let mut gamma = vec![1.0; n];
+    for _ in 0..1000 {
+        let mut next: Vec<f64> = (0..n)
+            .map(|i| {
+                let denominator: f64 = (0..n)
+                    .filter(|&j| played[i][j] > 0.0)
+                    .map(|j| played[i][j] / (gamma[i] + gamma[j]))
+                    .sum();
+                if denominator > 0.0 {
+                    total_points[i] / denominator
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        let log_mean = next.iter().map(|g: &f64| g.ln()).sum::<f64>() / n as f64;
+        for g in &mut next {
+            *g /= log_mean.exp();
+        }
+        let converged = next.iter().zip(&gamma).all(|(a, b)| (a - b).abs() < 1e-12);
+        gamma = next;
+        if converged {
+            break;
+        }
+    }
+
+    for (i, standing) in standings.iter_mut().enumerate() {
+        let games_played = f64::from(standing.wins + standing.draws + standing.losses);
+        standing.mean_margin /= games_played.max(1.0);
+        standing.elo = 400.0 * gamma[i].log10();
+
+        // The standard error of the score fraction, propagated to the rating by the delta method
+        // 勝率の標準誤差をデルタ法でレーティングに伝播させる
+        let s = total_points[i] / total_played[i].max(1.0);
+        standing.elo_error = if total_played[i] > 0.0 {
+            1.96 * 400.0 / (std::f64::consts::LN_10 * (total_played[i] * s * (1.0 - s)).sqrt())
+        } else {
+            f64::INFINITY
+        };
+    }
+    standings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{player, MoveLimit, MoveLimitOutcome};
+
+    #[test]
+    fn test_tournament_gauntlet() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        let config = Config::cerke_online_alpha().with_move_limit(Some(MoveLimit {
+            moves: 10,
+            outcome: MoveLimitOutcome::GameEndsInADraw,
+        }));
+        let dump_dir =
+            std::env::temp_dir().join(format!("cetkaik-gauntlet-{}", std::process::id()));
+        let result = Tournament::<CetkaikNaive>::new(
+            config,
+            vec![
+                Entrant::new("random", || Box::new(player::RandomPlayer)),
+                Entrant::new("greedy", || Box::new(player::GreedyCapturePlayer)),
+                Entrant::new("hand", || Box::new(player::HandGreedyPlayer)),
+            ],
+        )
+        .with_format(Format::Gauntlet(1))
+        .with_games_per_pairing(2)
+        .with_threads(3)
+        .with_dump_dir(&dump_dir)
+        .run()
+        .unwrap();
+
+        // the challenger plays both sides against each of the others with the same sticks
+        // 挑戦者は他の参加者それぞれと同じ投げ棒で両方の側を持って対局する
+        let pairs: Vec<_> = result.games.iter().map(|g| (g.ia, g.a)).collect();
+        assert_eq!(pairs, vec![(1, 0), (0, 1), (1, 2), (2, 1)]);
+        assert_eq!(result.games[0].seed, result.games[1].seed);
+        assert_ne!(result.games[1].seed, result.games[2].seed);
+
+        let standings = &result.standings;
+        assert_eq!(
+            standings[1].wins + standings[1].draws + standings[1].losses,
+            4
+        );
+        assert_eq!(
+            standings[0].wins + standings[0].draws + standings[0].losses,
+            2
+        );
+        assert!(standings.iter().map(|s| s.elo).sum::<f64>().abs() < 1e-6);
+        assert!(standings.iter().all(|s| s.elo_error.is_finite()));
+
+        let dumped = std::fs::read_to_string(dump_dir.join("game-00001.txt")).unwrap();
+        assert!(dumped.starts_with("# random (IA) vs greedy (A)\n"));
+        std::fs::remove_dir_all(&dump_dir).unwrap();
+
+        // A challenger out of range is an error rather than a panic
+        // 範囲外の挑戦者はパニックではなくエラーになる
+        let error = Tournament::<CetkaikNaive>::new(
+            config,
+            vec![Entrant::new("random", || Box::new(player::RandomPlayer))],
+        )
+        .with_format(Format::Gauntlet(1))
+        .run()
+        .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,379 @@
+use super::message::PureMove__;
+use super::state::GroundState_;
+use super::{Config, FirstMover, RepetitionConsequence};
+use cetkaik_fundamental::AbsoluteSide;
+use cetkaik_traits::CetkaikRepresentation;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+/// How `Entry::expected_value` relates to the true value of the position.
+/// ／`Entry::expected_value` が局面の真の値とどう関係するか。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum Bound {
+    Exact,
+
+    /// The true value is at least `expected_value` (the search failed high).
+    /// ／真の値は `expected_value` 以上（探索が上に外れた）。
+    Lower,
+
+    /// The true value is at most `expected_value` (the search failed low).
+    /// ／真の値は `expected_value` 以下（探索が下に外れた）。
+    Upper,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct Entry<Coord> {
+    /// The full key (see `key_of`) of the position, so that a position that merely shares the slot is never mistaken for it.
+    /// ／局面の鍵（`key_of` を参照）全体。スロットを共有するだけの局面と取り違えないため。
+    pub key: u64,
+    pub depth: u32,
+    pub bound: Bound,
+
+    /// Seen from IA side, as everywhere in `search`.
+    /// ／`search` の他の箇所と同じく IA 側から見た値。
+    pub expected_value: f64,
+
+    /// `None` if the best decision was not a move, e.g. a pass.
+    /// ／最善の決断が手でない（パスなど）ならば `None`。
+    pub best_move: Option<PureMove__<Coord>>,
+
+    /// The value of `TranspositionTable::generation` when the entry was stored. Set by `store`.
+    /// ／格納されたときの `TranspositionTable::generation` の値。`store` が設定する。
+    pub generation: u32,
+}
+
+/// The key under which `search::Searcher` stores `state`. Two states get the same key only if they are worth the same under `config`: besides `zobrist_hash()`, the key covers the part of the history of the season that `config` looks at, namely `moves_in_this_season` under `Config::move_limit`, the positions so far (see `repetition::PositionHistory::digest`) under a `Config::repetition` that is not `RepetitionConsequence::Allowed`, and `season_first_mover` under `FirstMover::Alternate`.
+/// ／`search::Searcher` が `state` を格納する鍵。`config` のもとで同じ価値を持つ状態にのみ同じ鍵が与えられる。`zobrist_hash()` に加え、`config` が見る季節の履歴を含む。すなわち、`Config::move_limit` があれば `moves_in_this_season` を、`Config::repetition` が `RepetitionConsequence::Allowed` でなければこれまでの局面（`repetition::PositionHistory::digest` を参照）を、`FirstMover::Alternate` ならば `season_first_mover` を含む。
+///
+/// ```
+/// use cetkaik_full_state_transition::transposition::key_of;
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let state = initial_state::<CetkaikNaive>().choose().0;
+/// let passing = Config::cerke_online_alpha().with_pass(true, false);
+/// let pass = |state| match apply_pass(state, message::Pass, passing).unwrap() {
+///     state::HandResolved_::NeitherTymokNorTaxot(next) => next,
+///     _ => unreachable!(),
+/// };
+/// let later = pass(&pass(&state));
+/// assert_eq!(later.zobrist_hash(), state.zobrist_hash());
+///
+/// let config = Config::cerke_online_alpha();
+/// assert_eq!(key_of(&state, config), state.zobrist_hash());
+/// assert_eq!(key_of(&later, config), key_of(&state, config));
+///
+/// let config = config.with_move_limit(Some(MoveLimit {
+///     moves: 100,
+///     outcome: MoveLimitOutcome::SeasonEndsWithoutScoreChange,
+/// }));
+/// assert_ne!(key_of(&later, config), key_of(&state, config));
+/// ```
+#[must_use]
+pub fn key_of<T: CetkaikRepresentation>(state: &GroundState_<T>, config: Config) -> u64 {
+    let mut key = state.zobrist_hash();
+    if config.move_limit.is_some() {
+        key = super::zobrist::mix(key ^ u64::from(state.moves_in_this_season));
+    }
+    if config.repetition.consequence != RepetitionConsequence::Allowed {
+        key = super::zobrist::mix(key ^ state.positions_in_this_season.digest());
+    }
+    if config.who_goes_first == FirstMover::Alternate {
+        key = super::zobrist::mix(key ^ u64::from(state.season_first_mover == AbsoluteSide::ASide));
+    }
+    key
+}
+
+/// Decides whether a new entry may overwrite an entry for a different position in the same slot. An entry for the same position is always overwritten.
+/// ／同じスロットにある別の局面のエントリを新しいエントリが上書きしてよいかを決める。同じ局面のエントリは常に上書きされる。
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum Replacement {
+    Always,
+
+    /// Overwrites only an entry searched to the same depth or shallower.
+    /// ／同じ深さかそれより浅く探索されたエントリのみを上書きする。
+    DepthPreferred,
+
+    /// Same as `DepthPreferred`, except that an entry stored before the latest `new_generation` is always overwritten.
+    /// ／`DepthPreferred` と同様だが、最新の `new_generation` より前に格納されたエントリは常に上書きする。
+    DepthPreferredInGeneration,
+}
+
+/// The slots are split into shards, each behind its own lock, so that threads storing to different shards never wait for each other.
+/// ／スロットをシャードに分け、それぞれに別々のロックを掛ける。これにより、異なるシャードに格納するスレッド同士は互いを待たない。
+const SHARDS: usize = 64;
+
+/// A fixed-size table from a key (see `key_of`) to the result of searching that position, which can be shared across threads (e.g. through an `Arc`).
+/// The table only makes sense for searches with the same `Config`, setup and evaluator.
+/// ／鍵（`key_of` を参照）からその局面を探索した結果への固定サイズの表。（`Arc` などを通じて）スレッド間で共有できる。
+/// 同じ `Config`・開始局面・評価関数による探索の間でしか意味をなさない。
+///
+/// ```
+/// use cetkaik_full_state_transition::transposition::{Bound, Entry, Replacement, TranspositionTable};
+///
+/// let table = TranspositionTable::<()>::new(1000).with_replacement(Replacement::DepthPreferred);
+/// let entry = |key, depth| Entry {
+///     key,
+///     depth,
+///     bound: Bound::Exact,
+///     expected_value: 0.5,
+///     best_move: None,
+///     generation: 0,
+/// };
+/// assert!(table.store(entry(42, 3)));
+/// assert_eq!(table.probe(42).unwrap().depth, 3);
+///
+/// // Another position in the same slot
+/// // 同じスロットにある別の局面
+/// let other = 42 + table.capacity() as u64;
+/// assert!(table.probe(other).is_none());
+/// assert!(!table.store(entry(other, 2)));
+/// assert!(table.store(entry(other, 4)));
+/// assert!(table.probe(42).is_none());
+/// ```
+pub struct TranspositionTable<Coord> {
+    shards: Vec<Mutex<Vec<Option<Entry<Coord>>>>>,
+    slots_per_shard: usize,
+    replacement: Replacement,
+    generation: AtomicU32,
+}
+
+impl<Coord: Copy> TranspositionTable<Coord> {
+    /// Allocates at least `capacity` slots (see `capacity`), with `Replacement::DepthPreferredInGeneration`.
+    /// ／少なくとも `capacity` 個のスロットを確保する（`capacity` を参照）。置換方針は `Replacement::DepthPreferredInGeneration`。
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let slots_per_shard = capacity.div_ceil(SHARDS).max(1);
+        Self {
+            shards: (0..SHARDS)
+                .map(|_| Mutex::new(vec![None; slots_per_shard]))
+                .collect(),
+            slots_per_shard,
+            replacement: Replacement::DepthPreferredInGeneration,
+            generation: AtomicU32::new(0),
+        }
+    }
+
+    #[must_use]
+    pub fn with_replacement(self, replacement: Replacement) -> Self {
+        Self {
+            replacement,
+            ..self
+        }
+    }
+
+    /// The number of slots. Two keys share a slot exactly when they are congruent modulo this number.
+    /// ／スロットの数。二つの鍵はこの数を法として合同であるときに限り同じスロットを共有する。
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.shards.len() * self.slots_per_shard
+    }
+
+    fn slot(&self, key: u64) -> (&Mutex<Vec<Option<Entry<Coord>>>>, usize) {
+        // `capacity` fits in `u64`, and the remainder is smaller than `capacity`
+        // `capacity` は `u64` に収まり、余りは `capacity` より小さい
+        #[allow(clippy::cast_possible_truncation)]
+        let index = (key % self.capacity() as u64) as usize;
+        (&self.shards[index % SHARDS], index / SHARDS)
+    }
+
+    /// # Panics
+    /// Panics if another thread has panicked while accessing the table.
+    #[must_use]
+    pub fn probe(&self, key: u64) -> Option<Entry<Coord>> {
+        let (shard, index) = self.slot(key);
+        let shard = shard.lock().expect("a thread panicked");
+        shard[index].filter(|entry| entry.key == key)
+    }
+
+    /// Stores `entry` unless the replacement policy keeps the entry already in the slot. Returns whether `entry` was stored. `entry.generation` is overwritten with the current generation.
+    /// ／置換方針がスロットに既にあるエントリを残すのでない限り、`entry` を格納する。`entry` が格納されたかどうかを返す。`entry.generation` は現在の世代で上書きされる。
+    ///
+    /// # Panics
+    /// Panics if another thread has panicked while accessing the table.
+    pub fn store(&self, entry: Entry<Coord>) -> bool {
+        let generation = self.generation();
+        let (shard, index) = self.slot(entry.key);
+        let mut shard = shard.lock().expect("a thread panicked");
+        let replace = shard[index].map_or(true, |old| {
+            old.key == entry.key
+                || match self.replacement {
+                    Replacement::Always => true,
+                    Replacement::DepthPreferred => entry.depth >= old.depth,
+                    Replacement::DepthPreferredInGeneration => {
+                        old.generation != generation || entry.depth >= old.depth
+                    }
+                }
+        });
+        if replace {
+            shard[index] = Some(Entry {
+                generation,
+                ..entry
+            });
+        }
+        replace
+    }
+
+    #[must_use]
+    pub fn generation(&self) -> u32 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Marks the entries stored so far as old. Call this before starting to search a new position.
+    /// ／これまでに格納されたエントリを古いものとする。新しい局面の探索を始める前に呼ぶこと。
+    pub fn new_generation(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// # Panics
+    /// Panics if another thread has panicked while accessing the table.
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            shard.lock().expect("a thread panicked").fill(None);
+        }
+    }
+
+    /// The number of occupied slots.
+    /// ／埋まっているスロットの数。
+    ///
+    /// # Panics
+    /// Panics if another thread has panicked while accessing the table.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                shard
+                    .lock()
+                    .expect("a thread panicked")
+                    .iter()
+                    .filter(|slot| slot.is_some())
+                    .count()
+            })
+            .sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{position_with_a_winning_hand, WINNING_CAPTURE};
+    use crate::search;
+
+    #[test]
+    fn test_transposition_table() {
+        use std::sync::Arc;
+        let entry = |key, depth| Entry::<()> {
+            key,
+            depth,
+            bound: Bound::Exact,
+            expected_value: f64::from(depth),
+            best_move: None,
+            generation: 0,
+        };
+
+        // Concurrent insertion, both of distinct keys and of the same keys
+        // 異なる鍵と同じ鍵の両方の並行挿入
+        let table = Arc::new(TranspositionTable::new(4096).with_replacement(Replacement::Always));
+        std::thread::scope(|scope| {
+            for thread in 0..8_u32 {
+                let table = Arc::clone(&table);
+                scope.spawn(move || {
+                    for i in 0..256 {
+                        assert!(table.store(entry(u64::from(thread) * 256 + i, thread)));
+                        assert!(table.store(entry(8 * 256 + i, thread)));
+                    }
+                });
+            }
+        });
+        assert_eq!(table.len(), 8 * 256 + 256);
+        for thread in 0..8_u32 {
+            for i in 0..256 {
+                assert_eq!(
+                    table.probe(u64::from(thread) * 256 + i).unwrap().depth,
+                    thread
+                );
+                let shared = table.probe(8 * 256 + i).unwrap();
+                assert!((shared.expected_value - f64::from(shared.depth)).abs() < f64::EPSILON);
+            }
+        }
+
+        // A key that shares the slot is never mistaken for the stored one
+        // スロットを共有する鍵が格納された鍵と取り違えられることはない
+        let capacity = table.capacity() as u64;
+        for replacement in [
+            Replacement::Always,
+            Replacement::DepthPreferred,
+            Replacement::DepthPreferredInGeneration,
+        ] {
+            let table = TranspositionTable::new(100).with_replacement(replacement);
+            let capacity = table.capacity() as u64;
+            assert!(table.store(entry(7, 5)));
+            assert!(table.probe(7 + capacity).is_none());
+            assert_eq!(
+                table.store(entry(7 + capacity, 1)),
+                replacement == Replacement::Always
+            );
+            table.new_generation();
+            assert_eq!(
+                table.store(entry(7 + 2 * capacity, 1)),
+                replacement != Replacement::DepthPreferred
+            );
+            assert_eq!(table.len(), 1);
+
+            // The same position is always overwritten, even by a shallower entry
+            // 同じ局面は浅いエントリによっても常に上書きされる
+            let occupant = if replacement == Replacement::DepthPreferred {
+                7
+            } else {
+                7 + 2 * capacity
+            };
+            assert!(table.store(entry(occupant, 0)));
+            assert_eq!(table.probe(occupant).unwrap().depth, 0);
+            table.clear();
+            assert!(table.is_empty());
+        }
+        assert_eq!(capacity, 4096);
+    }
+
+    #[test]
+    fn test_search_with_shared_transposition_table() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        use std::sync::Arc;
+        let table = Arc::new(TranspositionTable::new(1 << 16));
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    let table = Arc::clone(&table);
+                    scope.spawn(move || {
+                        search::Searcher::new(
+                            Config::cerke_online_alpha(),
+                            search::score_difference::<CetkaikNaive>,
+                        )
+                        .with_max_depth(2)
+                        .with_transposition_table(table)
+                        .search(&position_with_a_winning_hand())
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for result in results {
+            assert_eq!(result.best_move, Some(WINNING_CAPTURE));
+            assert!((result.expected_value - 1.0).abs() < f64::EPSILON);
+        }
+        let root = table
+            .probe(key_of(
+                &position_with_a_winning_hand(),
+                Config::cerke_online_alpha(),
+            ))
+            .unwrap();
+        assert_eq!(root.depth, 2);
+        assert_eq!(root.bound, Bound::Exact);
+    }
+}
@@ -0,0 +1,441 @@
+use super::player::GameRecord;
+use super::{state, FirstMover, Rate, Scores, Season, Victor};
+use cetkaik_fundamental::AbsoluteSide;
+use cetkaik_traits::CetkaikRepresentation;
+
+/// How likely each side is to win the game, and the expected final score.
+/// ／それぞれの側がゲームに勝つ確率と、最終得点の期待値。
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct MatchEstimate {
+    pub ia_wins: f64,
+    pub a_wins: f64,
+    pub draw: f64,
+    pub expected_final_ia_score: f64,
+}
+
+impl MatchEstimate {
+    /// The probability that `side` wins, counting a draw as half a win.
+    /// ／`side` が勝つ確率。引き分けは半勝とする。
+    #[must_use]
+    pub fn win_probability(self, side: AbsoluteSide) -> f64 {
+        match side {
+            AbsoluteSide::IASide => self.ia_wins + self.draw / 2.0,
+            AbsoluteSide::ASide => self.a_wins + self.draw / 2.0,
+        }
+    }
+
+    fn game_over(ia_score: i32) -> Self {
+        let victor = Scores::from_ia_score(ia_score).map_or_else(
+            |_| {
+                Victor(Some(if ia_score >= 40 {
+                    AbsoluteSide::IASide
+                } else {
+                    AbsoluteSide::ASide
+                }))
+            },
+            Scores::which_side_is_winning,
+        );
+        Self {
+            ia_wins: if victor.0 == Some(AbsoluteSide::IASide) {
+                1.0
+            } else {
+                0.0
+            },
+            a_wins: if victor.0 == Some(AbsoluteSide::ASide) {
+                1.0
+            } else {
+                0.0
+            },
+            draw: if victor.0.is_none() { 1.0 } else { 0.0 },
+            expected_final_ia_score: f64::from(ia_score.clamp(0, 40)),
+        }
+    }
+
+    const ZERO: Self = Self {
+        ia_wins: 0.0,
+        a_wins: 0.0,
+        draw: 0.0,
+        expected_final_ia_score: 0.0,
+    };
+
+    fn add_scaled(self, other: Self, probability: f64) -> Self {
+        Self {
+            ia_wins: probability.mul_add(other.ia_wins, self.ia_wins),
+            a_wins: probability.mul_add(other.a_wins, self.a_wins),
+            draw: probability.mul_add(other.draw, self.draw),
+            expected_final_ia_score: probability
+                .mul_add(other.expected_final_ia_score, self.expected_final_ia_score),
+        }
+    }
+}
+
+/// A model of the game that looks only at the scores. Each remaining season moves `value * rate` points to the player who moves first in it, where `value` is drawn from `season_outcomes` (negative if the season is lost) and `rate` is 1 except in the current season. The first mover of each later season is decided by `who_goes_first`.
+/// A game ends as soon as a player's score reaches 0 or 40, and otherwise after the last season, by `Scores::which_side_is_winning`.
+/// ／得点のみを見るゲームのモデル。残りの各季節はその先手に `value * rate` 点をもたらす。`value` は `season_outcomes` から引かれ（その季節に負ければ負）、`rate` は今の季節を除いて1。以降の季節の先手は `who_goes_first` によって決まる。
+/// ゲームは片方の得点が0か40に達した時点で終わり、そうでなければ最後の季節の後に `Scores::which_side_is_winning` によって終わる。
+///
+/// ```
+/// use cetkaik_full_state_transition::win_probability::MarkovModel;
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_fundamental::AbsoluteSide::{ASide, IASide};
+///
+/// let model = MarkovModel::default();
+/// let even = model.estimate(Scores::new(), Rate::X1, Season::Iei2, IASide);
+/// assert!((even.win_probability(IASide) - 0.5).abs() < 1e-9);
+///
+/// // Ten points ahead going into the last season
+/// // 最後の季節を前に10点の差
+/// let ahead = model.estimate(Scores::from_ia_score(25).unwrap(), Rate::X1, Season::Iat1, ASide);
+/// assert!(ahead.win_probability(IASide) > 0.7);
+///
+/// // ... but a doubled rate makes it closer
+/// // ……だがレートが倍になると差は縮まる
+/// let doubled = model.estimate(Scores::from_ia_score(25).unwrap(), Rate::X2, Season::Iat1, ASide);
+/// assert!(doubled.win_probability(IASide) < ahead.win_probability(IASide));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarkovModel {
+    /// Pairs of (points gained at `Rate::X1` over a season by the player who moves first in it, probability). The probabilities should sum up to 1.
+    /// ／（季節の先手が `Rate::X1` でその季節に得る点, 確率）の組。確率の和は1であるべき。
+    pub season_outcomes: Vec<(i32, f64)>,
+    pub who_goes_first: FirstMover,
+}
+
+/// A rough guess, in which neither player is favored. Estimate one from your own games if you can.
+/// ／大まかな推測。どちらの側も有利としない。できれば自分の対局から推定すること。
+impl Default for MarkovModel {
+    fn default() -> Self {
+        let hand_values = [
+            (3, 0.25),
+            (5, 0.35),
+            (7, 0.2),
+            (9, 0.05),
+            (10, 0.1),
+            (12, 0.05),
+        ];
+        Self {
+            season_outcomes: hand_values
+                .into_iter()
+                .flat_map(|(value, probability)| {
+                    [(value, probability / 2.0), (-value, probability / 2.0)]
+                })
+                .collect(),
+            who_goes_first: FirstMover::Random,
+        }
+    }
+}
+
+impl MarkovModel {
+    /// Estimates `season_outcomes` as the frequencies of the seasons' outcomes in `records`, which all started from `Setup::yhuap_initial`. The points that a season moved are divided by the rate it ended at (and rounded), so as to be at `Rate::X1`. `who_goes_first` is taken from the first record.
+    /// ／`Setup::yhuap_initial` から始まった `records` における季節の結果の頻度として `season_outcomes` を推定する。季節で動いた点は、`Rate::X1` におけるものとなるよう、その季節が終わったときのレートで割る（そして丸める）。`who_goes_first` は最初の記録から取る。
+    ///
+    /// # Errors
+    /// Fails if `records` contains no season, or if a record is inconsistent.
+    pub fn from_records<T: CetkaikRepresentation + Clone>(
+        records: &[GameRecord<T::AbsoluteCoord>],
+    ) -> Result<Self, &'static str> {
+        let mut outcomes: Vec<i32> = vec![];
+        for record in records {
+            // (season, its first mover, IA side's score when it started, the latest rate in it)
+            // （季節, その先手, それが始まったときの IA 側の得点, その中での最新のレート）
+            let mut current: Option<(Season, AbsoluteSide, i32, Rate)> = None;
+
+            // A tymok doubles what the season moves afterwards, so `rate` divides `delta_of_ia` unless penalties were paid at a lower rate
+            // 再行はその後に季節が動かす点を倍にするので、低いレートで罰則点を払っていない限り `rate` は `delta_of_ia` を割り切る
+            // The quotient is at most 40 in absolute value
+            // 商の絶対値は40以下
+            #[allow(clippy::cast_possible_truncation)]
+            let gained = |first_mover, delta_of_ia: i32, rate: Rate| {
+                let at_x1 = (f64::from(delta_of_ia) / f64::from(rate.num())).round() as i32;
+                match first_mover {
+                    AbsoluteSide::IASide => at_x1,
+                    AbsoluteSide::ASide => -at_x1,
+                }
+            };
+            super::player::replay(
+                record,
+                &super::setup::Setup::yhuap_initial(),
+                |state: &state::GroundState_<T>, _| {
+                    match &mut current {
+                        Some((season, _, _, rate)) if *season == state.season => {
+                            *rate = state.rate;
+                            return;
+                        }
+                        Some((_, first_mover, ia_score, rate)) => {
+                            outcomes.push(gained(
+                                *first_mover,
+                                state.scores.ia() - *ia_score,
+                                *rate,
+                            ));
+                        }
+                        None => {}
+                    }
+                    current = Some((
+                        state.season,
+                        state.season_first_mover,
+                        state.scores.ia(),
+                        state.rate,
+                    ));
+                },
+            )?;
+            if let Some((_, first_mover, ia_score, rate)) = current {
+                outcomes.push(gained(first_mover, record.final_ia_score - ia_score, rate));
+            }
+        }
+        if outcomes.is_empty() {
+            return Err("no season was played in the records");
+        }
+
+        outcomes.sort_unstable();
+        let total = f64::from(u32::try_from(outcomes.len()).unwrap_or(u32::MAX));
+        let mut season_outcomes: Vec<(i32, f64)> = vec![];
+        for value in outcomes {
+            match season_outcomes.last_mut() {
+                Some((last, probability)) if *last == value => *probability += 1.0 / total,
+                _ => season_outcomes.push((value, 1.0 / total)),
+            }
+        }
+        Ok(Self {
+            season_outcomes,
+            who_goes_first: records[0].config.who_goes_first,
+        })
+    }
+
+    /// The estimate when `season` is being played at `rate` with `whose_turn` to move, who is treated as the first mover of the rest of the season.
+    /// ／`season` が `rate` で行われ、`whose_turn` が手番であるときの見積もり。`whose_turn` は季節の残りの先手として扱われる。
+    #[must_use]
+    pub fn estimate(
+        &self,
+        scores: Scores,
+        rate: Rate,
+        season: Season,
+        whose_turn: AbsoluteSide,
+    ) -> MatchEstimate {
+        self.during_season(scores.ia(), rate, season, whose_turn)
+    }
+
+    /// The estimate when `season` is about to start with `first_mover` moving first (`None` if it is decided at random). `None` for `season` means that all the seasons have ended.
+    /// ／`season` が `first_mover` を先手として（無作為に決まるならば `None`）始まろうとしているときの見積もり。`season` が `None` ならば全ての季節が終わっている。
+    #[must_use]
+    pub fn estimate_at_season_start(
+        &self,
+        scores: Scores,
+        season: Option<Season>,
+        first_mover: Option<AbsoluteSide>,
+    ) -> MatchEstimate {
+        self.season_start(scores.ia(), season, first_mover)
+    }
+
+    fn season_start(
+        &self,
+        ia_score: i32,
+        season: Option<Season>,
+        first_mover: Option<AbsoluteSide>,
+    ) -> MatchEstimate {
+        let Some(season) = season.filter(|_| 0 < ia_score && ia_score < 40) else {
+            return MatchEstimate::game_over(ia_score);
+        };
+        match first_mover {
+            Some(first_mover) => self.during_season(ia_score, Rate::X1, season, first_mover),
+            None => MatchEstimate::ZERO
+                .add_scaled(
+                    self.during_season(ia_score, Rate::X1, season, AbsoluteSide::IASide),
+                    0.5,
+                )
+                .add_scaled(
+                    self.during_season(ia_score, Rate::X1, season, AbsoluteSide::ASide),
+                    0.5,
+                ),
+        }
+    }
+
+    fn during_season(
+        &self,
+        ia_score: i32,
+        rate: Rate,
+        season: Season,
+        first_mover: AbsoluteSide,
+    ) -> MatchEstimate {
+        self.season_outcomes
+            .iter()
+            .fold(MatchEstimate::ZERO, |acc, &(value, probability)| {
+                let delta_of_ia = match first_mover {
+                    AbsoluteSide::IASide => value,
+                    AbsoluteSide::ASide => -value,
+                } * rate.num();
+                let winner = match delta_of_ia.cmp(&0) {
+                    std::cmp::Ordering::Greater => Some(AbsoluteSide::IASide),
+                    std::cmp::Ordering::Less => Some(AbsoluteSide::ASide),
+                    std::cmp::Ordering::Equal => None,
+                };
+                acc.add_scaled(
+                    self.season_start(
+                        (ia_score + delta_of_ia).clamp(0, 40),
+                        season.next(),
+                        self.who_goes_first.decide(winner, first_mover),
+                    ),
+                    probability,
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{player, Config};
+    use cetkaik_fundamental::AbsoluteSide::{ASide, IASide};
+
+    #[test]
+    fn test_win_probability() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        let model = |season_outcomes, who_goes_first| MarkovModel {
+            season_outcomes,
+            who_goes_first,
+        };
+
+        // A knockout ends the game at once, and the score does not go beyond 40
+        // 点が尽きればただちにゲームが終わり、得点は40を超えない
+        let knockout = model(vec![(5, 1.0)], FirstMover::Random).estimate(
+            Scores::from_ia_score(38).unwrap(),
+            Rate::X1,
+            Season::Iei2,
+            IASide,
+        );
+        assert!((knockout.ia_wins - 1.0).abs() < 1e-9);
+        assert!((knockout.expected_final_ia_score - 40.0).abs() < 1e-9);
+
+        // Equal scores after winter are a draw
+        // 冬の後に得点が等しければ引き分け
+        let draw = model(vec![(0, 1.0)], FirstMover::Random).estimate(
+            Scores::new(),
+            Rate::X4,
+            Season::Iat1,
+            ASide,
+        );
+        assert!((draw.draw - 1.0).abs() < 1e-9);
+
+        // If the first mover always gains 10 points, who moves first decides everything
+        // 先手が常に10点を得るならば、誰が先手かが全てを決める
+        let first_mover_gains = |who_goes_first| {
+            model(vec![(10, 1.0)], who_goes_first).estimate(
+                Scores::new(),
+                Rate::X1,
+                Season::Iei2,
+                IASide,
+            )
+        };
+        let alternating = first_mover_gains(FirstMover::LoserOfPreviousSeason);
+        assert!((alternating.draw - 1.0).abs() < 1e-9);
+        let snowballing = first_mover_gains(FirstMover::WinnerOfPreviousSeason);
+        assert!((snowballing.ia_wins - 1.0).abs() < 1e-9);
+
+        // Estimated from records
+        // 記録からの推定
+        let config = Config::cerke_online_alpha().with_who_goes_first(FirstMover::Alternate);
+        let records: Vec<_> = (0..3)
+            .map(|seed| {
+                player::play_game::<CetkaikNaive>(
+                    &mut player::RandomPlayer,
+                    &mut player::RandomPlayer,
+                    config,
+                    seed,
+                )
+                .0
+            })
+            .collect();
+        let estimated = MarkovModel::from_records::<CetkaikNaive>(&records).unwrap();
+        assert_eq!(estimated.who_goes_first, FirstMover::Alternate);
+        let total: f64 = estimated.season_outcomes.iter().map(|(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(MarkovModel::from_records::<CetkaikNaive>(&[]).is_err());
+        let estimate = estimated.estimate(Scores::new(), Rate::X1, Season::Iei2, ASide);
+        assert!((estimate.ia_wins + estimate.a_wins + estimate.draw - 1.0).abs() < 1e-9);
+    }
+    #[test]
+    fn test_outcomes_from_records_are_at_x1() {
+        use crate::message::{AfterHalfAcceptance_, PureMove__, TymokOrTaxot};
+        use crate::player::Player;
+        use cetkaik_naive_representation::{absolute::Coord, CetkaikNaive};
+        use rand::rngs::StdRng;
+
+        // Continues the season after the first hand and ends it after the second, so that it ends at `Rate::X2`
+        // 最初の役の後は季節を続け、二つ目の役の後で終えるので、季節は `Rate::X2` で終わる
+        struct TymokOnce;
+        impl Player<CetkaikNaive> for TymokOnce {
+            fn play_move(
+                &mut self,
+                state: &state::GroundState_<CetkaikNaive>,
+                config: Config,
+                candidates: &[PureMove__<Coord>],
+                rng: &mut StdRng,
+            ) -> PureMove__<Coord> {
+                player::HandGreedyPlayer.play_move(state, config, candidates, rng)
+            }
+
+            fn after_half_acceptance(
+                &mut self,
+                state: &state::ExcitedState_<CetkaikNaive>,
+                config: Config,
+                candidates: &[AfterHalfAcceptance_<Coord>],
+                rng: &mut StdRng,
+            ) -> AfterHalfAcceptance_<Coord> {
+                player::HandGreedyPlayer.after_half_acceptance(state, config, candidates, rng)
+            }
+
+            fn tymok_or_taxot(
+                &mut self,
+                if_tymok: &state::GroundState_<CetkaikNaive>,
+                _: &crate::IfTaxot_<CetkaikNaive>,
+                _: Config,
+                _: &mut StdRng,
+            ) -> TymokOrTaxot {
+                if if_tymok.rate() == Rate::X2 {
+                    TymokOrTaxot::Tymok
+                } else {
+                    TymokOrTaxot::Taxot
+                }
+            }
+        }
+
+        // The first game whose spring ends at `Rate::X2` and which goes on to summer
+        // 春が `Rate::X2` で終わり、夏へと続く最初の対局
+        let (mut record, spring, later) = (0..)
+            .find_map(|seed| {
+                let (record, _) = player::play_game::<CetkaikNaive>(
+                    &mut TymokOnce,
+                    &mut TymokOnce,
+                    Config::cerke_online_alpha(),
+                    seed,
+                );
+                let (spring, later): (Vec<_>, Vec<_>) =
+                    crate::fixtures::positions::<CetkaikNaive>(&record)
+                        .into_iter()
+                        .partition(|state| state.season() == Season::Iei2);
+                (spring.last()?.rate() == Rate::X2 && !later.is_empty())
+                    .then_some((record, spring, later))
+            })
+            .unwrap();
+
+        // Only spring, which moved twice the points it would have at `Rate::X1`
+        // 春のみ。`Rate::X1` で動くはずの点の二倍が動いた
+        let summer_starts = (0..record.events.len())
+            .filter(|&i| matches!(record.events[i], player::Event::SeasonStarts { .. }))
+            .nth(1)
+            .unwrap();
+        record.events.truncate(summer_starts);
+        record.final_ia_score = later[0].scores().ia();
+        let gained = match spring[0].season_first_mover() {
+            IASide => record.final_ia_score - 20,
+            ASide => 20 - record.final_ia_score,
+        };
+        assert_ne!(gained, 0);
+        assert_eq!(gained % 2, 0);
+        assert_eq!(
+            MarkovModel::from_records::<CetkaikNaive>(&[record])
+                .unwrap()
+                .season_outcomes,
+            vec![(gained / 2, 1.0)]
+        );
+    }
+}
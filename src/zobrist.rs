@@ -0,0 +1,406 @@
+use super::state::{ExcitedStateWithoutCiurl_, GroundState_, HandNotResolved_};
+use super::{Rate, Scores, Season};
+use cetkaik_fundamental::{AbsoluteSide, ColorAndProf};
+use cetkaik_traits::{CetkaikRepresentation, IsAbsoluteField, IsBoard, IsPieceWithSide};
+use std::hash::{Hash, Hasher};
+
+struct Keys {
+    /// Indexed by the square (see `square_index`) and by the kind of the piece (see `piece_index`).
+    /// ／マス（`square_index` を参照）と駒の種類（`piece_index` を参照）で添字付けられる。
+    pieces: [[u64; 41]; 81],
+
+    /// Indexed by the side, by the kind of the piece (see `kind_index`) and by how many pieces of the same kind precede it, so that a multiset gets the same hash whatever the order of the pieces.
+    /// ／側、駒の種類（`kind_index` を参照）、同じ種類の駒がそれより前にいくつあるかで添字付けられる。これにより、多重集合は駒の順番によらず同じハッシュ値を得る。
+    hop1zuo1: [[[u64; 16]; 20]; 2],
+    a_side_to_move: u64,
+    seasons: [u64; 4],
+    ia_scores: [u64; 41],
+    rates: [u64; 7],
+    tam_has_moved_previously: u64,
+}
+
+/// <https://prng.di.unimi.it/splitmix64.c>
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+/// Scrambles `value`, so that values that differ slightly get unrelated hashes.
+/// ／`value` をかき混ぜる。わずかに異なる値が無関係なハッシュ値を得るようにするため。
+pub(crate) const fn mix(value: u64) -> u64 {
+    splitmix64(value).1
+}
+
+impl Keys {
+    /// The keys are fixed at compile time, so that a hash means the same thing across runs and across machines.
+    /// ／鍵はコンパイル時に固定されるので、ハッシュ値は実行ごと・機械ごとに変わらない。
+    #[allow(clippy::large_stack_arrays)] // only ever evaluated at compile time
+    const fn generate() -> Self {
+        let mut keys = Self {
+            pieces: [[0; 41]; 81],
+            hop1zuo1: [[[0; 16]; 20]; 2],
+            a_side_to_move: 0,
+            seasons: [0; 4],
+            ia_scores: [0; 41],
+            rates: [0; 7],
+            tam_has_moved_previously: 0,
+        };
+        let mut state = 0x6365_726b_6169_6b00;
+        let mut key;
+
+        let mut i = 0;
+        while i < 81 {
+            let mut j = 0;
+            while j < 41 {
+                (state, key) = splitmix64(state);
+                keys.pieces[i][j] = key;
+                j += 1;
+            }
+            i += 1;
+        }
+
+        let mut side = 0;
+        while side < 2 {
+            let mut kind = 0;
+            while kind < 20 {
+                let mut nth = 0;
+                while nth < 16 {
+                    (state, key) = splitmix64(state);
+                    keys.hop1zuo1[side][kind][nth] = key;
+                    nth += 1;
+                }
+                kind += 1;
+            }
+            side += 1;
+        }
+
+        (state, keys.a_side_to_move) = splitmix64(state);
+        let mut i = 0;
+        while i < 4 {
+            (state, keys.seasons[i]) = splitmix64(state);
+            i += 1;
+        }
+        let mut i = 0;
+        while i < 41 {
+            (state, keys.ia_scores[i]) = splitmix64(state);
+            i += 1;
+        }
+        let mut i = 0;
+        while i < 7 {
+            (state, keys.rates[i]) = splitmix64(state);
+            i += 1;
+        }
+        (_, keys.tam_has_moved_previously) = splitmix64(state);
+        keys
+    }
+}
+
+static KEYS: Keys = Keys::generate();
+
+/// Tam2 is 0; the other pieces are numbered from 1 to 40.
+/// ／皇は0、それ以外の駒は1から40。
+fn piece_index<T: CetkaikRepresentation>(piece: T::AbsolutePiece) -> usize {
+    piece.match_on_piece_and_apply(&|| 0, &|color, prof, side| {
+        1 + kind_index(ColorAndProf { color, prof }) * 2 + side_index(side)
+    })
+}
+
+const fn kind_index(piece: ColorAndProf) -> usize {
+    piece.color as usize * 10 + piece.prof as usize
+}
+
+const fn side_index(side: AbsoluteSide) -> usize {
+    match side {
+        AbsoluteSide::IASide => 0,
+        AbsoluteSide::ASide => 1,
+    }
+}
+
+/// The key for `piece` standing on `coord`.
+/// ／`coord` に立つ `piece` の鍵。
+#[must_use]
+pub fn piece<T: CetkaikRepresentation>(coord: T::AbsoluteCoord, piece: T::AbsolutePiece) -> u64 {
+    KEYS.pieces[super::square_index::<T>(coord)][piece_index::<T>(piece)]
+}
+
+/// The key for `side` holding a `piece` in hop1zuo1 after `nth` other pieces of the same kind. A multiset with `n` copies of a kind contributes the keys for `nth` from 0 to `n - 1`.
+/// ／`side` が同じ種類の駒を `nth` 個持った上でさらに `piece` を手駒に持つことの鍵。ある種類の駒を `n` 個含む多重集合は、`nth` が0から `n - 1` までの鍵を寄与する。
+#[must_use]
+pub const fn hop1zuo1(side: AbsoluteSide, piece: ColorAndProf, nth: usize) -> u64 {
+    KEYS.hop1zuo1[side_index(side)][kind_index(piece)][nth]
+}
+
+/// 0 for IA side, so that the key of the side to move can be toggled by XOR-ing `side_to_move(IASide) ^ side_to_move(ASide)`.
+/// ／IA 側は0。したがって `side_to_move(IASide) ^ side_to_move(ASide)` を XOR することで手番の鍵を切り替えられる。
+#[must_use]
+pub const fn side_to_move(side: AbsoluteSide) -> u64 {
+    match side {
+        AbsoluteSide::IASide => 0,
+        AbsoluteSide::ASide => KEYS.a_side_to_move,
+    }
+}
+
+#[must_use]
+pub const fn season(season: Season) -> u64 {
+    KEYS.seasons[season as usize]
+}
+
+#[must_use]
+pub const fn scores(scores: Scores) -> u64 {
+    KEYS.ia_scores[scores.ia().unsigned_abs() as usize]
+}
+
+#[must_use]
+pub const fn rate(rate: Rate) -> u64 {
+    KEYS.rates[rate as usize]
+}
+
+#[must_use]
+pub const fn tam_has_moved_previously(tam_has_moved_previously: bool) -> u64 {
+    if tam_has_moved_previously {
+        KEYS.tam_has_moved_previously
+    } else {
+        0
+    }
+}
+
+/// How many pieces of each kind `side` holds in hop1zuo1.
+/// ／`side` が手駒に各種類の駒をいくつ持っているか。
+fn hop1zuo1_counts<T: CetkaikRepresentation>(
+    f: &T::AbsoluteField,
+    side: AbsoluteSide,
+) -> [usize; 20] {
+    let mut counts = [0; 20];
+    for piece in f.hop1zuo1_of(side) {
+        counts[kind_index(piece)] += 1;
+    }
+    counts
+}
+
+/// Computes from scratch the hash of the board and of both hop1zuo1.
+/// ／盤面と両者の手駒のハッシュ値を一から計算する。
+///
+/// # Panics
+/// Panics if a hop1zuo1 contains more than 16 pieces of the same kind.
+#[must_use]
+pub fn hash_of_field<T: CetkaikRepresentation>(f: &T::AbsoluteField) -> u64 {
+    let mut hash = 0;
+    for coord in super::occupied_squares::<T>(f) {
+        let p = T::as_board_absolute(f)
+            .peek(coord)
+            .expect("`occupied_squares` must only return occupied squares");
+        hash ^= piece::<T>(coord, p);
+    }
+    for side in [AbsoluteSide::IASide, AbsoluteSide::ASide] {
+        let mut seen = [0; 20];
+        for p in f.hop1zuo1_of(side) {
+            hash ^= hop1zuo1(side, p, seen[kind_index(p)]);
+            seen[kind_index(p)] += 1;
+        }
+    }
+    hash
+}
+
+/// Computes from scratch the hash that `HandNotResolved_` and `ExcitedStateWithoutCiurl_` carry. It covers the board, both hop1zuo1 (as multisets), whose turn it is, the season, the scores and the rate.
+/// ／`HandNotResolved_` と `ExcitedStateWithoutCiurl_` が持つハッシュ値を一から計算する。盤面・両者の手駒（多重集合として）・手番・季節・得点・レートを含む。
+#[must_use]
+pub fn hash_of_position<T: CetkaikRepresentation>(
+    f: &T::AbsoluteField,
+    whose_turn: AbsoluteSide,
+    season: Season,
+    scores: Scores,
+    rate: Rate,
+) -> u64 {
+    hash_of_field::<T>(f)
+        ^ side_to_move(whose_turn)
+        ^ self::season(season)
+        ^ self::scores(scores)
+        ^ self::rate(rate)
+}
+
+/// Computes `state.zobrist_hash()` from scratch: `hash_of_position` together with `tam_has_moved_previously`. `GroundState_::new` and deserialization call this, and the transition functions keep the hash up to date incrementally.
+/// ／`state.zobrist_hash()` を一から計算する。`hash_of_position` に `tam_has_moved_previously` を加えたもの。`GroundState_::new` とデシリアライズはこれを呼び、状態遷移関数はハッシュ値を差分更新し続ける。
+///
+/// ```
+/// use cetkaik_full_state_transition::probabilistic::Probabilistic;
+/// use cetkaik_full_state_transition::*;
+/// use cetkaik_naive_representation::CetkaikNaive;
+///
+/// let Probabilistic::WhoGoesFirst { ia_first, a_first } = initial_state::<CetkaikNaive>() else {
+///     unreachable!()
+/// };
+/// assert_eq!(ia_first.zobrist_hash(), zobrist::hash_of_ground_state(&ia_first));
+/// assert_ne!(ia_first, a_first);
+///
+/// // Passing twice comes back to the same position, so the two states are equal as keys
+/// // 二回パスすると同じ局面に戻るので、二つの状態はキーとして等しい
+/// let config = Config::cerke_online_alpha().with_pass(true, false);
+/// let pass = |state| match apply_pass(state, message::Pass, config).unwrap() {
+///     state::HandResolved_::NeitherTymokNorTaxot(next) => next,
+///     _ => unreachable!(),
+/// };
+/// let back = pass(&pass(&ia_first));
+/// assert_eq!(back.moves_in_this_season(), 2);
+/// assert_eq!(back, ia_first);
+///
+/// let mut visited = std::collections::HashSet::new();
+/// visited.insert(ia_first);
+/// assert!(visited.contains(&back));
+/// ```
+#[must_use]
+pub fn hash_of_ground_state<T: CetkaikRepresentation>(state: &GroundState_<T>) -> u64 {
+    hash_of_position::<T>(
+        &state.f,
+        state.whose_turn,
+        state.season,
+        state.scores,
+        state.rate,
+    ) ^ tam_has_moved_previously(state.tam_has_moved_previously)
+}
+
+/// Computes `state.zobrist_hash()` from scratch.
+/// ／`state.zobrist_hash()` を一から計算する。
+#[must_use]
+pub fn hash_of_hand_not_resolved<T: CetkaikRepresentation>(state: &HandNotResolved_<T>) -> u64 {
+    hash_of_position::<T>(
+        &state.f,
+        state.whose_turn,
+        state.season,
+        state.scores,
+        state.rate,
+    )
+}
+
+/// Computes `state.zobrist_hash()` from scratch.
+/// ／`state.zobrist_hash()` を一から計算する。
+#[must_use]
+pub fn hash_of_excited_state<T: CetkaikRepresentation>(
+    state: &ExcitedStateWithoutCiurl_<T>,
+) -> u64 {
+    hash_of_position::<T>(
+        &state.f,
+        state.whose_turn,
+        state.season,
+        state.scores,
+        state.rate,
+    )
+}
+
+/// Whether the two fields have the same pieces on the same squares and the same hop1zuo1 as multisets.
+/// ／二つの盤面が同じマスに同じ駒を持ち、手駒が多重集合として等しいかどうか。
+fn same_field<T: CetkaikRepresentation>(a: &T::AbsoluteField, b: &T::AbsoluteField) -> bool {
+    let squares = super::occupied_squares::<T>(a);
+    squares.len() == super::occupied_squares::<T>(b).len()
+        && squares
+            .into_iter()
+            .all(|coord| T::as_board_absolute(a).peek(coord) == T::as_board_absolute(b).peek(coord))
+        && [AbsoluteSide::IASide, AbsoluteSide::ASide]
+            .into_iter()
+            .all(|side| hop1zuo1_counts::<T>(a, side) == hop1zuo1_counts::<T>(b, side))
+}
+
+/// Two states are equal when they agree on everything that `zobrist_hash()` covers. The history of the season (`season_first_mover`, `moves_in_this_season` and `positions_in_this_season`) is ignored.
+/// ／`zobrist_hash()` が含むもの全てが一致するとき、二つの状態は等しい。季節の履歴（`season_first_mover`、`moves_in_this_season`、`positions_in_this_season`）は無視する。
+impl<T: CetkaikRepresentation> PartialEq for GroundState_<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.zobrist_hash == other.zobrist_hash
+            && self.whose_turn == other.whose_turn
+            && self.season == other.season
+            && self.scores == other.scores
+            && self.rate == other.rate
+            && self.tam_has_moved_previously == other.tam_has_moved_previously
+            && same_field::<T>(&self.f, &other.f)
+    }
+}
+
+impl<T: CetkaikRepresentation> Eq for GroundState_<T> {}
+
+impl<T: CetkaikRepresentation> Hash for GroundState_<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.zobrist_hash.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{message, player, repetition, state, Config, IfTaxot_};
+
+    #[test]
+    fn test_zobrist_hash_is_maintained_incrementally() {
+        use cetkaik_naive_representation::CetkaikNaive;
+        use player::Player;
+        use rand::rngs::StdRng;
+
+        /// Plays at random, checking every state it is shown
+        struct Checker;
+        impl Player<CetkaikNaive> for Checker {
+            fn play_move(
+                &mut self,
+                state: &state::GroundState_<CetkaikNaive>,
+                config: Config,
+                candidates: &[message::PureMove__<cetkaik_naive_representation::absolute::Coord>],
+                rng: &mut StdRng,
+            ) -> message::PureMove__<cetkaik_naive_representation::absolute::Coord> {
+                assert_eq!(state.zobrist_hash, hash_of_ground_state(state));
+
+                // The hash is not serialized, but is calculated again when deserialized
+                // ハッシュ値はシリアライズされず、デシリアライズ時に再び計算される
+                let mut json = serde_json::to_value(state).unwrap();
+                assert!(json.get("zobrist_hash").is_none());
+                json["zobrist_hash"] = 12345.into();
+                let deserialized: state::GroundState_<CetkaikNaive> =
+                    serde_json::from_value(json).unwrap();
+                assert_eq!(deserialized.zobrist_hash(), state.zobrist_hash());
+                assert_eq!(&deserialized, state);
+
+                assert_eq!(
+                    state.positions_in_this_season.last(),
+                    Some(repetition::position_key::<CetkaikNaive>(
+                        &state.f,
+                        state.whose_turn,
+                        state.tam_has_moved_previously
+                    ))
+                );
+                player::RandomPlayer.play_move(state, config, candidates, rng)
+            }
+
+            fn after_half_acceptance(
+                &mut self,
+                state: &state::ExcitedState_<CetkaikNaive>,
+                config: Config,
+                candidates: &[message::AfterHalfAcceptance_<
+                    cetkaik_naive_representation::absolute::Coord,
+                >],
+                rng: &mut StdRng,
+            ) -> message::AfterHalfAcceptance_<cetkaik_naive_representation::absolute::Coord>
+            {
+                assert_eq!(state.c.zobrist_hash, hash_of_excited_state(&state.c));
+                let deserialized: state::ExcitedStateWithoutCiurl_<CetkaikNaive> =
+                    serde_json::from_value(serde_json::to_value(&state.c).unwrap()).unwrap();
+                assert_eq!(deserialized.zobrist_hash(), state.c.zobrist_hash());
+                player::RandomPlayer.after_half_acceptance(state, config, candidates, rng)
+            }
+
+            fn tymok_or_taxot(
+                &mut self,
+                if_tymok: &state::GroundState_<CetkaikNaive>,
+                if_taxot: &IfTaxot_<CetkaikNaive>,
+                config: Config,
+                rng: &mut StdRng,
+            ) -> message::TymokOrTaxot {
+                assert_eq!(if_tymok.zobrist_hash, hash_of_ground_state(if_tymok));
+                player::RandomPlayer.tymok_or_taxot(if_tymok, if_taxot, config, rng)
+            }
+        }
+
+        for config in [Config::cerke_online_alpha(), Config::strict_y1_huap1()] {
+            for seed in 0..2 {
+                player::play_game::<CetkaikNaive>(&mut Checker, &mut Checker, config, seed);
+            }
+        }
+    }
+}